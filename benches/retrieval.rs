@@ -0,0 +1,131 @@
+//! 检索路径基准测试
+//!
+//! 运行: `cargo bench --bench retrieval`
+//!
+//! - brute_force_recall: C++ 暴力搜索 (recommend_recall)
+//! - hnsw_search: 不同 ef_search 下的 HNSW 查询
+//! - encode: 单条编码 vs 批量编码 (需要 models/ 下的模型文件，缺失时跳过)
+//! - recommend_pipeline: 召回 -> Bloom 过滤 -> 排序 的完整流程
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use mini_recsys::ann::HnswIndex;
+use mini_recsys::embedding::EmbeddingModel;
+use mini_recsys::ffi::{
+    add_item_to_hnsw, destroy_hnsw_index, hnsw_search, init_hnsw_index, recommend_recall, set_hnsw_ef, HnswConfig,
+};
+use mini_recsys::model::{demo_rng, generate_category_embedding, generate_user_embedding, Item, User, CATEGORIES, DIM};
+use mini_recsys::service::{self, RankWeights, RecallPolicy, MAX_RECOMMENDATIONS, RECALL_K};
+use mini_recsys::storage::Storage;
+use std::collections::HashMap;
+
+const CATALOG_SIZES: [usize; 3] = [1_000, 5_000, 20_000];
+const EF_VALUES: [usize; 3] = [100, 200, 400];
+/// 固定种子，保证每次运行的物品库与查询向量一致
+const SEED: u64 = 42;
+
+fn make_catalog(n: usize) -> Vec<Item> {
+    let mut rng = demo_rng(Some(SEED));
+    (0..n)
+        .map(|i| {
+            let category = CATEGORIES[i % CATEGORIES.len()];
+            Item {
+                id: i as u64 + 1,
+                name: format!("{} item {}", category, i),
+                category: category.to_string(),
+                image_url: String::new(),
+                price: 0.0,
+                embedding: generate_category_embedding(category, &mut rng),
+                popularity: (i % 100) as f32 / 100.0,
+                titles: Default::default(),
+            }
+        })
+        .collect()
+}
+
+/// 用给定物品重建全局 HNSW 索引
+fn build_hnsw(items: &[Item]) {
+    destroy_hnsw_index();
+    let config = HnswConfig { dim: DIM, max_elements: items.len(), ..HnswConfig::default() };
+    init_hnsw_index(&config).expect("init hnsw");
+    for item in items {
+        add_item_to_hnsw(item.id, &item.embedding).expect("add item");
+    }
+}
+
+fn bench_brute_force(c: &mut Criterion) {
+    let mut group = c.benchmark_group("brute_force_recall");
+    let query = generate_user_embedding(&["Electronics", "Books"], &mut demo_rng(Some(SEED)));
+    for &n in &CATALOG_SIZES {
+        let items = make_catalog(n);
+        group.bench_with_input(BenchmarkId::from_parameter(n), &items, |b, items| {
+            b.iter(|| recommend_recall(black_box(&query), items, RECALL_K))
+        });
+    }
+    group.finish();
+}
+
+fn bench_hnsw(c: &mut Criterion) {
+    let mut group = c.benchmark_group("hnsw_search");
+    let query = generate_user_embedding(&["Electronics", "Books"], &mut demo_rng(Some(SEED)));
+    for &n in &CATALOG_SIZES {
+        build_hnsw(&make_catalog(n));
+        for &ef in &EF_VALUES {
+            set_hnsw_ef(ef);
+            group.bench_with_input(BenchmarkId::new(format!("ef{}", ef), n), &n, |b, _| {
+                b.iter(|| hnsw_search(black_box(&query), RECALL_K))
+            });
+        }
+    }
+    destroy_hnsw_index();
+    group.finish();
+}
+
+fn bench_encode(c: &mut Criterion) {
+    let model = match EmbeddingModel::new() {
+        Ok(model) => model,
+        Err(e) => {
+            eprintln!("⚠️  Skipping encode benchmarks: {}", e);
+            return;
+        }
+    };
+    let titles: Vec<String> = (0..32).map(|i| format!("Wireless noise cancelling headphones model {}", i)).collect();
+    let refs: Vec<&str> = titles.iter().map(String::as_str).collect();
+
+    let mut group = c.benchmark_group("encode");
+    group.sample_size(10);
+    group.bench_function("single_x32", |b| {
+        b.iter(|| {
+            for title in &refs {
+                model.encode(black_box(title)).expect("encode");
+            }
+        })
+    });
+    group.bench_function("batch_x32", |b| {
+        b.iter(|| model.encode_batch(black_box(&refs)).expect("encode_batch"))
+    });
+    group.finish();
+}
+
+fn bench_pipeline(c: &mut Criterion) {
+    let dir = std::env::temp_dir().join(format!("mini-recsys-bench-{}", std::process::id()));
+    let storage = Storage::new(dir.to_str().expect("utf-8 temp dir")).expect("open storage");
+    let user = User { id: 1, name: "bench".into(), embedding: generate_user_embedding(&["Home"], &mut demo_rng(Some(SEED))) };
+
+    let mut group = c.benchmark_group("recommend_pipeline");
+    for &n in &CATALOG_SIZES {
+        let items = make_catalog(n);
+        let item_map: HashMap<u64, usize> = items.iter().enumerate().map(|(i, item)| (item.id, i)).collect();
+        build_hnsw(&items);
+        group.bench_with_input(BenchmarkId::from_parameter(n), &items, |b, items| {
+            b.iter(|| service::recommend(&storage, &HnswIndex::default(), black_box(&user), items, &item_map, RankWeights::default(), MAX_RECOMMENDATIONS, RecallPolicy::default(), Vec::new()).expect("recommend"))
+        });
+    }
+    destroy_hnsw_index();
+    group.finish();
+
+    drop(storage);
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+criterion_group!(benches, bench_brute_force, bench_hnsw, bench_encode, bench_pipeline);
+criterion_main!(benches);
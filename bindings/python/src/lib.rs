@@ -0,0 +1,160 @@
+//! mini-recsys 的 Python 绑定 - 与线上服务走同一套 RecSys 门面
+//!
+//! ```python
+//! import mini_recsys
+//!
+//! rs = mini_recsys.RecSys(data_dir="/srv/recsys")
+//! rs.recommend(1, sim=0.9, popularity=0.1)
+//! rs.search("wireless headphones", fusion="rrf")
+//! rs.evaluate(k=10)
+//! ```
+//!
+//! HNSW 索引是进程级单例，一个 Python 进程内只应创建一个 `RecSys`。
+//! 耗时的调用会释放 GIL。
+
+use recsys_core::catalog::Catalog;
+use recsys_core::config::{Config, PathOverrides};
+use recsys_core::eval::EvalOptions;
+use recsys_core::hybrid::FusionStrategy;
+use recsys_core::model::ItemJson;
+use recsys_core::recsys::{RecSys, RecommendOptions, SearchOptions};
+use recsys_core::service::RankWeights;
+use pyo3::exceptions::{PyKeyError, PyRuntimeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+fn runtime_error(e: anyhow::Error) -> PyErr {
+    PyRuntimeError::new_err(format!("{:#}", e))
+}
+
+/// 推荐系统实例 (对应 Rust 的 `mini_recsys::recsys::RecSys`)
+#[pyclass(name = "RecSys", module = "mini_recsys")]
+struct PyRecSys {
+    inner: RecSys,
+}
+
+#[pymethods]
+impl PyRecSys {
+    /// 按配置打开数据库与索引；`config` 为配置文件路径 (默认读取当前目录下的 config.toml)，
+    /// `data_dir` 同命令行的 `--data-dir`
+    #[new]
+    #[pyo3(signature = (config = None, data_dir = None, products = None))]
+    fn new(py: Python<'_>, config: Option<String>, data_dir: Option<String>, products: Option<String>) -> PyResult<Self> {
+        let overrides = PathOverrides { data_dir, products, ..PathOverrides::default() };
+        let inner = py.allow_threads(|| {
+            let config = Config::load_from(config.as_deref(), &overrides)?;
+            RecSys::open(config)
+        }).map_err(runtime_error)?;
+        Ok(Self { inner })
+    }
+
+    /// 为用户推荐物品；同时给出 `sim` 与 `popularity` 时用这组排序权重 (只给一个时另一个取配置值)，
+    /// 否则与线上一样按 A/B 实验分桶
+    #[pyo3(signature = (uid, sim = None, popularity = None))]
+    fn recommend<'py>(&self, py: Python<'py>, uid: u64, sim: Option<f32>, popularity: Option<f32>) -> PyResult<Vec<Bound<'py, PyDict>>> {
+        let weights = (sim.is_some() || popularity.is_some()).then(|| {
+            let base = self.inner.config().ranking;
+            RankWeights { sim: sim.unwrap_or(base.sim), popularity: popularity.unwrap_or(base.popularity) }
+        });
+        let recommendation = py.allow_threads(|| self.inner.recommend(uid, RecommendOptions { weights, ..RecommendOptions::default() }))
+            .map_err(runtime_error)?
+            .ok_or_else(|| PyKeyError::new_err(format!("user {} not found", uid)))?;
+
+        let catalog = self.inner.catalog();
+        recommendation.output.ranked.iter()
+            .map(|scored| {
+                let dict = item_dict(py, &catalog, scored.item_id)?;
+                dict.set_item("sim_score", scored.sim_score)?;
+                dict.set_item("popularity", scored.popularity)?;
+                dict.set_item("final_score", scored.final_score)?;
+                Ok(dict)
+            })
+            .collect()
+    }
+
+    /// 混合搜索 (向量 + 全文)；`fusion` 为 "rrf" / "minmax" / "zscore"，不传时使用配置中的 `search.fusion`
+    #[pyo3(signature = (query, fusion = None))]
+    fn search<'py>(&self, py: Python<'py>, query: &str, fusion: Option<&str>) -> PyResult<Vec<Bound<'py, PyDict>>> {
+        let fusion: FusionStrategy = match fusion {
+            Some(fusion) => fusion.parse().map_err(PyValueError::new_err)?,
+            None => self.inner.config().search.fusion,
+        };
+        let output = py.allow_threads(|| self.inner.search(query, SearchOptions { fusion }))
+            .map_err(runtime_error)?
+            .ok_or_else(|| PyRuntimeError::new_err("search needs the embedding model, which failed to load"))?;
+
+        let catalog = self.inner.catalog();
+        output.results.iter()
+            .map(|result| {
+                let dict = item_dict(py, &catalog, result.id)?;
+                dict.set_item("score", result.score)?;
+                Ok(dict)
+            })
+            .collect()
+    }
+
+    /// 把一批文本编码为 L2 归一化的向量 (与物品向量同一空间)
+    fn embed(&self, py: Python<'_>, texts: Vec<String>) -> PyResult<Vec<Vec<f32>>> {
+        py.allow_threads(|| self.inner.embed(&texts))
+            .map_err(runtime_error)?
+            .ok_or_else(|| PyRuntimeError::new_err("embed needs the embedding model, which failed to load"))
+    }
+
+    /// 写入 (或覆盖) 单个物品并立即加入索引
+    #[pyo3(signature = (id, title, category, image_url = String::new(), price = 0.0))]
+    fn ingest(&self, py: Python<'_>, id: u64, title: String, category: String, image_url: String, price: f32) -> PyResult<()> {
+        let json = ItemJson { id, name: title, category, image_url, price, titles: Default::default() };
+        py.allow_threads(|| self.inner.ingest(json)).map_err(runtime_error)
+    }
+
+    /// 从 JSON 文件批量导入物品 (格式同 assets/products.json)，返回导入数量
+    fn import_json(&self, py: Python<'_>, path: &str) -> PyResult<usize> {
+        py.allow_threads(|| self.inner.import(path)).map_err(runtime_error)
+    }
+
+    /// 离线评估，每个召回策略返回一个指标字典
+    #[pyo3(signature = (k = EvalOptions::default().k, test_ratio = EvalOptions::default().test_ratio))]
+    fn evaluate<'py>(&self, py: Python<'py>, k: usize, test_ratio: f32) -> PyResult<Vec<Bound<'py, PyDict>>> {
+        let reports = py.allow_threads(|| self.inner.evaluate(EvalOptions { k, test_ratio })).map_err(runtime_error)?;
+        reports.iter()
+            .map(|report| {
+                let dict = PyDict::new_bound(py);
+                dict.set_item("strategy", report.strategy)?;
+                dict.set_item("users", report.users)?;
+                dict.set_item("recall", report.recall)?;
+                dict.set_item("ndcg", report.ndcg)?;
+                dict.set_item("mrr", report.mrr)?;
+                dict.set_item("coverage", report.coverage)?;
+                dict.set_item("diversity", report.diversity)?;
+                Ok(dict)
+            })
+            .collect()
+    }
+
+    /// 保存 HNSW 索引并刷新数据库
+    fn persist(&self, py: Python<'_>) -> PyResult<()> {
+        py.allow_threads(|| self.inner.persist()).map_err(runtime_error)
+    }
+
+    fn __len__(&self) -> usize {
+        self.inner.catalog().items().len()
+    }
+}
+
+/// 物品元数据字典 (物品已不在目录中时只有 `item_id`)
+fn item_dict<'py>(py: Python<'py>, catalog: &Catalog, item_id: u64) -> PyResult<Bound<'py, PyDict>> {
+    let dict = PyDict::new_bound(py);
+    dict.set_item("item_id", item_id)?;
+    if let Some(item) = catalog.item(item_id) {
+        dict.set_item("name", &item.name)?;
+        dict.set_item("category", &item.category)?;
+        dict.set_item("price", item.price)?;
+    }
+    Ok(dict)
+}
+
+#[pymodule]
+fn mini_recsys(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyRecSys>()?;
+    Ok(())
+}
@@ -10,8 +10,26 @@
 // 2. 指定要编译的 .cpp 源文件
 // 3. 调用 .compile() 生成静态库 (lib<name>.a 或 <name>.lib)
 // 4. Cargo 自动将该静态库链接到 Rust 二进制文件
+//
+// 未启用 `cpp-hnsw` feature 时跳过 C++ 编译，ffi 模块改用 src/brute_force.rs 中的纯 Rust 实现。
+// 启用 `grpc` feature 时用 tonic-build 从 proto/recsys.proto 生成 gRPC 代码 (需要 protoc)。
 
 fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    #[cfg(feature = "cpp-hnsw")]
+    compile_cpp();
+    #[cfg(feature = "grpc")]
+    compile_protos();
+}
+
+#[cfg(feature = "grpc")]
+fn compile_protos() {
+    tonic_build::compile_protos("proto/recsys.proto").expect("failed to compile proto/recsys.proto");
+    println!("cargo:rerun-if-changed=proto/");
+}
+
+#[cfg(feature = "cpp-hnsw")]
+fn compile_cpp() {
     // 使用 cc crate 编译 C++ 代码
     cc::Build::new()
         // 启用 C++17 标准
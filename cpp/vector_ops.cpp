@@ -182,7 +182,7 @@ extern "C" int hnsw_save_index(const char* path) {
     }
 }
 
-extern "C" int hnsw_load_index(const char* path, int dim, int max_elements) {
+extern "C" int hnsw_load_index(const char* path, int dim, int max_elements, int M, int ef_construction) {
     std::lock_guard<std::mutex> lock(g_mutex);
     
     // 清理旧索引
@@ -208,7 +208,7 @@ extern "C" int hnsw_load_index(const char* path, int dim, int max_elements) {
             return 0;  // 成功加载
         } else {
             // 文件不存在，创建新索引
-            g_hnsw_index = new hnswlib::HierarchicalNSW<float>(g_space, max_elements, 16, 200);
+            g_hnsw_index = new hnswlib::HierarchicalNSW<float>(g_space, max_elements, M, ef_construction);
             return 1;  // 创建了新索引
         }
     } catch (...) {
@@ -64,8 +64,10 @@ int hnsw_save_index(const char* path);
 /// @param path          索引文件路径
 /// @param dim           向量维度
 /// @param max_elements  最大元素数量 (仅在创建新索引时使用)
+/// @param M             每个节点的最大连接数 (仅在创建新索引时使用)
+/// @param ef_construction 构建时的搜索深度 (仅在创建新索引时使用)
 /// @return              0 成功加载, 1 创建了新索引, -1 失败
-int hnsw_load_index(const char* path, int dim, int max_elements);
+int hnsw_load_index(const char* path, int dim, int max_elements, int M, int ef_construction);
 
 // ============================================================================
 // 旧版接口 (Legacy Interface - 保持向后兼容)
@@ -50,7 +50,7 @@ function App() {
                 }).catch(() => { })
             }
         } catch (err) {
-            setError(err.response?.data?.error || err.message)
+            setError(err.response?.data?.message || err.message)
             setRecommendations([])
         } finally {
             setLoading(false)
@@ -71,7 +71,7 @@ function App() {
             setCurrentUser(null)
             setResponseTime((performance.now() - start).toFixed(0))
         } catch (err) {
-            setError(err.response?.data?.error || err.message)
+            setError(err.response?.data?.message || err.message)
             setSearchResults([])
         } finally {
             setLoading(false)
@@ -0,0 +1,295 @@
+//! ANN 召回 - HNSW 索引不可用时自动降级为暴力检索
+//!
+//! 索引加载失败、正在重建、为空或单次搜索出错时，改用 `recommend_recall`
+//! 对物品目录做暴力检索，并把结果标记为降级 (`StageCounts::degraded`)，而不是返回空推荐。
+//! 暴力检索的结果是精确的，只是更慢；超出 `memory.max_items` 而未在内存中保留向量的物品不参与降级召回。
+
+use crate::ffi::{get_hnsw_count, recommend_recall, try_hnsw_search};
+use crate::model::Item;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tracing::{debug, warn};
+
+/// 近邻索引
+pub trait AnnIndex {
+    /// 返回 (item_id, similarity) 列表，按相似度降序
+    fn search(&self, query: &[f32], k: usize) -> Result<Vec<(u64, f32)>, String>;
+
+    /// 索引中的向量数
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 日志中的名称
+    fn name(&self) -> &'static str;
+}
+
+/// HNSW 索引的重建代数: 偶数表示可用；奇数表示正在重建，或加载 / 重建失败 (见 `rebuild`)
+///
+/// 搜索前后各读一次，两次相同且为偶数时结果才来自完整的索引。
+/// 只检查可用标志的话，搜索期间完整地跑完一次重建 (true → false → true) 时察觉不到。
+#[derive(Debug, Default)]
+pub struct IndexGeneration(AtomicU64);
+
+impl IndexGeneration {
+    pub const fn new() -> Self {
+        Self(AtomicU64::new(0))
+    }
+
+    pub fn is_available(&self) -> bool {
+        self.current().is_multiple_of(2)
+    }
+
+    /// 标记为不可用 (加载失败，或开始重建)；已不可用时同样推进代数
+    pub fn invalidate(&self) {
+        let _ = self.0.fetch_update(Ordering::AcqRel, Ordering::Acquire, |g| Some(if g.is_multiple_of(2) { g + 1 } else { g + 2 }));
+    }
+
+    fn current(&self) -> u64 {
+        self.0.load(Ordering::Acquire)
+    }
+
+    /// 重建完成，恢复可用
+    fn publish(&self) {
+        self.0.fetch_add(1, Ordering::AcqRel);
+    }
+}
+
+/// 在 `generation` 不变且可用时执行 `search`；期间发生过 (哪怕已完成的) 重建时返回错误，由调用方降级
+pub fn guarded_search(
+    generation: &IndexGeneration,
+    search: impl FnOnce() -> Result<Vec<(u64, f32)>, String>,
+) -> Result<Vec<(u64, f32)>, String> {
+    let before = generation.current();
+    if !before.is_multiple_of(2) {
+        return Err("HNSW index is unavailable".to_string());
+    }
+    let hits = search()?;
+    if generation.current() != before {
+        return Err("HNSW index was rebuilt during the search".to_string());
+    }
+    Ok(hits)
+}
+
+/// 进程级 HNSW 索引 (见 `ffi.rs`)
+#[derive(Debug, Clone, Copy)]
+pub struct HnswIndex<'a> {
+    /// 不可用 (加载失败或正在重建) 时视为空索引
+    generation: &'a IndexGeneration,
+}
+
+impl<'a> HnswIndex<'a> {
+    pub fn new(generation: &'a IndexGeneration) -> Self {
+        Self { generation }
+    }
+}
+
+impl Default for HnswIndex<'static> {
+    fn default() -> Self {
+        static GENERATION: IndexGeneration = IndexGeneration::new();
+        Self::new(&GENERATION)
+    }
+}
+
+impl AnnIndex for HnswIndex<'_> {
+    fn search(&self, query: &[f32], k: usize) -> Result<Vec<(u64, f32)>, String> {
+        guarded_search(self.generation, || try_hnsw_search(query, k))
+    }
+
+    fn len(&self) -> usize {
+        if self.generation.is_available() { get_hnsw_count() } else { 0 }
+    }
+
+    fn name(&self) -> &'static str {
+        "hnsw"
+    }
+}
+
+/// 对物品目录逐个计算相似度
+#[derive(Debug, Clone, Copy)]
+pub struct BruteForce<'a> {
+    items: &'a [Item],
+}
+
+impl<'a> BruteForce<'a> {
+    pub fn new(items: &'a [Item]) -> Self {
+        Self { items }
+    }
+}
+
+impl AnnIndex for BruteForce<'_> {
+    fn search(&self, query: &[f32], k: usize) -> Result<Vec<(u64, f32)>, String> {
+        // 向量未驻留内存 (或维度不符) 的物品跳过；常见情况下无需复制目录
+        if self.items.iter().all(|item| item.embedding.len() == query.len()) {
+            return Ok(recommend_recall(query, self.items, k));
+        }
+        let resident: Vec<Item> = self.items.iter()
+            .filter(|item| item.embedding.len() == query.len())
+            .cloned()
+            .collect();
+        Ok(recommend_recall(query, &resident, k))
+    }
+
+    fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    fn name(&self) -> &'static str {
+        "brute_force"
+    }
+}
+
+/// 召回结果
+#[derive(Debug, Clone, Default)]
+pub struct Recall {
+    pub candidates: Vec<(u64, f32)>,
+    /// 结果来自降级的暴力检索
+    pub degraded: bool,
+}
+
+/// 重建 HNSW 索引: 进入时代数变为奇数，`build` 期间的请求召回降级为暴力检索，而不是查询只含部分物品的索引；
+/// `build` 成功后代数再加一 (偶数，可用)，失败时保持奇数
+pub fn rebuild<T, E>(generation: &IndexGeneration, build: impl FnOnce() -> Result<T, E>) -> Result<T, E> {
+    generation.invalidate();
+    let built = build()?;
+    generation.publish();
+    Ok(built)
+}
+
+/// 优先查询 `index`，索引为空或搜索出错时改查 `fallback`
+pub fn recall(index: &impl AnnIndex, fallback: &impl AnnIndex, query: &[f32], k: usize) -> Recall {
+    if index.is_empty() {
+        debug!(index = index.name(), fallback = fallback.name(), "ANN index is empty, using fallback recall");
+    } else {
+        match index.search(query, k) {
+            Ok(candidates) => return Recall { candidates, degraded: false },
+            Err(e) => warn!(index = index.name(), fallback = fallback.name(), error = %e, "⚠️  ANN search failed, using fallback recall"),
+        }
+    }
+
+    let candidates = fallback.search(query, k).unwrap_or_else(|e| {
+        warn!(index = fallback.name(), error = %e, "⚠️  Fallback recall failed");
+        Vec::new()
+    });
+    Recall { candidates, degraded: true }
+}
+
+// ============================================================================
+// 单元测试
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 固定返回 `result` 的索引
+    struct Fixed {
+        len: usize,
+        result: Result<Vec<(u64, f32)>, String>,
+    }
+
+    impl AnnIndex for Fixed {
+        fn search(&self, _query: &[f32], _k: usize) -> Result<Vec<(u64, f32)>, String> {
+            self.result.clone()
+        }
+
+        fn len(&self) -> usize {
+            self.len
+        }
+
+        fn name(&self) -> &'static str {
+            "fixed"
+        }
+    }
+
+    #[test]
+    fn test_recall_prefers_index() {
+        let index = Fixed { len: 3, result: Ok(vec![(7, 0.9)]) };
+        let fallback = Fixed { len: 3, result: Ok(vec![(1, 0.1)]) };
+        let recall = recall(&index, &fallback, &[1.0, 0.0], 10);
+        assert_eq!(recall.candidates, vec![(7, 0.9)]);
+        assert!(!recall.degraded);
+    }
+
+    #[test]
+    fn test_recall_degrades_on_empty_or_error() {
+        let fallback = Fixed { len: 3, result: Ok(vec![(1, 0.1)]) };
+
+        let empty = Fixed { len: 0, result: Ok(vec![(7, 0.9)]) };
+        let recall_empty = recall(&empty, &fallback, &[1.0, 0.0], 10);
+        assert_eq!(recall_empty.candidates, vec![(1, 0.1)]);
+        assert!(recall_empty.degraded);
+
+        let failing = Fixed { len: 3, result: Err("corrupt index".into()) };
+        let recall_failed = recall(&failing, &fallback, &[1.0, 0.0], 10);
+        assert_eq!(recall_failed.candidates, vec![(1, 0.1)]);
+        assert!(recall_failed.degraded);
+
+        let unavailable = IndexGeneration::new();
+        unavailable.invalidate();
+        assert!(recall(&HnswIndex::new(&unavailable), &fallback, &[1.0, 0.0], 10).degraded);
+    }
+
+    #[test]
+    fn test_recall_degrades_during_rebuild() {
+        let generation = IndexGeneration::new();
+        let fallback = Fixed { len: 3, result: Ok(vec![(1, 0.1)]) };
+
+        let during = rebuild(&generation, || {
+            Ok::<_, String>(recall(&HnswIndex::new(&generation), &fallback, &[1.0, 0.0], 10))
+        }).unwrap();
+        assert_eq!(during.candidates, vec![(1, 0.1)]);
+        assert!(during.degraded);
+        assert!(generation.is_available());
+
+        // 重建失败后索引保持不可用，下一次重建成功后恢复
+        assert!(rebuild(&generation, || Err::<(), _>("init failed".to_string())).is_err());
+        assert!(recall(&HnswIndex::new(&generation), &fallback, &[1.0, 0.0], 10).degraded);
+        rebuild(&generation, || Ok::<_, String>(())).unwrap();
+        assert!(generation.is_available());
+    }
+
+    /// 搜索过程中完整地执行一次重建的索引
+    struct RebuiltMidSearch<'a> {
+        generation: &'a IndexGeneration,
+    }
+
+    impl AnnIndex for RebuiltMidSearch<'_> {
+        fn search(&self, _query: &[f32], _k: usize) -> Result<Vec<(u64, f32)>, String> {
+            guarded_search(self.generation, || {
+                rebuild(self.generation, || Ok::<_, String>(())).unwrap();
+                Ok(vec![(7, 0.9)])
+            })
+        }
+
+        fn len(&self) -> usize {
+            3
+        }
+
+        fn name(&self) -> &'static str {
+            "rebuilt_mid_search"
+        }
+    }
+
+    #[test]
+    fn test_recall_degrades_when_rebuild_completes_during_search() {
+        let generation = IndexGeneration::new();
+        let fallback = Fixed { len: 3, result: Ok(vec![(1, 0.1)]) };
+
+        let recall = recall(&RebuiltMidSearch { generation: &generation }, &fallback, &[1.0, 0.0], 10);
+        assert_eq!(recall.candidates, vec![(1, 0.1)]);
+        assert!(recall.degraded);
+        // 重建已完成，之后的搜索不再降级
+        assert!(generation.is_available());
+        assert!(guarded_search(&generation, || Ok(vec![(7, 0.9)])).is_ok());
+    }
+
+    #[test]
+    fn test_brute_force_skips_spilled_items() {
+        let items = vec![Item::new(1, "A", vec![1.0, 0.0]), Item::new(2, "B", Vec::new()), Item::new(3, "C", vec![0.6, 0.8])];
+        let found = BruteForce::new(&items).search(&[1.0, 0.0], 10).unwrap();
+        let ids: Vec<u64> = found.iter().map(|&(id, _)| id).collect();
+        assert_eq!(ids, vec![1, 3]);
+    }
+}
@@ -0,0 +1,175 @@
+//! 端到端延迟压测 - 在进程内反复执行推荐与搜索流程，统计延迟分位数
+//!
+//! 与 benches/ 下的 criterion 基准不同，这里直接使用线上的数据目录与索引，
+//! 用于部署后快速确认真实数据规模下的 p50 / p95 / p99。
+//! `concurrency` > 1 时由多个线程同时发起请求，模拟 HTTP 处理器把流水线放到阻塞线程池后的并发负载。
+
+use crate::ann::HnswIndex;
+use crate::embedding::EmbeddingModel;
+use crate::hybrid::FusionStrategy;
+use crate::model::{Item, User};
+use crate::service::{self, RankWeights, RecallPolicy, MAX_RECOMMENDATIONS};
+use crate::storage::Storage;
+use crate::text_search::TextSearch;
+use anyhow::Result;
+use std::collections::HashMap;
+use std::time::Instant;
+
+/// 压测参数
+#[derive(Debug, Clone, Copy)]
+pub struct BenchOptions {
+    /// 每种请求的执行次数
+    pub iterations: usize,
+    /// 同时发起请求的线程数
+    pub concurrency: usize,
+}
+
+impl Default for BenchOptions {
+    fn default() -> Self {
+        Self { iterations: 200, concurrency: 1 }
+    }
+}
+
+/// 延迟统计 (毫秒)
+#[derive(Debug, Clone, Default)]
+pub struct LatencyStats {
+    pub count: usize,
+    pub mean: f64,
+    pub p50: f64,
+    pub p95: f64,
+    pub p99: f64,
+    pub max: f64,
+}
+
+impl LatencyStats {
+    pub fn from_samples(mut samples: Vec<f64>) -> Self {
+        if samples.is_empty() {
+            return Self::default();
+        }
+        samples.sort_by(|a, b| a.total_cmp(b));
+        Self {
+            count: samples.len(),
+            mean: samples.iter().sum::<f64>() / samples.len() as f64,
+            p50: percentile(&samples, 0.50),
+            p95: percentile(&samples, 0.95),
+            p99: percentile(&samples, 0.99),
+            max: samples[samples.len() - 1],
+        }
+    }
+}
+
+/// 最近秩法 (nearest-rank) 取分位数，`sorted` 需升序
+fn percentile(sorted: &[f64], q: f64) -> f64 {
+    let rank = (q * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+/// 压测结果: (请求类型, 延迟统计)
+pub type BenchReport = Vec<(&'static str, LatencyStats)>;
+
+/// 依次压测 /recommend 与 /search (模型未加载时跳过搜索)
+#[allow(clippy::too_many_arguments)]
+pub fn run(
+    storage: &Storage,
+    users: &[User],
+    items: &[Item],
+    item_map: &HashMap<u64, usize>,
+    model: Option<&EmbeddingModel>,
+    text_search: &TextSearch,
+    weights: RankWeights,
+    recall: RecallPolicy,
+    opts: BenchOptions,
+) -> Result<BenchReport> {
+    let mut report = Vec::new();
+
+    if !users.is_empty() {
+        let samples = sample(opts, |i| {
+            let user = &users[i % users.len()];
+            service::recommend(storage, &HnswIndex::default(), user, items, item_map, weights, MAX_RECOMMENDATIONS, recall, Vec::new()).map(drop)
+        })?;
+        report.push(("recommend", LatencyStats::from_samples(samples)));
+    }
+
+    if let (Some(model), false) = (model, items.is_empty()) {
+        let samples = sample(opts, |i| {
+            // 用物品标题作为查询，覆盖不同长度的文本
+            let query = &items[i % items.len()].name;
+            service::hybrid_search(model, text_search, &HnswIndex::default(), items, query, FusionStrategy::default()).map(drop)
+        })?;
+        report.push(("search", LatencyStats::from_samples(samples)));
+    }
+
+    Ok(report)
+}
+
+/// 由 `opts.concurrency` 个线程共执行 `opts.iterations` 次 `request(i)`，返回每次的延迟
+fn sample(opts: BenchOptions, request: impl Fn(usize) -> Result<()> + Sync) -> Result<Vec<f64>> {
+    let threads = opts.concurrency.max(1);
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..threads)
+            .map(|t| {
+                let request = &request;
+                scope.spawn(move || {
+                    (t..opts.iterations).step_by(threads)
+                        .map(|i| {
+                            let start = Instant::now();
+                            request(i)?;
+                            Ok(service::elapsed_ms(start))
+                        })
+                        .collect::<Result<Vec<f64>>>()
+                })
+            })
+            .collect();
+
+        let mut samples = Vec::with_capacity(opts.iterations);
+        for handle in handles {
+            samples.extend(handle.join().expect("bench thread panicked")?);
+        }
+        Ok(samples)
+    })
+}
+
+/// 打印压测结果表格
+pub fn print_report(report: &BenchReport) {
+    println!("{:<12} {:>7} {:>9} {:>9} {:>9} {:>9} {:>9}", "request", "count", "mean", "p50", "p95", "p99", "max");
+    for (name, s) in report {
+        println!(
+            "{:<12} {:>7} {:>8.2}ms {:>8.2}ms {:>8.2}ms {:>8.2}ms {:>8.2}ms",
+            name, s.count, s.mean, s.p50, s.p95, s.p99, s.max
+        );
+    }
+}
+
+// ============================================================================
+// 单元测试
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_latency_percentiles() {
+        let stats = LatencyStats::from_samples((1..=100).rev().map(|x| x as f64).collect());
+        assert_eq!(stats.count, 100);
+        assert_eq!((stats.p50, stats.p95, stats.p99, stats.max), (50.0, 95.0, 99.0, 100.0));
+        assert!((stats.mean - 50.5).abs() < 1e-9);
+
+        assert_eq!(LatencyStats::from_samples(vec![]).count, 0);
+        assert_eq!(LatencyStats::from_samples(vec![3.0]).p99, 3.0);
+    }
+
+    #[test]
+    fn test_concurrent_sampling_runs_every_iteration() {
+        let seen = std::sync::Mutex::new(Vec::new());
+        let samples = sample(BenchOptions { iterations: 10, concurrency: 3 }, |i| {
+            seen.lock().unwrap().push(i);
+            Ok(())
+        }).unwrap();
+        assert_eq!(samples.len(), 10);
+
+        let mut seen = seen.into_inner().unwrap();
+        seen.sort_unstable();
+        assert_eq!(seen, (0..10).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,178 @@
+//! 纯 Rust 暴力检索 - 未启用 `cpp-hnsw` feature 时替代 C++ HNSW 索引
+//!
+//! 通过 `ffi` 模块以相同的函数签名导出: 内积相似度、进程级全局索引、同 ID 重复添加时覆盖。
+//! 查询为精确的 O(N) 扫描，只适合小规模物品库、CI 与本地开发。
+//!
+//! 索引文件为 bincode 格式，与 hnswlib 的文件互不兼容；读到无法解析的文件时视为新建索引，
+//! 由启动流程从数据库重建。
+
+use crate::ffi::HnswConfig;
+use crate::model::Item;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::sync::{Mutex, MutexGuard};
+use tracing::warn;
+
+/// 全局索引 (与 C++ 侧的 g_hnsw_index 对应)
+static INDEX: Mutex<Option<FlatIndex>> = Mutex::new(None);
+
+#[derive(Serialize, Deserialize)]
+struct FlatIndex {
+    dim: usize,
+    max_elements: usize,
+    ids: Vec<u64>,
+    /// 行优先存储，第 i 行对应 ids[i]
+    vectors: Vec<f32>,
+    #[serde(skip)]
+    positions: HashMap<u64, usize>,
+}
+
+impl FlatIndex {
+    fn new(config: &HnswConfig) -> Self {
+        Self {
+            dim: config.dim,
+            max_elements: config.max_elements,
+            ids: Vec::new(),
+            vectors: Vec::new(),
+            positions: HashMap::new(),
+        }
+    }
+
+    fn rows(&self) -> impl Iterator<Item = (u64, &[f32])> {
+        self.ids.iter().copied().zip(self.vectors.chunks_exact(self.dim.max(1)))
+    }
+}
+
+fn lock() -> MutexGuard<'static, Option<FlatIndex>> {
+    INDEX.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// 按内积降序取前 k 个 (分数相同时保持输入顺序，内积为 NaN 的行不返回)
+fn top_k<'a>(query: &[f32], rows: impl Iterator<Item = (u64, &'a [f32])>, k: usize) -> Vec<(u64, f32)> {
+    let mut scored: Vec<(u64, f32)> = rows.map(|(id, row)| (id, dot(query, row))).collect();
+    crate::score::sort_desc(&mut scored, |&(_, s)| s);
+    scored.truncate(k);
+    scored
+}
+
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+// ============================================================================
+// 基础运算
+// ============================================================================
+
+pub fn add(a: i32, b: i32) -> i32 {
+    a + b
+}
+
+pub fn compute_dot_product(vec_a: &[f32], vec_b: &[f32]) -> Option<f32> {
+    (vec_a.len() == vec_b.len()).then(|| dot(vec_a, vec_b))
+}
+
+// ============================================================================
+// 索引操作
+// ============================================================================
+
+/// 初始化索引 (m / ef_construction / ef_search 对暴力检索无意义，忽略)
+pub fn init_hnsw_index(config: &HnswConfig) -> Result<(), String> {
+    *lock() = Some(FlatIndex::new(config));
+    Ok(())
+}
+
+pub fn add_item_to_hnsw(id: u64, embedding: &[f32]) -> Result<(), String> {
+    let mut guard = lock();
+    let index = guard.as_mut().ok_or_else(|| format!("Failed to add item {} to HNSW index", id))?;
+    if embedding.len() != index.dim {
+        return Err(format!("Failed to add item {}: expected {} dimensions, got {}", id, index.dim, embedding.len()));
+    }
+
+    match index.positions.get(&id) {
+        Some(&pos) => index.vectors[pos * index.dim..][..index.dim].copy_from_slice(embedding),
+        None => {
+            if index.ids.len() >= index.max_elements {
+                return Err(format!("Failed to add item {}: index is full", id));
+            }
+            index.positions.insert(id, index.ids.len());
+            index.ids.push(id);
+            index.vectors.extend_from_slice(embedding);
+        }
+    }
+    Ok(())
+}
+
+pub fn hnsw_search(query: &[f32], k: usize) -> Vec<(u64, f32)> {
+    try_hnsw_search(query, k).unwrap_or_default()
+}
+
+pub fn try_hnsw_search(query: &[f32], k: usize) -> Result<Vec<(u64, f32)>, String> {
+    match lock().as_ref() {
+        None => Err("HNSW index is not initialized".to_string()),
+        Some(index) if query.len() != index.dim => {
+            Err(format!("Query has {} dimensions, index expects {}", query.len(), index.dim))
+        }
+        Some(_) if k == 0 => Ok(Vec::new()),
+        Some(index) => Ok(top_k(query, index.rows(), k)),
+    }
+}
+
+/// 暴力检索总是精确的，ef 无意义
+pub fn set_hnsw_ef(_ef_search: usize) {}
+
+pub fn destroy_hnsw_index() {
+    *lock() = None;
+}
+
+pub fn get_hnsw_count() -> usize {
+    lock().as_ref().map_or(0, |index| index.ids.len())
+}
+
+pub fn save_hnsw_index(path: &str) -> Result<(), String> {
+    let guard = lock();
+    let index = guard.as_ref().ok_or_else(|| "Failed to save HNSW index".to_string())?;
+    let file = File::create(path).map_err(|e| format!("Failed to save HNSW index: {}", e))?;
+    bincode::serialize_into(BufWriter::new(file), index).map_err(|e| format!("Failed to save HNSW index: {}", e))
+}
+
+/// 加载索引 (文件不存在或无法解析时按 `config` 创建新索引)
+/// 返回: Ok(true) = 已加载, Ok(false) = 创建了新索引
+pub fn load_hnsw_index(path: &str, config: &HnswConfig) -> Result<bool, String> {
+    let loaded = File::open(path).ok().and_then(|file| {
+        match bincode::deserialize_from::<_, FlatIndex>(BufReader::new(file)) {
+            Ok(index) if index.dim == config.dim => Some(index),
+            Ok(index) => {
+                warn!(path, file_dim = index.dim, dim = config.dim, "⚠️  Index dimension mismatch, starting empty");
+                None
+            }
+            Err(e) => {
+                warn!(path, error = %e, "⚠️  Unreadable brute-force index (hnswlib file?), starting empty");
+                None
+            }
+        }
+    });
+
+    let mut guard = lock();
+    match loaded {
+        Some(mut index) => {
+            index.positions = index.ids.iter().enumerate().map(|(i, &id)| (id, i)).collect();
+            index.max_elements = index.max_elements.max(config.max_elements);
+            *guard = Some(index);
+            Ok(true)
+        }
+        None => {
+            *guard = Some(FlatIndex::new(config));
+            Ok(false)
+        }
+    }
+}
+
+// ============================================================================
+// 旧版暴力搜索 (Legacy)
+// ============================================================================
+
+pub fn recommend_recall(user_embedding: &[f32], items: &[Item], k: usize) -> Vec<(u64, f32)> {
+    top_k(user_embedding, items.iter().map(|item| (item.id, item.embedding.as_slice())), k)
+}
@@ -0,0 +1,107 @@
+//! Redis 推荐结果缓存 (需要 `redis-cache` feature) - 多个副本共享同一份缓存
+//!
+//! 缓存的是排序结果 (物品 ID 与分数)，物品详情在命中时从本地目录补全。
+//! 键为 `<prefix>rec:<uid>`，写入时带 TTL；`/mark_seen` 会删除该用户的键。
+//! Redis 不可用时只记录警告，按未命中处理，不影响请求。
+//!
+//! 未启用 feature 时 `RecommendationCache` 无法构造 (连接总是返回错误)。
+
+use crate::model::ExperimentTag;
+use crate::service::ScoredItem;
+use serde::{Deserialize, Serialize};
+
+/// 一次推荐的缓存内容
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedRecommendation {
+    pub ranked: Vec<ScoredItem>,
+    pub filtered_count: usize,
+    pub experiment: Option<ExperimentTag>,
+}
+
+#[cfg(feature = "redis-cache")]
+pub use redis_impl::RecommendationCache;
+
+#[cfg(feature = "redis-cache")]
+mod redis_impl {
+    use super::CachedRecommendation;
+    use crate::config::CacheConfig;
+    use anyhow::{Context, Result};
+    use redis::aio::ConnectionManager;
+    use redis::AsyncCommands;
+    use tracing::warn;
+
+    pub struct RecommendationCache {
+        conn: ConnectionManager,
+        prefix: String,
+        ttl_secs: u64,
+    }
+
+    impl RecommendationCache {
+        pub async fn connect(config: &CacheConfig) -> Result<Self> {
+            let client = redis::Client::open(config.redis_url.as_str())
+                .with_context(|| format!("Invalid cache.redis_url {}", config.redis_url))?;
+            let conn = ConnectionManager::new(client).await
+                .with_context(|| format!("Failed to connect to Redis at {}", config.redis_url))?;
+            Ok(Self { conn, prefix: config.prefix.clone(), ttl_secs: config.ttl_secs })
+        }
+
+        fn key(&self, uid: u64) -> String {
+            format!("{}rec:{}", self.prefix, uid)
+        }
+
+        pub async fn get(&self, uid: u64) -> Option<CachedRecommendation> {
+            let mut conn = self.conn.clone();
+            let raw: Option<String> = conn.get(self.key(uid)).await
+                .inspect_err(|e| warn!(uid, error = %e, "⚠️  Redis GET failed"))
+                .ok()?;
+            serde_json::from_str(&raw?)
+                .inspect_err(|e| warn!(uid, error = %e, "⚠️  Discarding unreadable cache entry"))
+                .ok()
+        }
+
+        pub async fn put(&self, uid: u64, value: &CachedRecommendation) {
+            let Ok(raw) = serde_json::to_string(value) else { return };
+            let mut conn = self.conn.clone();
+            let result: redis::RedisResult<()> = conn.set_ex(self.key(uid), raw, self.ttl_secs).await;
+            if let Err(e) = result {
+                warn!(uid, error = %e, "⚠️  Redis SET failed");
+            }
+        }
+
+        pub async fn invalidate(&self, uid: u64) {
+            let mut conn = self.conn.clone();
+            let result: redis::RedisResult<()> = conn.del(self.key(uid)).await;
+            if let Err(e) = result {
+                warn!(uid, error = %e, "⚠️  Redis DEL failed");
+            }
+        }
+    }
+}
+
+// ============================================================================
+// 未启用 redis-cache feature 时的占位实现
+// ============================================================================
+
+#[cfg(not(feature = "redis-cache"))]
+pub struct RecommendationCache {
+    never: std::convert::Infallible,
+}
+
+#[cfg(not(feature = "redis-cache"))]
+impl RecommendationCache {
+    pub async fn connect(_config: &crate::config::CacheConfig) -> anyhow::Result<Self> {
+        anyhow::bail!("mini-recsys was built without the `redis-cache` feature")
+    }
+
+    pub async fn get(&self, _uid: u64) -> Option<CachedRecommendation> {
+        match self.never {}
+    }
+
+    pub async fn put(&self, _uid: u64, _value: &CachedRecommendation) {
+        match self.never {}
+    }
+
+    pub async fn invalidate(&self, _uid: u64) {
+        match self.never {}
+    }
+}
@@ -0,0 +1,158 @@
+//! 内存目录 - 用户与物品列表及其 ID 索引
+//!
+//! `RecSys` 以 `Arc<Catalog>` 快照的形式提供目录: 请求开始时取一份快照，处理期间不受并发写入影响。
+//! 写入在写锁内进行写时复制 (没有请求持有旧快照时原地修改)，并且只经过本模块的方法，
+//! 保证列表与 ID 索引始终一致。
+
+use crate::model::{Item, User};
+use std::collections::HashMap;
+
+/// 用户与物品目录
+#[derive(Debug, Clone, Default)]
+pub struct Catalog {
+    users: Vec<User>,
+    user_map: HashMap<u64, usize>,
+    items: Vec<Item>,
+    item_map: HashMap<u64, usize>,
+    /// 保留向量的物品数 (`memory.max_items`，0 = 不限制)
+    max_resident: usize,
+}
+
+impl Catalog {
+    /// 按给定顺序建立目录；重复的 ID 以后出现的为准
+    pub fn new(users: Vec<User>, items: Vec<Item>, max_resident: usize) -> Self {
+        let mut catalog = Self { max_resident, ..Self::default() };
+        for user in users {
+            catalog.upsert_user(user);
+        }
+        for item in items {
+            catalog.upsert_item(item);
+        }
+        catalog
+    }
+
+    pub fn users(&self) -> &[User] {
+        &self.users
+    }
+
+    pub fn user(&self, uid: u64) -> Option<&User> {
+        self.user_map.get(&uid).map(|&idx| &self.users[idx])
+    }
+
+    pub fn items(&self) -> &[Item] {
+        &self.items
+    }
+
+    pub fn item(&self, id: u64) -> Option<&Item> {
+        self.item_map.get(&id).map(|&idx| &self.items[idx])
+    }
+
+    /// 物品 ID -> `items()` 下标
+    pub fn item_map(&self) -> &HashMap<u64, usize> {
+        &self.item_map
+    }
+
+    /// 写入或覆盖用户 (保持原位置)；返回是否为新用户
+    pub fn upsert_user(&mut self, user: User) -> bool {
+        match self.user_map.get(&user.id) {
+            Some(&idx) => {
+                self.users[idx] = user;
+                false
+            }
+            None => {
+                self.user_map.insert(user.id, self.users.len());
+                self.users.push(user);
+                true
+            }
+        }
+    }
+
+    /// 写入或覆盖物品 (保持原位置)；返回是否为新物品
+    ///
+    /// 位置超出 `max_resident` 的物品不保留向量，需要时从数据库读取。
+    pub fn upsert_item(&mut self, item: Item) -> bool {
+        match self.item_map.get(&item.id) {
+            Some(&idx) => {
+                self.items[idx] = spill(item, idx, self.max_resident);
+                false
+            }
+            None => {
+                let idx = self.items.len();
+                self.item_map.insert(item.id, idx);
+                self.items.push(spill(item, idx, self.max_resident));
+                true
+            }
+        }
+    }
+
+    /// 更新物品的热门度；返回是否存在该物品
+    pub fn set_popularity(&mut self, id: u64, popularity: f32) -> bool {
+        match self.item_map.get(&id) {
+            Some(&idx) => {
+                self.items[idx].popularity = popularity;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 超出 `max_resident` 而未保留向量的物品数
+    pub fn spilled(&self) -> usize {
+        match self.max_resident {
+            0 => 0,
+            max => self.items.len().saturating_sub(max),
+        }
+    }
+}
+
+/// 第 `idx` 个物品超出 `max_items` (0 = 不限制) 时丢弃内存中的向量，只保留元数据
+fn spill(item: Item, idx: usize, max_items: usize) -> Item {
+    if max_items > 0 && idx >= max_items {
+        Item { embedding: Vec::new(), ..item }
+    } else {
+        item
+    }
+}
+
+// ============================================================================
+// 单元测试
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(id: u64) -> Item {
+        Item::new(id, format!("item {}", id), vec![1.0, 0.0])
+    }
+
+    #[test]
+    fn test_spill_beyond_max_items() {
+        assert!(spill(item(1), 0, 2).embedding.len() == 2);
+        assert!(spill(item(3), 2, 2).embedding.is_empty());
+        assert!(spill(item(9), 100, 0).embedding.len() == 2); // 0 = 不限制
+    }
+
+    #[test]
+    fn test_upsert_keeps_index_consistent() {
+        let mut catalog = Catalog::new(Vec::new(), vec![item(10), item(20), item(10)], 2);
+        // 重复的 10 覆盖原位置
+        assert_eq!(catalog.items().len(), 2);
+
+        assert!(catalog.upsert_item(item(30)));
+        assert!(!catalog.upsert_item(Item { popularity: 0.9, ..item(20) }));
+        for (idx, item) in catalog.items().iter().enumerate() {
+            assert_eq!(catalog.item_map()[&item.id], idx);
+        }
+        assert_eq!(catalog.item(20).unwrap().popularity, 0.9);
+        // 第三个位置超出 max_resident
+        assert!(catalog.item(30).unwrap().embedding.is_empty());
+        assert_eq!(catalog.spilled(), 1);
+
+        let user = User { id: 7, name: "new".into(), embedding: vec![0.0, 1.0] };
+        assert!(catalog.upsert_user(user.clone()));
+        assert!(!catalog.upsert_user(User { name: "renamed".into(), ..user }));
+        assert_eq!(catalog.users().len(), 1);
+        assert_eq!(catalog.user(7).unwrap().name, "renamed");
+    }
+}
@@ -0,0 +1,253 @@
+//! Parquet 读写 (需要 `parquet` feature) - 导出物品元数据与向量，导入外部计算的向量
+//!
+//! 列: `id` (UInt64)、`name` / `category` / `image_url` (Utf8)、`price` / `popularity` (Float32)、
+//! `embedding` (FixedSizeList<Float32, dim>)。可直接用 pandas / Polars 读取:
+//!
+//! ```python
+//! df = pl.read_parquet("data/catalog.parquet")
+//! vectors = np.stack(df["embedding"].to_numpy())
+//! ```
+//!
+//! 导入时只读取 `id` (UInt64 / Int64) 与 `embedding` (Float32 的 FixedSizeList 或 List) 两列，
+//! 因此导出的文件可以直接导回。
+//!
+//! 排序训练样本 (`export --format parquet`) 每个字段一列，列名与 JSONL 的键相同。
+
+use crate::export::TrainingExample;
+use crate::model::Item;
+use anyhow::{anyhow, bail, Context, Result};
+use arrow_array::cast::AsArray;
+use arrow_array::types::{Float32Type, Int64Type, UInt64Type};
+use arrow_array::{Array, ArrayRef, FixedSizeListArray, Float32Array, RecordBatch, StringArray, UInt64Array, UInt8Array};
+use arrow_schema::{DataType, Field, Schema, SchemaRef};
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use std::io::Write;
+use std::path::Path;
+use std::sync::Arc;
+
+/// 每个 RecordBatch (行组内) 的行数
+const BATCH_ROWS: usize = 4096;
+
+fn catalog_schema(dim: usize) -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new("id", DataType::UInt64, false),
+        Field::new("name", DataType::Utf8, false),
+        Field::new("category", DataType::Utf8, false),
+        Field::new("image_url", DataType::Utf8, false),
+        Field::new("price", DataType::Float32, false),
+        Field::new("popularity", DataType::Float32, false),
+        Field::new("embedding", DataType::FixedSizeList(vector_field(), dim as i32), false),
+    ]))
+}
+
+fn vector_field() -> Arc<Field> {
+    Arc::new(Field::new("item", DataType::Float32, false))
+}
+
+/// 把物品 (须带完整向量) 写成 Parquet，返回写入的行数
+pub fn write_catalog(items: &[Item], dim: usize, out: impl Write + Send) -> Result<usize> {
+    let schema = catalog_schema(dim);
+    let props = WriterProperties::builder().set_compression(Compression::SNAPPY).build();
+    let mut writer = ArrowWriter::try_new(out, Arc::clone(&schema), Some(props))?;
+
+    for chunk in items.chunks(BATCH_ROWS) {
+        let mut vectors = Vec::with_capacity(chunk.len() * dim);
+        for item in chunk {
+            if item.embedding.len() != dim {
+                bail!("Item {} has {} dimensions, expected {}", item.id, item.embedding.len(), dim);
+            }
+            vectors.extend_from_slice(&item.embedding);
+        }
+        let embedding = FixedSizeListArray::try_new(vector_field(), dim as i32, Arc::new(Float32Array::from(vectors)), None)?;
+
+        let columns: Vec<ArrayRef> = vec![
+            Arc::new(UInt64Array::from_iter_values(chunk.iter().map(|item| item.id))),
+            Arc::new(StringArray::from_iter_values(chunk.iter().map(|item| &item.name))),
+            Arc::new(StringArray::from_iter_values(chunk.iter().map(|item| &item.category))),
+            Arc::new(StringArray::from_iter_values(chunk.iter().map(|item| &item.image_url))),
+            Arc::new(Float32Array::from_iter_values(chunk.iter().map(|item| item.price))),
+            Arc::new(Float32Array::from_iter_values(chunk.iter().map(|item| item.popularity))),
+            Arc::new(embedding),
+        ];
+        let batch = RecordBatch::try_new(Arc::clone(&schema), columns).context("Failed to build record batch")?;
+        writer.write(&batch)?;
+    }
+
+    writer.close()?;
+    Ok(items.len())
+}
+
+fn training_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new("uid", DataType::UInt64, false),
+        Field::new("item_id", DataType::UInt64, false),
+        Field::new("ts_ms", DataType::UInt64, false),
+        Field::new("position", DataType::UInt64, false),
+        Field::new("label", DataType::UInt8, false),
+        Field::new("variant", DataType::Utf8, true),
+        Field::new("category", DataType::Utf8, false),
+        Field::new("price", DataType::Float32, false),
+        Field::new("popularity", DataType::Float32, false),
+        Field::new("sim_score", DataType::Float32, false),
+        Field::new("user_views_before", DataType::UInt64, false),
+        Field::new("user_clicks_before", DataType::UInt64, false),
+    ]))
+}
+
+/// 把排序训练样本写成 Parquet，返回写入的行数
+pub fn write_training(examples: &[TrainingExample], out: impl Write + Send) -> Result<usize> {
+    let schema = training_schema();
+    let props = WriterProperties::builder().set_compression(Compression::SNAPPY).build();
+    let mut writer = ArrowWriter::try_new(out, Arc::clone(&schema), Some(props))?;
+
+    for chunk in examples.chunks(BATCH_ROWS) {
+        let u64_column = |value: fn(&TrainingExample) -> u64| -> ArrayRef {
+            Arc::new(UInt64Array::from_iter_values(chunk.iter().map(value)))
+        };
+        let f32_column = |value: fn(&TrainingExample) -> f32| -> ArrayRef {
+            Arc::new(Float32Array::from_iter_values(chunk.iter().map(value)))
+        };
+        let columns: Vec<ArrayRef> = vec![
+            u64_column(|e| e.uid),
+            u64_column(|e| e.item_id),
+            u64_column(|e| e.ts_ms),
+            u64_column(|e| e.position as u64),
+            Arc::new(UInt8Array::from_iter_values(chunk.iter().map(|e| e.label))),
+            Arc::new(chunk.iter().map(|e| e.variant.as_deref()).collect::<StringArray>()),
+            Arc::new(StringArray::from_iter_values(chunk.iter().map(|e| &e.category))),
+            f32_column(|e| e.price),
+            f32_column(|e| e.popularity),
+            f32_column(|e| e.sim_score),
+            u64_column(|e| e.user_views_before as u64),
+            u64_column(|e| e.user_clicks_before as u64),
+        ];
+        let batch = RecordBatch::try_new(Arc::clone(&schema), columns).context("Failed to build record batch")?;
+        writer.write(&batch)?;
+    }
+
+    writer.close()?;
+    Ok(examples.len())
+}
+
+/// 读取 `id` -> 向量 映射
+pub fn read_embeddings(path: &Path) -> Result<Vec<(u64, Vec<f32>)>> {
+    let file = std::fs::File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let reader = ParquetRecordBatchReaderBuilder::try_new(file)
+        .and_then(|builder| builder.build())
+        .with_context(|| format!("Invalid Parquet file {}", path.display()))?;
+
+    let mut out = Vec::new();
+    for batch in reader {
+        let batch = batch?;
+        let ids = id_column(&batch)?;
+        let column = batch.column_by_name("embedding").context("Missing `embedding` column")?;
+        if column.null_count() > 0 {
+            bail!("`embedding` column has null rows");
+        }
+        for (row, id) in ids.into_iter().enumerate() {
+            let vector = match (column.as_fixed_size_list_opt(), column.as_list_opt::<i32>()) {
+                (Some(list), _) => list.value(row),
+                (None, Some(list)) => list.value(row),
+                (None, None) => bail!("`embedding` must be a list of float32, found {}", column.data_type()),
+            };
+            let vector = vector.as_primitive_opt::<Float32Type>()
+                .ok_or_else(|| anyhow!("`embedding` must be a list of float32, found {}", column.data_type()))?;
+            out.push((id, vector.values().to_vec()));
+        }
+    }
+    Ok(out)
+}
+
+fn id_column(batch: &RecordBatch) -> Result<Vec<u64>> {
+    let column = batch.column_by_name("id").context("Missing `id` column")?;
+    if column.null_count() > 0 {
+        bail!("`id` column has null rows");
+    }
+    if let Some(ids) = column.as_primitive_opt::<UInt64Type>() {
+        return Ok(ids.values().to_vec());
+    }
+    if let Some(ids) = column.as_primitive_opt::<Int64Type>() {
+        return ids.values().iter()
+            .map(|&id| u64::try_from(id).map_err(|_| anyhow!("Negative id {}", id)))
+            .collect();
+    }
+    bail!("`id` must be a UInt64 or Int64 column, found {}", column.data_type())
+}
+
+// ============================================================================
+// 单元测试
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_catalog() {
+        let items = vec![
+            Item::new(1, "Desk Lamp", vec![1.0, 0.0, 0.5]),
+            Item::new(2, "Office Chair", vec![0.0, 1.0, 0.5]),
+        ];
+        let path = std::env::temp_dir().join(format!("mini-recsys-catalog-{}.parquet", std::process::id()));
+        assert_eq!(write_catalog(&items, 3, std::fs::File::create(&path).unwrap()).unwrap(), 2);
+
+        let reader = ParquetRecordBatchReaderBuilder::try_new(std::fs::File::open(&path).unwrap()).unwrap().build().unwrap();
+        let batches: Vec<RecordBatch> = reader.collect::<Result<_, _>>().unwrap();
+        std::fs::remove_file(&path).ok();
+
+        let batch = &batches[0];
+        assert_eq!(batch.num_rows(), 2);
+        let embedding = batch.column_by_name("embedding").unwrap().as_any().downcast_ref::<FixedSizeListArray>().unwrap();
+        assert_eq!(embedding.value_length(), 3);
+        let second = embedding.value(1);
+        let second = second.as_any().downcast_ref::<Float32Array>().unwrap();
+        assert_eq!(second.values().to_vec(), vec![0.0, 1.0, 0.5]);
+
+        assert!(write_catalog(&items, 4, Vec::new()).is_err());
+    }
+
+    #[test]
+    fn test_write_training() {
+        let example = |item_id, label, variant: Option<&str>| TrainingExample {
+            uid: 1,
+            item_id,
+            ts_ms: 100,
+            position: item_id as usize,
+            label,
+            variant: variant.map(String::from),
+            category: "Home".into(),
+            price: 19.9,
+            popularity: 0.5,
+            sim_score: 0.8,
+            user_views_before: 3,
+            user_clicks_before: 1,
+        };
+        let examples = vec![example(1, 1, Some("treatment")), example(2, 0, None)];
+        let path = std::env::temp_dir().join(format!("mini-recsys-training-{}.parquet", std::process::id()));
+        assert_eq!(write_training(&examples, std::fs::File::create(&path).unwrap()).unwrap(), 2);
+
+        let reader = ParquetRecordBatchReaderBuilder::try_new(std::fs::File::open(&path).unwrap()).unwrap().build().unwrap();
+        let batches: Vec<RecordBatch> = reader.collect::<Result<_, _>>().unwrap();
+        std::fs::remove_file(&path).ok();
+        let batch = &batches[0];
+        assert_eq!(batch.num_rows(), 2);
+        assert_eq!(batch.column_by_name("label").unwrap().as_primitive::<arrow_array::types::UInt8Type>().values().to_vec(), vec![1, 0]);
+        let variant = batch.column_by_name("variant").unwrap().as_string::<i32>();
+        assert_eq!((variant.value(0), variant.is_null(1)), ("treatment", true));
+        assert_eq!(batch.column_by_name("position").unwrap().as_primitive::<UInt64Type>().values().to_vec(), vec![1, 2]);
+    }
+
+    #[test]
+    fn test_export_round_trip() {
+        let items = vec![Item::new(7, "Desk Lamp", vec![0.25, 0.5]), Item::new(9, "Rug", vec![1.0, -1.0])];
+        let path = std::env::temp_dir().join(format!("mini-recsys-roundtrip-{}.parquet", std::process::id()));
+        write_catalog(&items, 2, std::fs::File::create(&path).unwrap()).unwrap();
+
+        let vectors = read_embeddings(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+        assert_eq!(vectors, vec![(7, vec![0.25, 0.5]), (9, vec![1.0, -1.0])]);
+    }
+}
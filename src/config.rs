@@ -0,0 +1,1038 @@
+//! 配置 - 默认值 <- TOML 文件 <- 环境变量，逐层覆盖
+//!
+//! - 配置文件: `RECSYS_CONFIG` 指定的路径，未设置时读取当前目录下的 `config.toml` (不存在则跳过)
+//! - 环境变量: `RECSYS__<SECTION>__<KEY>`，例如 `RECSYS__SERVER__BIND=0.0.0.0:8080`、
+//!   `RECSYS__RANKING__SIM=0.8`。值按 TOML 字面量解析，解析失败时视为字符串。
+//!
+//! 完整字段与默认值见仓库根目录的 `config.example.toml`。
+
+use crate::embedding::{MODEL_PATH, TOKENIZER_PATH};
+use crate::hybrid::FusionStrategy;
+use crate::csv_import::CsvConfig;
+use crate::i18n;
+use crate::jobs::JobsConfig;
+use crate::model::DIM;
+use crate::service::{RankWeights, RecallPolicy};
+use crate::slowlog::SlowQueryThresholds;
+use crate::storage::BloomConfig;
+use crate::webhooks::EVENT_NAMES;
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// 环境变量前缀
+const ENV_PREFIX: &str = "RECSYS__";
+/// `server.bind` 以此开头时监听 Unix socket，例如 `unix:/run/mini-recsys.sock`
+pub const UNIX_SOCKET_PREFIX: &str = "unix:";
+const DEFAULT_CONFIG_FILE: &str = "config.toml";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub server: ServerConfig,
+    pub paths: PathsConfig,
+    pub embedding: EmbeddingConfig,
+    pub hnsw: HnswParams,
+    /// 未命中 A/B 实验时的排序权重
+    pub ranking: RankWeights,
+    /// /recommend 的召回深度与结果数下限
+    pub recall: RecallPolicy,
+    /// /search 的默认融合策略
+    pub search: SearchConfig,
+    pub bloom: BloomConfig,
+    pub slow_query: SlowQueryThresholds,
+    pub request_log: RequestLogConfig,
+    pub demo: DemoConfig,
+    pub jobs: JobsConfig,
+    pub memory: MemoryConfig,
+    /// /recommend 翻页快照
+    pub pagination: PaginationConfig,
+    /// 近期曝光降权 (刷新时轮换推荐列表)
+    pub impressions: ImpressionsConfig,
+    /// 按 CTR 计算热门度 (Wilson 下界)
+    pub popularity: PopularityConfig,
+    /// 相似用户召回 ("和你相似的用户也看过")
+    pub lookalike: LookalikeConfig,
+    /// 多语言标题的展示与编码语言
+    pub i18n: I18nConfig,
+    /// `import --format csv` 的分隔符与列名映射
+    pub csv: CsvConfig,
+    /// OTLP 链路与指标导出
+    pub telemetry: TelemetryConfig,
+    /// 交互与曝光记录写往哪里 (sled / Postgres)
+    pub events: EventsConfig,
+    /// 设置后从 Kafka / Redpanda topic 消费交互事件 (需要 `kafka` feature)
+    pub kafka: Option<KafkaConfig>,
+    /// 设置后把推荐结果缓存到 Redis，供多个副本共享 (需要 `redis-cache` feature)
+    pub cache: Option<CacheConfig>,
+    /// 设置后把前 K 个结果交给外部模型服务重排 (需要 `rerank` feature)
+    pub rerank: Option<RerankConfig>,
+    /// 设置后启动时从 S3 兼容桶恢复数据库与索引，并定期上传 (需要 `s3` feature)
+    pub remote: Option<RemoteConfig>,
+    /// 物品与反馈事件的出站 Webhook (需要 `webhooks` feature)
+    pub webhooks: Vec<WebhookConfig>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    /// TCP 地址 (`0.0.0.0:3000`) 或 Unix socket 路径 (`unix:/run/mini-recsys.sock`)
+    pub bind: String,
+    pub cors_origin: String,
+    /// 设置后直接以 HTTPS 提供服务 (需要 `tls` feature)
+    pub tls: Option<TlsConfig>,
+    /// 单独的指标监听地址 (只提供 /health、/status、/stats、/admin/metrics)，便于只对内网开放
+    pub metrics_bind: Option<String>,
+    /// gRPC 监听地址 (需要 `grpc` feature)
+    pub grpc_bind: Option<String>,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            bind: "0.0.0.0:3000".into(),
+            cors_origin: "http://localhost:5173".into(),
+            tls: None,
+            metrics_bind: None,
+            grpc_bind: None,
+        }
+    }
+}
+
+impl ServerConfig {
+    /// 监听 Unix socket 时返回 socket 路径
+    pub fn unix_socket(&self) -> Option<&str> {
+        self.bind.strip_prefix(UNIX_SOCKET_PREFIX)
+    }
+}
+
+/// PEM 格式的证书链与私钥路径
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TlsConfig {
+    pub cert: String,
+    pub key: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PathsConfig {
+    /// 数据根目录；设置后其余相对路径都以它为基准 (未设置时以当前工作目录为基准)
+    pub data_dir: Option<String>,
+    pub db: String,
+    pub index: String,
+    pub text_index: String,
+    pub products: String,
+    pub model: String,
+    pub tokenizer: String,
+    pub slow_log: String,
+}
+
+impl Default for PathsConfig {
+    fn default() -> Self {
+        Self {
+            data_dir: None,
+            db: "data/db".into(),
+            index: "data/index.bin".into(),
+            text_index: "data/tantivy_index".into(),
+            products: "assets/products.json".into(),
+            model: MODEL_PATH.into(),
+            tokenizer: TOKENIZER_PATH.into(),
+            slow_log: "data/slow_queries.jsonl".into(),
+        }
+    }
+}
+
+impl PathsConfig {
+    fn files_mut(&mut self) -> [&mut String; 7] {
+        [
+            &mut self.db, &mut self.index, &mut self.text_index, &mut self.products,
+            &mut self.model, &mut self.tokenizer, &mut self.slow_log,
+        ]
+    }
+
+    /// 把相对路径解析到 `data_dir` 下 (绝对路径保持不变)；`data_dir` 不存在时报错
+    pub fn resolve(&mut self) -> Result<()> {
+        let Some(root) = self.data_dir.clone() else { return Ok(()) };
+        let root = Path::new(&root);
+        if !root.is_dir() {
+            bail!("Data directory {} does not exist or is not a directory", root.display());
+        }
+        for path in self.files_mut() {
+            if Path::new(path.as_str()).is_relative() {
+                *path = root.join(path.as_str()).to_string_lossy().into_owned();
+            }
+        }
+        Ok(())
+    }
+
+    /// 创建可写文件 (数据库、索引、慢查询日志) 所在的目录
+    pub fn create_dirs(&self) -> Result<()> {
+        for path in [&self.db, &self.index, &self.text_index, &self.slow_log] {
+            let Some(parent) = Path::new(path).parent().filter(|p| !p.as_os_str().is_empty()) else { continue };
+            std::fs::create_dir_all(parent).with_context(|| format!("Failed to create directory {}", parent.display()))?;
+        }
+        Ok(())
+    }
+}
+
+/// 命令行中的路径覆盖，优先级高于配置文件与环境变量
+#[derive(Debug, Clone, Default, clap::Args)]
+pub struct PathOverrides {
+    /// 数据根目录: 相对路径 (数据库、索引、物品目录、模型) 都以它为基准
+    #[arg(long, global = true)]
+    pub data_dir: Option<String>,
+    /// Sled 数据库目录
+    #[arg(long, global = true)]
+    pub db: Option<String>,
+    /// HNSW 索引文件
+    #[arg(long, global = true)]
+    pub index: Option<String>,
+    /// Tantivy 全文索引目录
+    #[arg(long, global = true)]
+    pub text_index: Option<String>,
+    /// 数据库为空时导入的物品目录 (JSON)
+    #[arg(long, global = true)]
+    pub products: Option<String>,
+    /// ONNX 模型文件
+    #[arg(long, global = true)]
+    pub model: Option<String>,
+    /// tokenizer.json
+    #[arg(long, global = true)]
+    pub tokenizer: Option<String>,
+}
+
+impl PathOverrides {
+    fn apply(&self, paths: &mut PathsConfig) {
+        let fields = [
+            (&self.db, &mut paths.db), (&self.index, &mut paths.index),
+            (&self.text_index, &mut paths.text_index), (&self.products, &mut paths.products),
+            (&self.model, &mut paths.model), (&self.tokenizer, &mut paths.tokenizer),
+        ];
+        for (value, field) in fields {
+            if let Some(value) = value {
+                *field = value.clone();
+            }
+        }
+        if self.data_dir.is_some() {
+            paths.data_dir = self.data_dir.clone();
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EmbeddingConfig {
+    /// 向量维度，必须与 ONNX 模型输出一致
+    pub dim: usize,
+    /// ONNX Session 数量，决定启动编码与请求编码的并发度；0 = 自动 (CPU 核数，最多 4 个)
+    pub sessions: usize,
+}
+
+impl Default for EmbeddingConfig {
+    fn default() -> Self {
+        Self { dim: DIM, sessions: 0 }
+    }
+}
+
+impl EmbeddingConfig {
+    /// 自动模式下的 Session 上限 (每个 Session 各占一份模型内存)
+    const AUTO_MAX_SESSIONS: usize = 4;
+
+    /// 实际创建的 Session 数量
+    pub fn session_count(&self) -> usize {
+        match self.sessions {
+            0 => std::thread::available_parallelism()
+                .map_or(1, |n| n.get())
+                .min(Self::AUTO_MAX_SESSIONS),
+            n => n,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HnswParams {
+    /// 仅在新建索引时生效
+    pub m: usize,
+    /// 仅在新建索引时生效
+    pub ef_construction: usize,
+    pub ef_search: usize,
+    /// 新建索引时在物品数之外预留的容量
+    pub headroom: usize,
+}
+
+impl Default for HnswParams {
+    fn default() -> Self {
+        Self { m: 16, ef_construction: 200, ef_search: 100, headroom: 1000 }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RequestLogConfig {
+    /// 请求分析日志保留时长
+    pub retention_hours: u64,
+}
+
+impl Default for RequestLogConfig {
+    fn default() -> Self {
+        Self { retention_hours: 7 * 24 }
+    }
+}
+
+/// 交互事件 topic 的消费配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct KafkaConfig {
+    /// 逗号分隔的 broker 地址，例如 `localhost:9092`
+    pub brokers: String,
+    pub topic: String,
+    #[serde(default = "default_kafka_group_id")]
+    pub group_id: String,
+    /// 消费组第一次启动时从 topic 最早的消息开始 (默认只消费新消息)
+    #[serde(default)]
+    pub from_beginning: bool,
+}
+
+fn default_kafka_group_id() -> String {
+    "mini-recsys".into()
+}
+
+/// Redis 推荐结果缓存
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CacheConfig {
+    /// 例如 `redis://127.0.0.1:6379/0`
+    pub redis_url: String,
+    #[serde(default = "default_cache_ttl_secs")]
+    pub ttl_secs: u64,
+    /// 键前缀，多个部署共用一个 Redis 时用来隔离
+    #[serde(default = "default_cache_prefix")]
+    pub prefix: String,
+}
+
+fn default_cache_ttl_secs() -> u64 {
+    60
+}
+
+fn default_cache_prefix() -> String {
+    "mini-recsys:".into()
+}
+
+/// 外部重排服务
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RerankConfig {
+    pub url: String,
+    /// 设置后请求带 `Authorization: Bearer <token>`
+    pub bearer_token: Option<String>,
+    /// 交给外部服务的候选数
+    #[serde(default = "default_rerank_top_k")]
+    pub top_k: usize,
+    /// 超时后沿用本地排序
+    #[serde(default = "default_rerank_timeout_ms")]
+    pub timeout_ms: u64,
+    /// 是否重排 /recommend
+    #[serde(default = "default_true")]
+    pub recommend: bool,
+    /// 是否重排 /search
+    #[serde(default = "default_true")]
+    pub search: bool,
+}
+
+impl RerankConfig {
+    pub fn timeout(&self) -> std::time::Duration {
+        std::time::Duration::from_millis(self.timeout_ms)
+    }
+}
+
+fn default_rerank_top_k() -> usize {
+    20
+}
+
+fn default_rerank_timeout_ms() -> u64 {
+    300
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// 交互与曝光记录的落地方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SinkKind {
+    /// 本地数据库；离线评估、重放、训练样本导出只读取这里
+    Sled,
+    /// `[events.postgres]` (需要 `postgres` feature)
+    Postgres,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EventsConfig {
+    /// 同时写入所列的每一个落地方式
+    pub sinks: Vec<SinkKind>,
+    pub postgres: Option<PostgresSinkConfig>,
+}
+
+impl Default for EventsConfig {
+    fn default() -> Self {
+        Self { sinks: vec![SinkKind::Sled], postgres: None }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PostgresSinkConfig {
+    /// 例如 `postgres://recsys:secret@db:5432/analytics`
+    pub url: String,
+    #[serde(default = "default_postgres_connections")]
+    pub max_connections: u32,
+}
+
+fn default_postgres_connections() -> u32 {
+    4
+}
+
+/// OTLP 导出 (需要 `otel` feature)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TelemetryConfig {
+    /// OTLP/gRPC 地址，例如 `http://otel-collector:4317`；不设置时取 `OTEL_EXPORTER_OTLP_ENDPOINT`
+    pub otlp_endpoint: Option<String>,
+    /// 上报的 `service.name`
+    pub service_name: String,
+    /// 指标上报周期 (秒)
+    pub metrics_interval_secs: u64,
+}
+
+impl Default for TelemetryConfig {
+    fn default() -> Self {
+        Self { otlp_endpoint: None, service_name: "mini-recsys".into(), metrics_interval_secs: 60 }
+    }
+}
+
+impl TelemetryConfig {
+    /// 导出地址: 配置优先，其次 `OTEL_EXPORTER_OTLP_ENDPOINT`；都没有时不导出
+    pub fn endpoint(&self) -> Option<String> {
+        self.otlp_endpoint.clone()
+            .or_else(|| std::env::var("OTEL_EXPORTER_OTLP_ENDPOINT").ok())
+            .filter(|endpoint| !endpoint.is_empty())
+    }
+}
+
+/// S3 兼容对象存储 (AWS S3、MinIO、R2 等)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RemoteConfig {
+    pub bucket: String,
+    /// 非 AWS 的服务地址，例如 `http://minio:9000`；不设置时使用 AWS
+    pub endpoint: Option<String>,
+    #[serde(default = "default_remote_region")]
+    pub region: String,
+    /// 对象键前缀，多个部署共用一个桶时用来隔离
+    #[serde(default = "default_remote_prefix")]
+    pub prefix: String,
+    /// 不设置时读取 `AWS_ACCESS_KEY_ID` / `AWS_SECRET_ACCESS_KEY`
+    pub access_key_id: Option<String>,
+    pub secret_access_key: Option<String>,
+    /// 允许 `http://` 地址 (本地 MinIO)
+    #[serde(default)]
+    pub allow_http: bool,
+}
+
+fn default_remote_region() -> String {
+    "us-east-1".into()
+}
+
+fn default_remote_prefix() -> String {
+    "mini-recsys/".into()
+}
+
+/// 一个 Webhook 订阅 (`[[webhooks]]`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WebhookConfig {
+    pub url: String,
+    /// 设置后请求带 `X-Recsys-Signature` 签名头
+    pub secret: Option<String>,
+    /// 订阅的事件 (`item.created` / `item.updated` / `item.deleted` / `feedback`)；为空时订阅全部
+    #[serde(default)]
+    pub events: Vec<String>,
+    /// 失败后的重试次数 (间隔 1s、2s、4s...)
+    #[serde(default = "default_webhook_retries")]
+    pub retries: u32,
+}
+
+fn default_webhook_retries() -> u32 {
+    3
+}
+
+/// 内存上限 (ONNX Session 数见 `embedding.sessions`，每个 Session 各占一份模型内存)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MemoryConfig {
+    /// 向量常驻内存的物品数上限；超出的物品只在内存中保留元数据，向量按需从数据库读取。0 = 不限制
+    pub max_items: usize,
+    /// 编码结果缓存条数 (搜索查询、单条导入的标题)；0 = 关闭
+    pub embedding_cache: usize,
+}
+
+impl Default for MemoryConfig {
+    fn default() -> Self {
+        Self { max_items: 0, embedding_cache: 1024 }
+    }
+}
+
+/// /recommend 翻页 (`page_size` / `page_token`) 的快照参数
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PaginationConfig {
+    /// 第一页召回并保存的结果数，即可翻到的结果总数
+    pub max_results: usize,
+    /// 快照保留时长，过期后令牌失效
+    pub ttl_secs: u64,
+    /// 同时保留的快照数上限，超出时淘汰最早的快照
+    pub max_snapshots: usize,
+}
+
+impl Default for PaginationConfig {
+    fn default() -> Self {
+        Self { max_results: 100, ttl_secs: 300, max_snapshots: 10_000 }
+    }
+}
+
+impl PaginationConfig {
+    pub fn ttl(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.ttl_secs)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SearchConfig {
+    /// 请求未指定 `fusion` 时使用的融合策略
+    pub fusion: FusionStrategy,
+}
+
+/// 近期曝光降权: 最近返回过但未被点击的物品在下一次推荐中排名靠后
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ImpressionsConfig {
+    /// 曝光保留时长，过期后物品恢复原来的排序
+    pub ttl_secs: u64,
+    /// 分数压低的比例 (0..=1)；默认 0 = 关闭 (开启后有近期曝光的用户不走共享缓存)
+    pub penalty: f32,
+    /// 同时跟踪的用户数上限，超出时淘汰最久未曝光的用户
+    pub max_users: usize,
+}
+
+impl Default for ImpressionsConfig {
+    fn default() -> Self {
+        Self { ttl_secs: 600, penalty: 0.0, max_users: 100_000 }
+    }
+}
+
+impl ImpressionsConfig {
+    pub fn ttl(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.ttl_secs)
+    }
+}
+
+/// 按曝光与点击计算热门度 (见 `popularity.rs`)，由 `jobs.popularity_refresh` 定期重算
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PopularityConfig {
+    /// Wilson 区间的置信分位数: 越大对曝光少的物品越保守 (1.96 ≈ 95%)
+    pub z: f64,
+}
+
+impl Default for PopularityConfig {
+    fn default() -> Self {
+        Self { z: 1.96 }
+    }
+}
+
+/// 相似用户召回 (见 `lookalike.rs`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LookalikeConfig {
+    /// 从最相似的多少个用户的历史中召回；0 = 关闭
+    pub neighbors: usize,
+    /// 每个用户在内存中保留的最近交互物品数
+    pub history_len: usize,
+    /// 召回分数 = 用户相似度 × weight，与内容相似度 (sim_score) 在同一尺度上比较
+    pub weight: f32,
+}
+
+impl Default for LookalikeConfig {
+    fn default() -> Self {
+        Self { neighbors: 10, history_len: 50, weight: 0.8 }
+    }
+}
+
+/// 多语言标题 (见 `i18n.rs`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct I18nConfig {
+    /// 物品 `title` 字段的语言；未协商出其他语言时展示它
+    pub default_lang: String,
+    /// 可通过 `?lang=` / Accept-Language 选择的展示语言
+    pub languages: Vec<String>,
+    /// 编码物品向量时使用哪种语言的标题 (与模型的语言一致)；为 None 时使用 `title`
+    pub encode_lang: Option<String>,
+}
+
+impl Default for I18nConfig {
+    fn default() -> Self {
+        Self { default_lang: "en".into(), languages: vec!["en".into(), "zh".into()], encode_lang: None }
+    }
+}
+
+impl I18nConfig {
+    /// 规范化后的可选语言 (总是包含 `default_lang`)
+    pub fn supported(&self) -> Vec<String> {
+        let mut supported = vec![i18n::normalize(&self.default_lang)];
+        for lang in &self.languages {
+            let lang = i18n::normalize(lang);
+            if !supported.contains(&lang) {
+                supported.push(lang);
+            }
+        }
+        supported
+    }
+
+    /// 规范化后的编码语言
+    pub fn encode_lang(&self) -> Option<String> {
+        self.encode_lang.as_deref().map(i18n::normalize)
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DemoConfig {
+    /// 演示数据 (类别向量噪声、演示用户向量) 的随机种子；不设置时每次启动都不同
+    pub seed: Option<u64>,
+}
+
+impl Config {
+    /// 按 默认值 <- 配置文件 <- 环境变量 的顺序加载
+    pub fn load() -> Result<Self> {
+        Self::load_with(&PathOverrides::default())
+    }
+
+    /// 同 `load`，最后再应用命令行路径覆盖，并把相对路径解析到 `paths.data_dir` 下
+    pub fn load_with(overrides: &PathOverrides) -> Result<Self> {
+        Self::load_from(std::env::var("RECSYS_CONFIG").ok().as_deref(), overrides)
+    }
+
+    /// 同 `load_with`，但配置文件由调用方指定 (不读取 `RECSYS_CONFIG`)；
+    /// 为 None 时读取当前目录下的 `config.toml`，不存在则跳过
+    pub fn load_from(explicit: Option<&str>, overrides: &PathOverrides) -> Result<Self> {
+        let path = explicit.unwrap_or(DEFAULT_CONFIG_FILE);
+
+        let mut table = default_table()?;
+        if Path::new(path).exists() {
+            let content = std::fs::read_to_string(path).with_context(|| format!("Failed to read config {}", path))?;
+            let file: toml::Table = content.parse().with_context(|| format!("Failed to parse config {}", path))?;
+            merge(&mut table, file);
+        } else if explicit.is_some() {
+            bail!("Config file {} not found", path);
+        }
+        apply_env(&mut table, std::env::vars())?;
+
+        let mut config: Config = toml::Value::Table(table).try_into().context("Invalid config")?;
+        overrides.apply(&mut config.paths);
+        config.paths.resolve()?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        let w = self.ranking;
+        if !(w.sim.is_finite() && w.popularity.is_finite()) {
+            bail!("ranking weights must be finite");
+        }
+        if self.recall.k == 0 || self.recall.max_k < self.recall.k {
+            bail!("recall.k must be positive and no larger than recall.max_k");
+        }
+        if self.recall.max_k > 10_000 {
+            bail!("recall.max_k must be at most 10000");
+        }
+        if self.hnsw.ef_search == 0 || self.hnsw.m == 0 {
+            bail!("hnsw.m and hnsw.ef_search must be positive");
+        }
+        if !(self.bloom.fpr > 0.0 && self.bloom.fpr < 1.0) {
+            bail!("bloom.fpr must be in (0, 1)");
+        }
+        if self.server.cors_origin.is_empty() || !self.server.cors_origin.bytes().all(|b| b.is_ascii_graphic()) {
+            bail!("server.cors_origin must be a non-empty origin without spaces");
+        }
+        if self.server.tls.is_some() && !cfg!(feature = "tls") {
+            bail!("server.tls is set but mini-recsys was built without the `tls` feature");
+        }
+        if self.kafka.is_some() && !cfg!(feature = "kafka") {
+            bail!("kafka is set but mini-recsys was built without the `kafka` feature");
+        }
+        if let Some(cache) = &self.cache {
+            if !cfg!(feature = "redis-cache") {
+                bail!("cache is set but mini-recsys was built without the `redis-cache` feature");
+            }
+            if cache.ttl_secs == 0 {
+                bail!("cache.ttl_secs must be positive");
+            }
+        }
+        self.csv.validate()?;
+        if !(1..=1000).contains(&self.pagination.max_results) {
+            bail!("pagination.max_results must be between 1 and 1000");
+        }
+        if self.pagination.ttl_secs == 0 || self.pagination.max_snapshots == 0 {
+            bail!("pagination.ttl_secs and pagination.max_snapshots must be positive");
+        }
+        if !(0.0..=1.0).contains(&self.impressions.penalty) {
+            bail!("impressions.penalty must be in [0, 1]");
+        }
+        if !(self.popularity.z.is_finite() && self.popularity.z > 0.0) {
+            bail!("popularity.z must be positive");
+        }
+        if !(0.0..=1.0).contains(&self.lookalike.weight) {
+            bail!("lookalike.weight must be in [0, 1]");
+        }
+        if self.i18n.default_lang.trim().is_empty() || self.i18n.languages.iter().any(|lang| lang.trim().is_empty()) {
+            bail!("i18n.default_lang and i18n.languages must not contain empty language tags");
+        }
+        if let Some(rerank) = &self.rerank {
+            if !cfg!(feature = "rerank") {
+                bail!("rerank is set but mini-recsys was built without the `rerank` feature");
+            }
+            if !rerank.url.starts_with("http://") && !rerank.url.starts_with("https://") {
+                bail!("rerank.url must be an http(s) URL, got {}", rerank.url);
+            }
+            if rerank.top_k < 2 || rerank.timeout_ms == 0 {
+                bail!("rerank.top_k must be at least 2 and rerank.timeout_ms positive");
+            }
+        }
+        if self.events.sinks.is_empty() {
+            bail!("events.sinks must not be empty");
+        }
+        if self.events.sinks.contains(&SinkKind::Postgres) {
+            if !cfg!(feature = "postgres") {
+                bail!("events.sinks contains postgres but mini-recsys was built without the `postgres` feature");
+            }
+            if self.events.postgres.is_none() {
+                bail!("events.sinks contains postgres but [events.postgres] is missing");
+            }
+        }
+        if self.telemetry.otlp_endpoint.is_some() && !cfg!(feature = "otel") {
+            bail!("telemetry.otlp_endpoint is set but mini-recsys was built without the `otel` feature");
+        }
+        if self.telemetry.metrics_interval_secs == 0 {
+            bail!("telemetry.metrics_interval_secs must be positive");
+        }
+        if self.remote.is_some() && !cfg!(feature = "s3") {
+            bail!("remote is set but mini-recsys was built without the `s3` feature");
+        }
+        if !self.webhooks.is_empty() && !cfg!(feature = "webhooks") {
+            bail!("webhooks are set but mini-recsys was built without the `webhooks` feature");
+        }
+        for hook in &self.webhooks {
+            if !(hook.url.starts_with("http://") || hook.url.starts_with("https://")) {
+                bail!("webhooks.url must be an http:// or https:// URL, got {}", hook.url);
+            }
+            if let Some(event) = hook.events.iter().find(|e| !EVENT_NAMES.contains(&e.as_str())) {
+                bail!("Unknown webhook event {:?} (expected one of {})", event, EVENT_NAMES.join(", "));
+            }
+        }
+        if self.server.grpc_bind.is_some() && !cfg!(feature = "grpc") {
+            bail!("server.grpc_bind is set but mini-recsys was built without the `grpc` feature");
+        }
+        let binds = [Some(&self.server.bind), self.server.metrics_bind.as_ref(), self.server.grpc_bind.as_ref()];
+        let binds: Vec<&String> = binds.into_iter().flatten().collect();
+        if (1..binds.len()).any(|i| binds[..i].contains(&binds[i])) {
+            bail!("server.bind, server.metrics_bind and server.grpc_bind must be different addresses");
+        }
+        if let Some(path) = self.server.unix_socket() {
+            if !cfg!(unix) {
+                bail!("server.bind = {} but Unix sockets are not supported on this platform", self.server.bind);
+            }
+            if path.is_empty() {
+                bail!("server.bind = {} is missing the socket path", self.server.bind);
+            }
+            if self.server.tls.is_some() {
+                bail!("server.tls cannot be combined with a Unix socket bind");
+            }
+        }
+        Ok(())
+    }
+}
+
+fn default_table() -> Result<toml::Table> {
+    match toml::Value::try_from(Config::default()).context("Failed to serialize default config")? {
+        toml::Value::Table(table) => Ok(table),
+        _ => bail!("default config is not a table"),
+    }
+}
+
+/// 递归合并: overlay 中的值覆盖 base 中的同名键
+fn merge(base: &mut toml::Table, overlay: toml::Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base_table)), toml::Value::Table(overlay_table)) => {
+                merge(base_table, overlay_table);
+            }
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+/// 将字符串解析为 TOML 字面量 (数字、布尔等)，失败时视为字符串
+fn parse_env_value(raw: &str) -> toml::Value {
+    format!("v = {}", raw)
+        .parse::<toml::Table>()
+        .ok()
+        .and_then(|mut t| t.remove("v"))
+        .unwrap_or_else(|| toml::Value::String(raw.to_string()))
+}
+
+/// 应用 `RECSYS__SECTION__KEY` 环境变量覆盖
+fn apply_env(table: &mut toml::Table, vars: impl IntoIterator<Item = (String, String)>) -> Result<()> {
+    for (name, raw) in vars {
+        let Some(path) = name.strip_prefix(ENV_PREFIX) else { continue };
+        let keys: Vec<String> = path.split("__").map(|k| k.to_lowercase()).collect();
+        let (last, parents) = keys.split_last().expect("split yields at least one item");
+
+        let mut current = &mut *table;
+        for key in parents {
+            current = match current.entry(key.clone()).or_insert_with(|| toml::Value::Table(Default::default())) {
+                toml::Value::Table(t) => t,
+                _ => bail!("{} does not refer to a config section", name),
+            };
+        }
+        current.insert(last.clone(), parse_env_value(&raw));
+    }
+    Ok(())
+}
+
+// ============================================================================
+// 单元测试
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jobs::Schedule;
+
+    fn build(file: &str, env: &[(&str, &str)]) -> Result<Config> {
+        let mut table = default_table()?;
+        merge(&mut table, file.parse()?);
+        apply_env(&mut table, env.iter().map(|(k, v)| (k.to_string(), v.to_string())))?;
+        Ok(toml::Value::Table(table).try_into()?)
+    }
+
+    #[test]
+    fn test_layering() {
+        let config = build(
+            "[server]\nbind = \"127.0.0.1:4000\"\n[ranking]\nsim = 0.5\n",
+            &[("RECSYS__RANKING__SIM", "0.8"), ("RECSYS__HNSW__EF_SEARCH", "256"), ("OTHER", "x")],
+        ).unwrap();
+
+        assert_eq!(config.server.bind, "127.0.0.1:4000");
+        assert_eq!(config.server.cors_origin, ServerConfig::default().cors_origin);
+        assert!((config.ranking.sim - 0.8).abs() < 1e-6); // 环境变量优先于文件
+        assert!((config.ranking.popularity - 0.3).abs() < 1e-6);
+        assert_eq!(config.hnsw.ef_search, 256);
+    }
+
+    #[test]
+    fn test_optional_tls_section() {
+        assert!(build("", &[]).unwrap().server.tls.is_none());
+
+        let config = build("[server.tls]\ncert = \"certs/cert.pem\"\nkey = \"certs/key.pem\"\n", &[]).unwrap();
+        let tls = config.server.tls.unwrap();
+        assert_eq!((tls.cert.as_str(), tls.key.as_str()), ("certs/cert.pem", "certs/key.pem"));
+    }
+
+    #[test]
+    fn test_unix_socket_bind() {
+        let config = build("[server]\nbind = \"unix:/tmp/recsys.sock\"\n", &[]).unwrap();
+        assert_eq!(config.server.unix_socket(), Some("/tmp/recsys.sock"));
+        assert_eq!(Config::default().server.unix_socket(), None);
+    }
+
+    #[test]
+    fn test_listener_binds_must_differ() {
+        let config = build("[server]\nmetrics_bind = \"127.0.0.1:9100\"\n", &[]).unwrap();
+        assert!(config.validate().is_ok());
+
+        let config = build("[server]\nbind = \"127.0.0.1:9100\"\nmetrics_bind = \"127.0.0.1:9100\"\n", &[]).unwrap();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_webhooks() {
+        let toml = "[[webhooks]]\nurl = \"https://hooks.example.com/recsys\"\nevents = [\"item.created\", \"feedback\"]\n";
+        let config = build(toml, &[]).unwrap();
+        assert_eq!(config.webhooks.len(), 1);
+        assert_eq!(config.webhooks[0].retries, 3);
+        assert_eq!(config.validate().is_ok(), cfg!(feature = "webhooks"));
+
+        let config = build("[[webhooks]]\nurl = \"https://hooks.example.com\"\nevents = [\"item.removed\"]\n", &[]).unwrap();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_rerank() {
+        let config = build("[rerank]\nurl = \"http://reranker:8000/rerank\"\nsearch = false\n", &[]).unwrap();
+        let rerank = config.rerank.as_ref().unwrap();
+        assert_eq!((rerank.top_k, rerank.timeout_ms, rerank.recommend, rerank.search), (20, 300, true, false));
+        assert_eq!(config.validate().is_ok(), cfg!(feature = "rerank"));
+
+        assert!(build("[rerank]\nurl = \"reranker:8000\"\n", &[]).unwrap().validate().is_err());
+    }
+
+    #[test]
+    fn test_event_sinks() {
+        let config = build("", &[]).unwrap();
+        assert_eq!(config.events.sinks, vec![SinkKind::Sled]);
+
+        let toml = "[events]\nsinks = [\"sled\", \"postgres\"]\n[events.postgres]\nurl = \"postgres://localhost/analytics\"\n";
+        let config = build(toml, &[]).unwrap();
+        assert_eq!(config.events.postgres.as_ref().unwrap().max_connections, 4);
+        assert_eq!(config.validate().is_ok(), cfg!(feature = "postgres"));
+
+        assert!(build("[events]\nsinks = [\"postgres\"]\n", &[]).unwrap().validate().is_err());
+        assert!(build("[events]\nsinks = [\"kafka\"]\n", &[]).is_err());
+    }
+
+    #[test]
+    fn test_telemetry() {
+        let config = build("", &[("RECSYS__TELEMETRY__OTLP_ENDPOINT", "http://collector:4317")]).unwrap();
+        assert_eq!(config.telemetry.endpoint().as_deref(), Some("http://collector:4317"));
+        assert_eq!(config.telemetry.service_name, "mini-recsys");
+        assert_eq!(config.validate().is_ok(), cfg!(feature = "otel"));
+
+        assert!(build("[telemetry]\nmetrics_interval_secs = 0\n", &[]).unwrap().validate().is_err());
+    }
+
+    #[test]
+    fn test_remote() {
+        let config = build("[remote]\nbucket = \"recsys\"\n", &[]).unwrap();
+        let remote = config.remote.as_ref().unwrap();
+        assert_eq!(remote.region, "us-east-1");
+        assert_eq!(remote.prefix, "mini-recsys/");
+        assert_eq!(config.validate().is_ok(), cfg!(feature = "s3"));
+    }
+
+    #[test]
+    fn test_data_dir_resolution() {
+        let root = std::env::temp_dir();
+        let overrides = PathOverrides {
+            data_dir: Some(root.to_string_lossy().into_owned()),
+            products: Some("/srv/catalog.json".into()),
+            ..PathOverrides::default()
+        };
+        let mut paths = PathsConfig::default();
+        overrides.apply(&mut paths);
+        paths.resolve().unwrap();
+        assert_eq!(Path::new(&paths.db), root.join("data/db"));
+        assert_eq!(paths.products, "/srv/catalog.json"); // 绝对路径不变
+
+        let mut missing = PathsConfig { data_dir: Some("/nonexistent/recsys".into()), ..PathsConfig::default() };
+        assert!(missing.resolve().is_err());
+    }
+
+    #[test]
+    fn test_job_schedules() {
+        let config = build("[jobs]\nindex_save = \"off\"\n", &[("RECSYS__JOBS__REQUEST_LOG_PRUNE", "30m")]).unwrap();
+        assert_eq!(config.jobs.index_save, Schedule::Off);
+        assert_eq!(config.jobs.request_log_prune, Schedule::every_secs(30 * 60));
+        assert_eq!(config.jobs.history_prune, Schedule::every_secs(60 * 60));
+        assert_eq!(config.bloom.ttl_days, 0);
+
+        let config = build("[bloom]\nexpected_items = 10000\nfpr = 0.01\nhashes = 7\n", &[("RECSYS__BLOOM__TTL_DAYS", "30")]).unwrap();
+        assert_eq!(config.bloom.ttl_days, 30);
+
+        assert!(build("[jobs]\nindex_save = \"soon\"\n", &[]).is_err());
+    }
+
+    #[test]
+    fn test_pagination() {
+        let config = build("[pagination]\nttl_secs = 60\n", &[]).unwrap();
+        assert_eq!(config.pagination.ttl(), std::time::Duration::from_secs(60));
+        assert_eq!(config.pagination.max_results, 100);
+        assert!(config.validate().is_ok());
+
+        assert!(build("[pagination]\nmax_results = 0\n", &[]).unwrap().validate().is_err());
+    }
+
+    #[test]
+    fn test_impressions() {
+        assert_eq!(build("", &[]).unwrap().impressions.penalty, 0.0);
+        let config = build("", &[("RECSYS__IMPRESSIONS__PENALTY", "0.5")]).unwrap();
+        assert_eq!(config.impressions.penalty, 0.5);
+        assert!(config.validate().is_ok());
+
+        assert!(build("[impressions]\npenalty = 1.5\n", &[]).unwrap().validate().is_err());
+    }
+
+    #[test]
+    fn test_recall() {
+        let config = build("[recall]\nk = 200\n", &[("RECSYS__RECALL__MIN_RESULTS", "0")]).unwrap();
+        assert_eq!((config.recall.k, config.recall.max_k, config.recall.min_results), (200, 1000, 0));
+        assert!(config.validate().is_ok());
+
+        assert!(build("[recall]\nk = 0\n", &[]).unwrap().validate().is_err());
+        assert!(build("[recall]\nk = 500\nmax_k = 400\n", &[]).unwrap().validate().is_err());
+    }
+
+    #[test]
+    fn test_search_fusion() {
+        assert_eq!(Config::default().search.fusion, FusionStrategy::Rrf);
+        let config = build("[search]\nfusion = \"minmax\"\n", &[]).unwrap();
+        assert_eq!(config.search.fusion, FusionStrategy::MinMax);
+        let config = build("", &[("RECSYS__SEARCH__FUSION", "zscore")]).unwrap();
+        assert_eq!(config.search.fusion, FusionStrategy::ZScore);
+        assert!(build("[search]\nfusion = \"borda\"\n", &[]).is_err());
+    }
+
+    #[test]
+    fn test_popularity() {
+        let config = build("[popularity]\nz = 2.58\n", &[]).unwrap();
+        assert!((config.popularity.z - 2.58).abs() < 1e-9);
+        assert!(config.validate().is_ok());
+
+        assert!(build("", &[("RECSYS__POPULARITY__Z", "0")]).unwrap().validate().is_err());
+    }
+
+    #[test]
+    fn test_lookalike() {
+        let config = build("[lookalike]\nneighbors = 0\n", &[("RECSYS__LOOKALIKE__WEIGHT", "0.5")]).unwrap();
+        assert_eq!(config.lookalike.neighbors, 0);
+        assert_eq!(config.lookalike.history_len, 50);
+        assert!(config.validate().is_ok());
+
+        assert!(build("[lookalike]\nweight = 2.0\n", &[]).unwrap().validate().is_err());
+    }
+
+    #[test]
+    fn test_i18n() {
+        let config = build("[i18n]\ndefault_lang = \"zh_CN\"\nlanguages = [\"en\", \"zh-cn\"]\nencode_lang = \"EN\"\n", &[]).unwrap();
+        assert_eq!(config.i18n.supported(), vec!["zh-cn", "en"]);
+        assert_eq!(config.i18n.encode_lang().as_deref(), Some("en"));
+        assert!(config.validate().is_ok());
+
+        assert!(build("[i18n]\ndefault_lang = \" \"\n", &[]).unwrap().validate().is_err());
+    }
+
+    #[test]
+    fn test_env_strings_and_unknown_keys() {
+        let config = build("", &[("RECSYS__PATHS__DB", "/var/lib/recsys/db")]).unwrap();
+        assert_eq!(config.paths.db, "/var/lib/recsys/db");
+
+        assert!(build("[server]\nport = 1\n", &[]).is_err());
+    }
+}
@@ -0,0 +1,209 @@
+//! CSV 物品目录解析 - 按 `[csv]` 中的列名映射到物品字段，逐行校验
+//!
+//! 有问题的行不会中断导入: 每行的错误 (行号 + 原因) 汇总在 `CsvCatalog::errors` 中，
+//! 其余行照常导入。表头缺少必需列时整个文件报错。
+//! 形如 `<title 列名>_<语言>` 的列 (例如 `title_zh`) 是该语言的标题，单元格为空时跳过。
+
+use crate::model::ItemJson;
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::io::Read;
+
+/// CSV 格式与列名映射 (`[csv]`)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CsvConfig {
+    /// 单字节分隔符，例如 `","`、`";"`、`"\t"`
+    pub delimiter: char,
+    pub id: String,
+    pub title: String,
+    pub category: String,
+    /// 可选列: 文件中没有这一列时图片地址为空
+    pub image_url: String,
+    pub price: String,
+}
+
+impl Default for CsvConfig {
+    fn default() -> Self {
+        Self {
+            delimiter: ',',
+            id: "id".into(),
+            title: "title".into(),
+            category: "category".into(),
+            image_url: "image_url".into(),
+            price: "price".into(),
+        }
+    }
+}
+
+impl CsvConfig {
+    pub fn validate(&self) -> Result<()> {
+        if !self.delimiter.is_ascii() || self.delimiter == '"' {
+            bail!("csv.delimiter must be a single ASCII character other than '\"'");
+        }
+        Ok(())
+    }
+}
+
+/// 一行的错误；`line` 为文件中的行号 (表头为第 1 行)
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RowError {
+    pub line: u64,
+    pub message: String,
+}
+
+/// 解析结果: 通过校验的物品与各行的错误
+#[derive(Debug, Default)]
+pub struct CsvCatalog {
+    pub items: Vec<ItemJson>,
+    pub errors: Vec<RowError>,
+}
+
+/// 各字段所在的列号
+struct Columns {
+    id: usize,
+    title: usize,
+    category: usize,
+    image_url: Option<usize>,
+    price: usize,
+    /// (语言, 列号)
+    titles: Vec<(String, usize)>,
+}
+
+impl Columns {
+    fn locate(headers: &csv::StringRecord, config: &CsvConfig) -> Result<Self> {
+        let find = |name: &str| headers.iter().position(|h| h.trim() == name);
+        let require = |field: &str, name: &str| {
+            find(name).ok_or_else(|| anyhow!("CSV header has no {:?} column (mapped to {}, see csv.{})", name, field, field))
+        };
+        Ok(Self {
+            id: require("id", &config.id)?,
+            title: require("title", &config.title)?,
+            category: require("category", &config.category)?,
+            image_url: find(&config.image_url),
+            price: require("price", &config.price)?,
+            titles: headers.iter().enumerate()
+                .filter_map(|(i, h)| {
+                    let lang = h.trim().strip_prefix(config.title.as_str())?.strip_prefix('_')?;
+                    (!lang.is_empty()).then(|| (lang.to_string(), i))
+                })
+                .collect(),
+        })
+    }
+
+    fn parse(&self, record: &csv::StringRecord) -> Result<ItemJson, String> {
+        let field = |i: usize| record.get(i).map(str::trim).unwrap_or_default();
+
+        let id = field(self.id).parse::<u64>()
+            .map_err(|_| format!("invalid id {:?}", field(self.id)))?;
+        let name = field(self.title);
+        if name.is_empty() {
+            return Err("title is empty".into());
+        }
+        let category = field(self.category);
+        if category.is_empty() {
+            return Err("category is empty".into());
+        }
+        let price = field(self.price).parse::<f32>()
+            .ok()
+            .filter(|p| p.is_finite() && *p >= 0.0)
+            .ok_or_else(|| format!("invalid price {:?}", field(self.price)))?;
+
+        Ok(ItemJson {
+            id,
+            name: name.to_string(),
+            category: category.to_string(),
+            image_url: self.image_url.map(field).unwrap_or_default().to_string(),
+            price,
+            titles: self.titles.iter()
+                .map(|(lang, i)| (lang.clone(), field(*i).to_string()))
+                .filter(|(_, title)| !title.is_empty())
+                .collect(),
+        })
+    }
+}
+
+/// 解析整个 CSV 文件
+pub fn parse_catalog(input: impl Read, config: &CsvConfig) -> Result<CsvCatalog> {
+    config.validate()?;
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(config.delimiter as u8)
+        .flexible(true)
+        .from_reader(input);
+    let columns = Columns::locate(reader.headers().context("Failed to read CSV header")?, config)?;
+
+    let mut catalog = CsvCatalog::default();
+    let mut seen = HashSet::new();
+    for record in reader.records() {
+        let record = match record {
+            Ok(record) => record,
+            Err(e) => {
+                let line = e.position().map_or(0, |p| p.line());
+                catalog.errors.push(RowError { line, message: e.to_string() });
+                continue;
+            }
+        };
+        let line = record.position().map_or(0, |p| p.line());
+        match columns.parse(&record) {
+            Ok(item) if !seen.insert(item.id) => {
+                catalog.errors.push(RowError { line, message: format!("duplicate id {}", item.id) });
+            }
+            Ok(item) => catalog.items.push(item),
+            Err(message) => catalog.errors.push(RowError { line, message }),
+        }
+    }
+    Ok(catalog)
+}
+
+// ============================================================================
+// 单元测试
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_with_mapping_and_row_errors() {
+        let config = CsvConfig {
+            delimiter: ';',
+            id: "sku".into(),
+            title: "product_name".into(),
+            price: "price_eur".into(),
+            ..CsvConfig::default()
+        };
+        let csv = "sku;product_name;category;price_eur\n\
+                   1;Desk Lamp;Home;19.90\n\
+                   x2;Chair;Home;49\n\
+                   3;;Home;5\n\
+                   4;Rug;Home;-1\n\
+                   1;Desk Lamp v2;Home;21\n\
+                   5;Mug;Kitchen;4.5\n";
+        let catalog = parse_catalog(csv.as_bytes(), &config).unwrap();
+
+        let ids: Vec<u64> = catalog.items.iter().map(|i| i.id).collect();
+        assert_eq!(ids, vec![1, 5]);
+        assert_eq!(catalog.items[0].name, "Desk Lamp");
+        assert_eq!(catalog.items[0].image_url, "");
+
+        let lines: Vec<u64> = catalog.errors.iter().map(|e| e.line).collect();
+        assert_eq!(lines, vec![3, 4, 5, 6]);
+        assert!(catalog.errors[0].message.contains("invalid id"));
+        assert_eq!(catalog.errors[3].message, "duplicate id 1");
+    }
+
+    #[test]
+    fn test_localized_title_columns() {
+        let csv = "id,title,title_zh,category,price\n1,Desk Lamp,台灯,Home,19.9\n2,Mug,,Kitchen,4.5\n";
+        let catalog = parse_catalog(csv.as_bytes(), &CsvConfig::default()).unwrap();
+        assert_eq!(catalog.items[0].titles.get("zh").map(String::as_str), Some("台灯"));
+        assert!(catalog.items[1].titles.is_empty());
+    }
+
+    #[test]
+    fn test_missing_required_column() {
+        let err = parse_catalog("id,title,price\n1,Lamp,3\n".as_bytes(), &CsvConfig::default()).unwrap_err();
+        assert!(err.to_string().contains("\"category\""));
+    }
+}
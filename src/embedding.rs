@@ -1,38 +1,122 @@
 //! Embedding 模块 - 使用 ONNX Runtime 进行语义向量化
+//!
+//! 未启用 `onnx` feature 时 `EmbeddingModel` 无法构造 (加载总是返回错误)，
+//! 调用方按"模型缺失"处理: 物品使用类别向量，搜索不可用。
 
-use anyhow::{Context, Result};
+use anyhow::Result;
+#[cfg(feature = "onnx")]
+use anyhow::Context;
+#[cfg(feature = "onnx")]
 use ndarray::{Array1, Array2};
+#[cfg(feature = "onnx")]
 use ort::session::Session;
+#[cfg(feature = "onnx")]
 use ort::value::Value;
+#[cfg(feature = "onnx")]
 use ort::inputs;
-use std::sync::Mutex;
+#[cfg(feature = "onnx")]
+use std::collections::{HashMap, VecDeque};
+#[cfg(feature = "onnx")]
+use std::sync::atomic::{AtomicUsize, Ordering};
+#[cfg(feature = "onnx")]
+use std::sync::{Mutex, MutexGuard, TryLockError};
+#[cfg(feature = "onnx")]
 use tokenizers::Tokenizer;
 
-const MODEL_PATH: &str = "models/all-MiniLM-L6-v2.onnx";
-const TOKENIZER_PATH: &str = "models/tokenizer.json";
+pub const MODEL_PATH: &str = "models/all-MiniLM-L6-v2.onnx";
+pub const TOKENIZER_PATH: &str = "models/tokenizer.json";
+#[cfg(feature = "onnx")]
 const EMBEDDING_DIM: usize = 384;
 
+#[cfg(feature = "onnx")]
 pub struct EmbeddingModel {
-    session: Mutex<Session>,
+    /// Session 池: 每个 Session 同一时刻只能跑一次推理，多个 Session 支持并发编码
+    sessions: Vec<Mutex<Session>>,
+    /// 所有 Session 都忙时轮询排队的起点
+    next: AtomicUsize,
     tokenizer: Tokenizer,
+    /// `encode` 结果缓存 (默认关闭，见 `with_cache`)
+    cache: Mutex<EncodeCache>,
 }
 
+#[cfg(feature = "onnx")]
 impl EmbeddingModel {
     pub fn new() -> Result<Self> {
-        // 初始化 Session
-        let session = Session::builder()?
-            .with_intra_threads(4)?
-            .commit_from_file(MODEL_PATH)
-            .context("Failed to load ONNX model")?;
+        Self::from_files(MODEL_PATH, TOKENIZER_PATH)
+    }
+
+    /// 从指定的 ONNX 模型与 tokenizer 文件加载 (单个 Session)
+    pub fn from_files(model_path: &str, tokenizer_path: &str) -> Result<Self> {
+        Self::from_files_with_sessions(model_path, tokenizer_path, 1)
+    }
+
+    /// 同 `from_files`，创建 `sessions` 个 Session (至少 1 个)
+    ///
+    /// 每个 Session 各自加载一份模型权重，CPU 核数在 Session 间平分作为算子内线程数。
+    pub fn from_files_with_sessions(model_path: &str, tokenizer_path: &str, sessions: usize) -> Result<Self> {
+        for path in [model_path, tokenizer_path] {
+            if !std::path::Path::new(path).is_file() {
+                anyhow::bail!("{} not found (set paths.model / paths.tokenizer or --model / --tokenizer)", path);
+            }
+        }
+        let sessions = sessions.max(1);
+        let cores = std::thread::available_parallelism().map_or(4, |n| n.get());
+        let intra_threads = (cores / sessions).max(1);
 
-        let tokenizer = Tokenizer::from_file(TOKENIZER_PATH)
+        // 初始化 Session 池
+        let sessions = (0..sessions)
+            .map(|_| {
+                let session = Session::builder()?
+                    .with_intra_threads(intra_threads)?
+                    .commit_from_file(model_path)
+                    .context("Failed to load ONNX model")?;
+                Ok(Mutex::new(session))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let tokenizer = Tokenizer::from_file(tokenizer_path)
             .map_err(|e| anyhow::anyhow!("Failed to load tokenizer: {}", e))?;
 
-        Ok(Self { session: Mutex::new(session), tokenizer })
+        Ok(Self { sessions, next: AtomicUsize::new(0), tokenizer, cache: Mutex::new(EncodeCache::new(0)) })
+    }
+
+    /// 缓存最近 `capacity` 条 `encode` 结果 (重复的搜索查询不再推理)；0 = 不缓存
+    pub fn with_cache(self, capacity: usize) -> Self {
+        Self { cache: Mutex::new(EncodeCache::new(capacity)), ..self }
+    }
+
+    /// Session 数量 (即可同时进行的推理数)
+    pub fn sessions(&self) -> usize {
+        self.sessions.len()
     }
 
-    /// 将文本编码为语义向量 (384 维)
+    /// 取一个空闲 Session；都在忙时轮询挑一个排队等待
+    ///
+    /// Session 在两次推理之间没有状态，持锁线程 panic 导致的锁中毒可以直接忽略。
+    fn session(&self) -> MutexGuard<'_, Session> {
+        let free = self.sessions.iter().find_map(|s| match s.try_lock() {
+            Ok(guard) => Some(guard),
+            Err(TryLockError::Poisoned(e)) => Some(e.into_inner()),
+            Err(TryLockError::WouldBlock) => None,
+        });
+        free.unwrap_or_else(|| {
+            let i = self.next.fetch_add(1, Ordering::Relaxed) % self.sessions.len();
+            self.sessions[i].lock().unwrap_or_else(|e| e.into_inner())
+        })
+    }
+
+    /// 将文本编码为语义向量 (384 维)，命中缓存时直接返回
     pub fn encode(&self, text: &str) -> Result<Vec<f32>> {
+        if let Some(hit) = self.cache.lock().unwrap_or_else(|e| e.into_inner()).get(text) {
+            return Ok(hit);
+        }
+        let vector = self.encode_uncached(text)?;
+        self.cache.lock().unwrap_or_else(|e| e.into_inner()).insert(text, &vector);
+        Ok(vector)
+    }
+
+    #[tracing::instrument(name = "encode", level = "debug", skip_all, fields(chars = text.len()))]
+    fn encode_uncached(&self, text: &str) -> Result<Vec<f32>> {
         // Step A: Tokenize
         let encoding = self.tokenizer
             .encode(text, true)
@@ -50,7 +134,7 @@ impl EmbeddingModel {
         let token_type_ids_val = Value::from_array((vec![1usize, seq_len], token_type_ids))?;
 
         // Step C: 运行推理
-        let mut session = self.session.lock().map_err(|_| anyhow::anyhow!("Failed to lock ONNX session"))?;
+        let mut session = self.session();
         let outputs = session.run(inputs![
             "input_ids" => input_ids_val,
             "attention_mask" => attention_mask_val,
@@ -86,7 +170,180 @@ impl EmbeddingModel {
         Ok(normalized)
     }
 
+    /// 批量编码: 按最长序列补齐后一次推理，返回与输入顺序一致的向量
+    #[tracing::instrument(name = "encode_batch", level = "debug", skip_all, fields(batch = texts.len()))]
+    pub fn encode_batch(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
+        if texts.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        // Step A: Tokenize
+        let encodings = self.tokenizer
+            .encode_batch(texts.to_vec(), true)
+            .map_err(|e| anyhow::anyhow!("Tokenization failed: {}", e))?;
+
+        let batch = encodings.len();
+        let seq_len = encodings.iter().map(|e| e.get_ids().len()).max().unwrap_or(0);
+
+        // Step B: 构建 [batch, seq_len] 输入张量 (padding 位置 mask = 0)
+        // 内存布局: 第 b 条文本的第 t 个 token 位于 b * seq_len + t
+        let mut input_ids = vec![0i64; batch * seq_len];
+        let mut attention_mask = vec![0i64; batch * seq_len];
+        let mut token_type_ids = vec![0i64; batch * seq_len];
+        for (b, encoding) in encodings.iter().enumerate() {
+            let offset = b * seq_len;
+            for (t, &id) in encoding.get_ids().iter().enumerate() {
+                input_ids[offset + t] = id as i64;
+            }
+            for (t, &m) in encoding.get_attention_mask().iter().enumerate() {
+                attention_mask[offset + t] = m as i64;
+            }
+            for (t, &ty) in encoding.get_type_ids().iter().enumerate() {
+                token_type_ids[offset + t] = ty as i64;
+            }
+        }
+
+        let input_ids_val = Value::from_array((vec![batch, seq_len], input_ids))?;
+        let attention_mask_val = Value::from_array((vec![batch, seq_len], attention_mask.clone()))?;
+        let token_type_ids_val = Value::from_array((vec![batch, seq_len], token_type_ids))?;
+
+        // Step C: 运行推理
+        let mut session = self.session();
+        let outputs = session.run(inputs![
+            "input_ids" => input_ids_val,
+            "attention_mask" => attention_mask_val,
+            "token_type_ids" => token_type_ids_val,
+        ])?;
+
+        let (_, output_data) = outputs[0]
+            .try_extract_tensor::<f32>()
+            .context("Failed to extract output tensor")?;
+
+        // Step D: 逐条 Mean Pooling + L2 归一化
+        // 输出布局: [batch, seq_len, EMBEDDING_DIM]
+        let mut results = Vec::with_capacity(batch);
+        for b in 0..batch {
+            let mut pooled = vec![0.0f32; EMBEDDING_DIM];
+            let mut mask_sum = 0.0f32;
+            for t in 0..seq_len {
+                if attention_mask[b * seq_len + t] == 0 {
+                    continue;
+                }
+                mask_sum += 1.0;
+                let row = &output_data[(b * seq_len + t) * EMBEDDING_DIM..][..EMBEDDING_DIM];
+                for (p, &h) in pooled.iter_mut().zip(row) {
+                    *p += h;
+                }
+            }
+            let norm: f32 = pooled.iter().map(|x| (x / mask_sum).powi(2)).sum::<f32>().sqrt();
+            results.push(pooled.iter().map(|x| x / mask_sum / norm).collect());
+        }
+
+        Ok(results)
+    }
+
     pub fn dimension(&self) -> usize {
         EMBEDDING_DIM
     }
 }
+
+/// 按插入顺序淘汰的编码缓存 (FIFO)
+#[cfg(feature = "onnx")]
+struct EncodeCache {
+    capacity: usize,
+    vectors: HashMap<String, Vec<f32>>,
+    order: VecDeque<String>,
+}
+
+#[cfg(feature = "onnx")]
+impl EncodeCache {
+    fn new(capacity: usize) -> Self {
+        Self { capacity, vectors: HashMap::new(), order: VecDeque::new() }
+    }
+
+    fn get(&self, text: &str) -> Option<Vec<f32>> {
+        self.vectors.get(text).cloned()
+    }
+
+    fn insert(&mut self, text: &str, vector: &[f32]) {
+        if self.capacity == 0 || self.vectors.contains_key(text) {
+            return;
+        }
+        if self.order.len() >= self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.vectors.remove(&oldest);
+            }
+        }
+        self.order.push_back(text.to_string());
+        self.vectors.insert(text.to_string(), vector.to_vec());
+    }
+}
+
+// ============================================================================
+// 未启用 onnx feature 时的占位实现
+// ============================================================================
+
+#[cfg(not(feature = "onnx"))]
+pub struct EmbeddingModel {
+    never: std::convert::Infallible,
+}
+
+#[cfg(not(feature = "onnx"))]
+impl EmbeddingModel {
+    pub fn new() -> Result<Self> {
+        Self::from_files(MODEL_PATH, TOKENIZER_PATH)
+    }
+
+    pub fn from_files(model_path: &str, tokenizer_path: &str) -> Result<Self> {
+        Self::from_files_with_sessions(model_path, tokenizer_path, 1)
+    }
+
+    pub fn from_files_with_sessions(_model_path: &str, _tokenizer_path: &str, _sessions: usize) -> Result<Self> {
+        anyhow::bail!("mini-recsys was built without the `onnx` feature")
+    }
+
+    pub fn with_cache(self, _capacity: usize) -> Self {
+        match self.never {}
+    }
+
+    pub fn sessions(&self) -> usize {
+        match self.never {}
+    }
+
+    pub fn encode(&self, _text: &str) -> Result<Vec<f32>> {
+        match self.never {}
+    }
+
+    pub fn encode_batch(&self, _texts: &[&str]) -> Result<Vec<Vec<f32>>> {
+        match self.never {}
+    }
+
+    pub fn dimension(&self) -> usize {
+        match self.never {}
+    }
+}
+
+// ============================================================================
+// 单元测试
+// ============================================================================
+
+#[cfg(all(test, feature = "onnx"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_cache_evicts_oldest() {
+        let mut cache = EncodeCache::new(2);
+        cache.insert("a", &[1.0]);
+        cache.insert("b", &[2.0]);
+        cache.insert("a", &[9.0]); // 已存在，不覆盖也不改变淘汰顺序
+        cache.insert("c", &[3.0]);
+        assert_eq!(cache.get("a"), None);
+        assert_eq!(cache.get("b"), Some(vec![2.0]));
+        assert_eq!(cache.get("c"), Some(vec![3.0]));
+
+        let mut disabled = EncodeCache::new(0);
+        disabled.insert("a", &[1.0]);
+        assert_eq!(disabled.get("a"), None);
+    }
+}
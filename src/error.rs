@@ -0,0 +1,160 @@
+//! HTTP 错误类型 - 所有接口统一返回 `{code, message, details}`
+//!
+//! `code` 为稳定的机器可读错误码 (SCREAMING_SNAKE_CASE)，客户端应据此分支处理；
+//! `message` 仅供人阅读，措辞可能变化。
+
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Json, Response};
+use serde::Serialize;
+use serde_json::{json, Value};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ApiError {
+    #[error("User {0} not found")]
+    UserNotFound(u64),
+
+    #[error("Item {0} not found")]
+    ItemNotFound(u64),
+
+    #[error("Experiment {0} not found")]
+    ExperimentNotFound(String),
+
+    /// 请求内容不合法 (例如上传的 CSV 缺少必需列)
+    #[error("{0}")]
+    InvalidInput(String),
+
+    /// 翻页快照已过期或被淘汰，需要从第一页重新请求
+    #[error("Page token has expired, request the first page again")]
+    PageTokenExpired,
+
+    #[error("Embedding model not loaded")]
+    ModelUnavailable,
+
+    /// 启动预热尚未完成 (进度见 GET /status)
+    #[error("Service is warming up")]
+    WarmingUp,
+
+    /// Sled 读写失败
+    #[error("{context}")]
+    Storage {
+        context: &'static str,
+        #[source]
+        source: anyhow::Error,
+    },
+
+    /// 推荐 / 搜索流水线内部错误
+    #[error("{context}")]
+    Internal {
+        context: &'static str,
+        #[source]
+        source: anyhow::Error,
+    },
+}
+
+/// 错误响应体
+#[derive(Debug, Serialize)]
+pub struct ErrorBody {
+    pub code: &'static str,
+    pub message: String,
+    pub details: Option<Value>,
+}
+
+impl ApiError {
+    /// 包装存储层错误
+    pub fn storage(context: &'static str) -> impl FnOnce(anyhow::Error) -> Self {
+        move |source| Self::Storage { context, source }
+    }
+
+    /// 包装流水线内部错误
+    pub fn internal(context: &'static str) -> impl FnOnce(anyhow::Error) -> Self {
+        move |source| Self::Internal { context, source }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::UserNotFound(_) => "USER_NOT_FOUND",
+            Self::ItemNotFound(_) => "ITEM_NOT_FOUND",
+            Self::ExperimentNotFound(_) => "EXPERIMENT_NOT_FOUND",
+            Self::InvalidInput(_) => "INVALID_INPUT",
+            Self::PageTokenExpired => "PAGE_TOKEN_EXPIRED",
+            Self::ModelUnavailable => "MODEL_UNAVAILABLE",
+            Self::WarmingUp => "WARMING_UP",
+            Self::Storage { .. } => "STORAGE_ERROR",
+            Self::Internal { .. } => "INTERNAL_ERROR",
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            Self::UserNotFound(_) | Self::ItemNotFound(_) | Self::ExperimentNotFound(_) => StatusCode::NOT_FOUND,
+            Self::InvalidInput(_) => StatusCode::BAD_REQUEST,
+            Self::PageTokenExpired => StatusCode::GONE,
+            Self::ModelUnavailable | Self::WarmingUp => StatusCode::SERVICE_UNAVAILABLE,
+            Self::Storage { .. } | Self::Internal { .. } => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn details(&self) -> Option<Value> {
+        match self {
+            Self::UserNotFound(uid) => Some(json!({ "uid": uid })),
+            Self::ItemNotFound(item_id) => Some(json!({ "item_id": item_id })),
+            Self::ExperimentNotFound(id) => Some(json!({ "experiment_id": id })),
+            Self::InvalidInput(_) | Self::PageTokenExpired | Self::ModelUnavailable | Self::WarmingUp => None,
+            Self::Storage { source, .. } | Self::Internal { source, .. } => {
+                Some(json!({ "cause": format!("{:#}", source) }))
+            }
+        }
+    }
+
+    pub fn body(&self) -> ErrorBody {
+        ErrorBody { code: self.code(), message: self.to_string(), details: self.details() }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        if self.status().is_server_error() {
+            tracing::error!(code = self.code(), error = ?self, "❌ Request failed");
+        }
+        (self.status(), Json(self.body())).into_response()
+    }
+}
+
+/// gRPC 状态码与 HTTP 状态码一一对应，消息带上稳定的错误码前缀
+#[cfg(feature = "grpc")]
+impl From<ApiError> for tonic::Status {
+    fn from(e: ApiError) -> Self {
+        let message = format!("{}: {}", e.code(), e);
+        match e.status() {
+            StatusCode::NOT_FOUND => tonic::Status::not_found(message),
+            StatusCode::BAD_REQUEST => tonic::Status::invalid_argument(message),
+            StatusCode::SERVICE_UNAVAILABLE => tonic::Status::unavailable(message),
+            _ => {
+                tracing::error!(code = e.code(), error = ?e, "❌ gRPC request failed");
+                tonic::Status::internal(message)
+            }
+        }
+    }
+}
+
+// ============================================================================
+// 单元测试
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_body_shape() {
+        let body = serde_json::to_value(ApiError::UserNotFound(42).body()).unwrap();
+        assert_eq!(body, json!({ "code": "USER_NOT_FOUND", "message": "User 42 not found", "details": { "uid": 42 } }));
+
+        let err = ApiError::storage("Failed to get filter")(anyhow::anyhow!("disk full"));
+        assert_eq!(err.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        let body = err.body();
+        assert_eq!((body.code, body.message.as_str()), ("STORAGE_ERROR", "Failed to get filter"));
+        assert_eq!(body.details, Some(json!({ "cause": "disk full" })));
+    }
+}
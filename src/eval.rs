@@ -0,0 +1,316 @@
+//! 离线评估 - 交互日志 train/test 切分 + 排序指标 (Recall@K / NDCG / MRR / Coverage / Diversity)
+//!
+//! 按时间切分每个用户的交互序列：较早的部分视为"已看过" (等价于 Bloom Filter 过滤)，
+//! 较晚的部分作为 ground truth，然后用与线上相同的 `service::rank_candidates` 重放推荐。
+
+use crate::model::{InteractionEvent, Item, User};
+use crate::service::{self, RankWeights, MIN_RECOMMENDATIONS, RECALL_K};
+use crate::storage::Storage;
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+/// 评估参数
+#[derive(Debug, Clone, Copy)]
+pub struct EvalOptions {
+    /// 截断位置 K
+    pub k: usize,
+    /// 每个用户留作测试集的交互比例 (按时间取最新的部分)
+    pub test_ratio: f32,
+}
+
+impl Default for EvalOptions {
+    fn default() -> Self {
+        Self { k: 10, test_ratio: 0.2 }
+    }
+}
+
+/// 参与评估的排序策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Strategy {
+    /// 线上默认: sim * 0.7 + popularity * 0.3
+    Hybrid,
+    /// 只看向量相似度
+    Similarity,
+    /// 只看热门度 (全量物品作为候选)
+    Popular,
+}
+
+impl Strategy {
+    pub const ALL: [Strategy; 3] = [Strategy::Hybrid, Strategy::Similarity, Strategy::Popular];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Strategy::Hybrid => "hybrid",
+            Strategy::Similarity => "similarity",
+            Strategy::Popular => "popular",
+        }
+    }
+
+    fn weights(&self) -> RankWeights {
+        match self {
+            Strategy::Hybrid => RankWeights::default(),
+            Strategy::Similarity => RankWeights { sim: 1.0, popularity: 0.0 },
+            Strategy::Popular => RankWeights { sim: 0.0, popularity: 1.0 },
+        }
+    }
+}
+
+/// 单个策略的评估结果 (各指标为测试用户上的均值)
+#[derive(Debug, Clone, Serialize)]
+pub struct StrategyReport {
+    pub strategy: &'static str,
+    pub users: usize,
+    pub recall: f64,
+    pub ndcg: f64,
+    pub mrr: f64,
+    /// 被推荐过的物品占全部物品的比例
+    pub coverage: f64,
+    /// 列表内类别不同的物品对占比
+    pub diversity: f64,
+}
+
+/// 按用户切分后的交互数据
+#[derive(Debug, Default)]
+pub struct Split {
+    pub train: HashMap<u64, Vec<u64>>,
+    pub test: HashMap<u64, Vec<u64>>,
+}
+
+// ============================================================================
+// 数据切分
+// ============================================================================
+
+/// 按时间切分每个用户的交互 (events 需按时间升序)
+///
+/// 交互少于 2 条的用户不进入测试集；测试集中已在训练集出现的物品会被剔除。
+pub fn split_by_time(events: &[InteractionEvent], test_ratio: f32) -> Split {
+    let mut per_user: HashMap<u64, Vec<u64>> = HashMap::new();
+    for event in events {
+        let history = per_user.entry(event.uid).or_default();
+        if !history.contains(&event.item_id) {
+            history.push(event.item_id);
+        }
+    }
+
+    let mut split = Split::default();
+    for (uid, history) in per_user {
+        if history.len() < 2 {
+            split.train.insert(uid, history);
+            continue;
+        }
+        let n_test = ((history.len() as f32 * test_ratio).round() as usize).clamp(1, history.len() - 1);
+        let (train, test) = history.split_at(history.len() - n_test);
+        split.train.insert(uid, train.to_vec());
+        split.test.insert(uid, test.to_vec());
+    }
+    split
+}
+
+// ============================================================================
+// 指标
+// ============================================================================
+
+/// |推荐 ∩ 相关| / |相关|
+pub fn recall_at_k(recommended: &[u64], relevant: &HashSet<u64>) -> f64 {
+    if relevant.is_empty() {
+        return 0.0;
+    }
+    let hits = recommended.iter().filter(|id| relevant.contains(id)).count();
+    hits as f64 / relevant.len() as f64
+}
+
+/// 二值相关度的 NDCG: DCG = Σ 1 / log2(rank + 1)
+pub fn ndcg_at_k(recommended: &[u64], relevant: &HashSet<u64>) -> f64 {
+    let dcg: f64 = recommended.iter()
+        .enumerate()
+        .filter(|(_, id)| relevant.contains(id))
+        .map(|(i, _)| 1.0 / ((i + 2) as f64).log2())
+        .sum();
+    let ideal_hits = relevant.len().min(recommended.len());
+    let idcg: f64 = (0..ideal_hits).map(|i| 1.0 / ((i + 2) as f64).log2()).sum();
+    if idcg == 0.0 { 0.0 } else { dcg / idcg }
+}
+
+/// 第一个命中位置的倒数，无命中为 0
+pub fn reciprocal_rank(recommended: &[u64], relevant: &HashSet<u64>) -> f64 {
+    recommended.iter()
+        .position(|id| relevant.contains(id))
+        .map(|pos| 1.0 / (pos + 1) as f64)
+        .unwrap_or(0.0)
+}
+
+/// 列表内两两类别不同的比例
+pub fn intra_list_diversity(categories: &[&str]) -> f64 {
+    let n = categories.len();
+    if n < 2 {
+        return 0.0;
+    }
+    let mut different = 0;
+    for i in 0..n {
+        for j in (i + 1)..n {
+            if categories[i] != categories[j] {
+                different += 1;
+            }
+        }
+    }
+    different as f64 / (n * (n - 1) / 2) as f64
+}
+
+// ============================================================================
+// 评估流程
+// ============================================================================
+
+/// 对交互日志执行离线评估
+///
+/// `recall` 为召回函数 (query, k) -> [(item_id, sim)]，线上传入 HNSW 搜索，
+/// 测试中可以传入暴力搜索。
+pub fn evaluate(
+    storage: &Storage,
+    users: &[User],
+    items: &[Item],
+    item_map: &HashMap<u64, usize>,
+    recall: impl Fn(&[f32], usize) -> Vec<(u64, f32)>,
+    opts: EvalOptions,
+) -> Result<Vec<StrategyReport>> {
+    let events: Vec<InteractionEvent> = storage.iter_events().collect::<Result<_>>()?;
+    let split = split_by_time(&events, opts.test_ratio);
+    Ok(evaluate_split(&split, users, items, item_map, recall, opts))
+}
+
+/// 在给定切分上评估所有策略
+pub fn evaluate_split(
+    split: &Split,
+    users: &[User],
+    items: &[Item],
+    item_map: &HashMap<u64, usize>,
+    recall: impl Fn(&[f32], usize) -> Vec<(u64, f32)>,
+    opts: EvalOptions,
+) -> Vec<StrategyReport> {
+    let mut test_users: Vec<&User> = users.iter().filter(|u| split.test.contains_key(&u.id)).collect();
+    test_users.sort_by_key(|u| u.id);
+
+    Strategy::ALL.iter()
+        .map(|strategy| {
+            let mut report = StrategyReport {
+                strategy: strategy.name(),
+                users: test_users.len(),
+                recall: 0.0,
+                ndcg: 0.0,
+                mrr: 0.0,
+                coverage: 0.0,
+                diversity: 0.0,
+            };
+            let mut recommended_items: HashSet<u64> = HashSet::new();
+
+            for user in &test_users {
+                let seen: HashSet<u64> = split.train.get(&user.id)
+                    .map(|h| h.iter().copied().collect())
+                    .unwrap_or_default();
+                let relevant: HashSet<u64> = split.test[&user.id].iter().copied().collect();
+
+                let candidates = match strategy {
+                    Strategy::Popular => items.iter().map(|item| (item.id, 0.0)).collect(),
+                    _ => recall(&user.embedding, RECALL_K.max(opts.k)),
+                };
+                let output = service::rank_candidates(
+                    candidates,
+                    items,
+                    item_map,
+                    |item_id| seen.contains(&item_id),
+                    strategy.weights(),
+                    MIN_RECOMMENDATIONS.min(opts.k),
+                    opts.k,
+                );
+                let list: Vec<u64> = output.ranked.iter().map(|r| r.item_id).collect();
+                let categories: Vec<&str> = list.iter()
+                    .filter_map(|id| item_map.get(id).map(|&idx| items[idx].category.as_str()))
+                    .collect();
+
+                report.recall += recall_at_k(&list, &relevant);
+                report.ndcg += ndcg_at_k(&list, &relevant);
+                report.mrr += reciprocal_rank(&list, &relevant);
+                report.diversity += intra_list_diversity(&categories);
+                recommended_items.extend(list);
+            }
+
+            if !test_users.is_empty() {
+                let n = test_users.len() as f64;
+                report.recall /= n;
+                report.ndcg /= n;
+                report.mrr /= n;
+                report.diversity /= n;
+            }
+            if !items.is_empty() {
+                report.coverage = recommended_items.len() as f64 / items.len() as f64;
+            }
+            report
+        })
+        .collect()
+}
+
+/// 以表格形式打印评估结果
+pub fn print_report(reports: &[StrategyReport], k: usize) {
+    println!(
+        "{:<12} {:>6} {:>10} {:>8} {:>8} {:>9} {:>10}",
+        "strategy", "users", format!("recall@{}", k), format!("ndcg@{}", k), "mrr", "coverage", "diversity"
+    );
+    for r in reports {
+        println!(
+            "{:<12} {:>6} {:>10.4} {:>8.4} {:>8.4} {:>9.4} {:>10.4}",
+            r.strategy, r.users, r.recall, r.ndcg, r.mrr, r.coverage, r.diversity
+        );
+    }
+}
+
+// ============================================================================
+// 单元测试
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::EventKind;
+
+    fn relevant(ids: &[u64]) -> HashSet<u64> {
+        ids.iter().copied().collect()
+    }
+
+    #[test]
+    fn test_metrics() {
+        let list = [5, 1, 7, 2];
+        let rel = relevant(&[1, 2, 9]);
+
+        assert!((recall_at_k(&list, &rel) - 2.0 / 3.0).abs() < 1e-9);
+        assert!((reciprocal_rank(&list, &rel) - 0.5).abs() < 1e-9);
+
+        let dcg = 1.0 / 3f64.log2() + 1.0 / 5f64.log2();
+        let idcg = 1.0 + 1.0 / 3f64.log2() + 1.0 / 4f64.log2();
+        assert!((ndcg_at_k(&list, &rel) - dcg / idcg).abs() < 1e-9);
+
+        assert_eq!(reciprocal_rank(&list, &relevant(&[42])), 0.0);
+    }
+
+    #[test]
+    fn test_intra_list_diversity() {
+        assert_eq!(intra_list_diversity(&["Books", "Books"]), 0.0);
+        assert_eq!(intra_list_diversity(&["Books", "Home"]), 1.0);
+        assert!((intra_list_diversity(&["Books", "Books", "Home"]) - 2.0 / 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_split_by_time() {
+        let events: Vec<InteractionEvent> = [(1, 10), (1, 11), (1, 10), (1, 12), (1, 13), (1, 14), (2, 20)]
+            .iter()
+            .enumerate()
+            .map(|(ts, &(uid, item_id))| InteractionEvent { uid, item_id, kind: EventKind::View, ts_ms: ts as u64 })
+            .collect();
+
+        let split = split_by_time(&events, 0.2);
+        // 用户 1 去重后 5 条交互，最新 1 条进入测试集
+        assert_eq!(split.train[&1], vec![10, 11, 12, 13]);
+        assert_eq!(split.test[&1], vec![14]);
+        // 用户 2 只有 1 条交互，不参与测试
+        assert!(!split.test.contains_key(&2));
+    }
+}
@@ -0,0 +1,339 @@
+//! 事件落地 - 交互记录 (浏览 / 点击) 与曝光记录 (已返回的推荐 / 搜索结果) 写往哪里
+//!
+//! `[events] sinks` 选择一个或多个落地方式:
+//! - `sled`: 写入本地数据库 (默认)；离线评估、重放、训练样本导出与 CTR 统计只读取这里
+//! - `postgres`: 写入 Postgres 的 `interactions` / `impressions` 表，供分析系统查询 (需要 `postgres` feature)
+//!
+//! Postgres 写入在后台线程中批量进行，不阻塞请求；写入失败只打印警告。
+
+use crate::config::{EventsConfig, SinkKind};
+use crate::model::{InteractionEvent, RequestRecord};
+use crate::storage::Storage;
+use anyhow::{Context, Result};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// 交互与曝光记录的落地方式
+pub trait EventSink: Send + Sync {
+    /// 一条交互 (浏览 / 点击)
+    fn record_interaction(&self, event: &InteractionEvent) -> Result<()>;
+
+    /// 一次已返回的请求及其结果
+    fn record_impression(&self, record: &RequestRecord) -> Result<()>;
+
+    /// 等待缓冲中的记录写完 (退出前调用)，返回超时时仍未写入的数量
+    fn flush(&self, _timeout: Duration) -> usize {
+        0
+    }
+
+    /// 日志中的名称
+    fn name(&self) -> &'static str;
+}
+
+/// 写入本地 sled 数据库的 `events` 与 `request_log` Tree
+pub struct SledSink {
+    storage: Arc<Storage>,
+}
+
+impl SledSink {
+    pub fn new(storage: Arc<Storage>) -> Self {
+        Self { storage }
+    }
+}
+
+impl EventSink for SledSink {
+    fn record_interaction(&self, event: &InteractionEvent) -> Result<()> {
+        self.storage.append_event(event)
+    }
+
+    fn record_impression(&self, record: &RequestRecord) -> Result<()> {
+        self.storage.append_request(record)
+    }
+
+    fn name(&self) -> &'static str {
+        "sled"
+    }
+}
+
+/// 按 `[events]` 打开全部落地方式
+pub fn open_sinks(config: &EventsConfig, storage: &Arc<Storage>) -> Result<Vec<Box<dyn EventSink>>> {
+    config.sinks.iter()
+        .map(|kind| -> Result<Box<dyn EventSink>> {
+            match kind {
+                SinkKind::Sled => Ok(Box::new(SledSink::new(Arc::clone(storage)))),
+                SinkKind::Postgres => {
+                    let postgres = config.postgres.as_ref().context("events.sinks contains postgres but [events.postgres] is missing")?;
+                    Ok(Box::new(PostgresSink::connect(postgres)?))
+                }
+            }
+        })
+        .collect()
+}
+
+#[cfg(feature = "postgres")]
+pub use postgres::PostgresSink;
+
+#[cfg(feature = "postgres")]
+mod postgres {
+    use super::EventSink;
+    use crate::config::PostgresSinkConfig;
+    use crate::model::{InteractionEvent, LoggedRequest, RequestRecord};
+    use anyhow::{anyhow, Context, Result};
+    use serde::Serialize;
+    use sqlx::postgres::{PgPool, PgPoolOptions};
+    use sqlx::{Postgres, QueryBuilder};
+    use std::sync::{Arc, Condvar, Mutex};
+    use std::time::Duration;
+    use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+    use tracing::{info, warn};
+
+    /// 单条 INSERT 最多写入的行数
+    const BATCH_SIZE: usize = 500;
+
+    const SCHEMA: &[&str] = &[
+        "CREATE TABLE IF NOT EXISTS interactions (
+            uid BIGINT NOT NULL,
+            item_id BIGINT NOT NULL,
+            kind TEXT NOT NULL,
+            ts_ms BIGINT NOT NULL
+        )",
+        "CREATE TABLE IF NOT EXISTS impressions (
+            ts_ms BIGINT NOT NULL,
+            endpoint TEXT NOT NULL,
+            uid BIGINT,
+            query TEXT,
+            fusion TEXT,
+            item_ids BIGINT[] NOT NULL,
+            scores REAL[] NOT NULL,
+            experiment_id TEXT,
+            variant TEXT,
+            latency_ms DOUBLE PRECISION NOT NULL
+        )",
+    ];
+
+    enum Record {
+        Interaction(InteractionEvent),
+        Impression(RequestRecord),
+    }
+
+    /// 写入 Postgres 的落地方式 (后台线程 + 独立的 tokio 运行时，同步调用方也可使用)
+    pub struct PostgresSink {
+        queue: UnboundedSender<Record>,
+        /// 已入队但尚未写入的数量
+        pending: Arc<(Mutex<usize>, Condvar)>,
+    }
+
+    impl PostgresSink {
+        /// 连接数据库并建表，失败时返回错误
+        pub fn connect(config: &PostgresSinkConfig) -> Result<Self> {
+            let pending = Arc::new((Mutex::new(0), Condvar::new()));
+            let (queue, rx) = mpsc::unbounded_channel();
+            let (ready_tx, ready_rx) = std::sync::mpsc::channel();
+            let (config, worker_pending) = (config.clone(), Arc::clone(&pending));
+            std::thread::Builder::new()
+                .name("postgres-sink".into())
+                .spawn(move || {
+                    let runtime = match tokio::runtime::Builder::new_current_thread().enable_all().build() {
+                        Ok(runtime) => runtime,
+                        Err(e) => return drop(ready_tx.send(Err(anyhow!(e)))),
+                    };
+                    runtime.block_on(async {
+                        let pool = match open_pool(&config).await {
+                            Ok(pool) => pool,
+                            Err(e) => return drop(ready_tx.send(Err(e))),
+                        };
+                        let _ = ready_tx.send(Ok(()));
+                        write_loop(&pool, rx, &worker_pending).await;
+                    });
+                })
+                .context("Failed to start Postgres sink thread")?;
+            ready_rx.recv().context("Postgres sink thread exited")??;
+            info!("🐘 Postgres event sink connected");
+            Ok(Self { queue, pending })
+        }
+
+        fn enqueue(&self, record: Record) {
+            *lock(&self.pending.0) += 1;
+            if self.queue.send(record).is_err() {
+                *lock(&self.pending.0) -= 1;
+                warn!("⚠️  Postgres sink thread is gone, record dropped");
+            }
+        }
+    }
+
+    impl EventSink for PostgresSink {
+        fn record_interaction(&self, event: &InteractionEvent) -> Result<()> {
+            self.enqueue(Record::Interaction(event.clone()));
+            Ok(())
+        }
+
+        fn record_impression(&self, record: &RequestRecord) -> Result<()> {
+            self.enqueue(Record::Impression(record.clone()));
+            Ok(())
+        }
+
+        fn flush(&self, timeout: Duration) -> usize {
+            let (count, done) = &*self.pending;
+            let guard = lock(count);
+            let (guard, _) = done.wait_timeout_while(guard, timeout, |n| *n > 0)
+                .unwrap_or_else(|e| e.into_inner());
+            *guard
+        }
+
+        fn name(&self) -> &'static str {
+            "postgres"
+        }
+    }
+
+    fn lock(count: &Mutex<usize>) -> std::sync::MutexGuard<'_, usize> {
+        count.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    async fn open_pool(config: &PostgresSinkConfig) -> Result<PgPool> {
+        let pool = PgPoolOptions::new()
+            .max_connections(config.max_connections)
+            .connect(&config.url)
+            .await
+            .context("Failed to connect to Postgres")?;
+        for statement in SCHEMA {
+            sqlx::query(statement).execute(&pool).await.context("Failed to create event tables")?;
+        }
+        Ok(pool)
+    }
+
+    /// 逐批取出队列中的记录写入，直到发送端全部关闭
+    async fn write_loop(pool: &PgPool, mut rx: UnboundedReceiver<Record>, pending: &(Mutex<usize>, Condvar)) {
+        let mut batch = Vec::with_capacity(BATCH_SIZE);
+        while rx.recv_many(&mut batch, BATCH_SIZE).await > 0 {
+            let (interactions, impressions): (Vec<_>, Vec<_>) = batch.drain(..).partition(|r| matches!(r, Record::Interaction(_)));
+            let written = interactions.len() + impressions.len();
+            if let Err(e) = insert_interactions(pool, &interactions).await {
+                warn!(rows = interactions.len(), error = %format!("{:#}", e), "⚠️  Failed to write interactions to Postgres");
+            }
+            if let Err(e) = insert_impressions(pool, &impressions).await {
+                warn!(rows = impressions.len(), error = %format!("{:#}", e), "⚠️  Failed to write impressions to Postgres");
+            }
+            let (count, done) = pending;
+            *lock(count) -= written;
+            done.notify_all();
+        }
+    }
+
+    async fn insert_interactions(pool: &PgPool, records: &[Record]) -> Result<()> {
+        if records.is_empty() {
+            return Ok(());
+        }
+        let mut query = QueryBuilder::<Postgres>::new("INSERT INTO interactions (uid, item_id, kind, ts_ms) ");
+        query.push_values(records, |mut row, record| {
+            let Record::Interaction(event) = record else { unreachable!("partitioned by kind") };
+            row.push_bind(event.uid as i64)
+                .push_bind(event.item_id as i64)
+                .push_bind(label(&event.kind))
+                .push_bind(event.ts_ms as i64);
+        });
+        query.build().execute(pool).await?;
+        Ok(())
+    }
+
+    async fn insert_impressions(pool: &PgPool, records: &[Record]) -> Result<()> {
+        if records.is_empty() {
+            return Ok(());
+        }
+        let mut query = QueryBuilder::<Postgres>::new(
+            "INSERT INTO impressions (ts_ms, endpoint, uid, query, fusion, item_ids, scores, experiment_id, variant, latency_ms) ",
+        );
+        query.push_values(records, |mut row, record| {
+            let Record::Impression(record) = record else { unreachable!("partitioned by kind") };
+            let (endpoint, uid, q, fusion) = match &record.request {
+                LoggedRequest::Recommend { uid } => ("recommend", Some(*uid as i64), None, None),
+                LoggedRequest::Search { q, fusion } => ("search", None, Some(q.clone()), Some(label(fusion))),
+            };
+            row.push_bind(record.ts_ms as i64)
+                .push_bind(endpoint)
+                .push_bind(uid)
+                .push_bind(q)
+                .push_bind(fusion)
+                .push_bind(record.item_ids.iter().map(|&id| id as i64).collect::<Vec<_>>())
+                .push_bind(record.scores.clone())
+                .push_bind(record.experiment.as_ref().map(|e| e.experiment_id.clone()))
+                .push_bind(record.experiment.as_ref().map(|e| e.variant.clone()))
+                .push_bind(record.latency_ms);
+        });
+        query.build().execute(pool).await?;
+        Ok(())
+    }
+
+    /// 枚举的 serde 名称，例如 `EventKind::Click` -> `"click"`
+    fn label(value: &impl Serialize) -> String {
+        serde_json::to_value(value).ok()
+            .and_then(|v| v.as_str().map(str::to_string))
+            .unwrap_or_default()
+    }
+}
+
+// ============================================================================
+// 未启用 postgres feature 时的占位实现
+// ============================================================================
+
+#[cfg(not(feature = "postgres"))]
+pub struct PostgresSink {
+    never: std::convert::Infallible,
+}
+
+#[cfg(not(feature = "postgres"))]
+impl PostgresSink {
+    pub fn connect(_config: &crate::config::PostgresSinkConfig) -> Result<Self> {
+        anyhow::bail!("mini-recsys was built without the `postgres` feature")
+    }
+}
+
+#[cfg(not(feature = "postgres"))]
+impl EventSink for PostgresSink {
+    fn record_interaction(&self, _event: &InteractionEvent) -> Result<()> {
+        match self.never {}
+    }
+
+    fn record_impression(&self, _record: &RequestRecord) -> Result<()> {
+        match self.never {}
+    }
+
+    fn name(&self) -> &'static str {
+        match self.never {}
+    }
+}
+
+// ============================================================================
+// 单元测试
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{EventKind, LoggedRequest};
+
+    #[test]
+    fn test_sled_sink_writes_to_storage() {
+        let path = std::env::temp_dir().join(format!("mini-recsys-events-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&path);
+        let storage = Arc::new(Storage::new(&path.to_string_lossy()).unwrap());
+        let sinks = open_sinks(&EventsConfig::default(), &storage).unwrap();
+        assert_eq!(sinks.len(), 1);
+
+        sinks[0].record_interaction(&InteractionEvent::new(1, 7, EventKind::Click)).unwrap();
+        let record = RequestRecord {
+            ts_ms: 1,
+            request: LoggedRequest::Recommend { uid: 1 },
+            item_ids: vec![7],
+            scores: vec![0.5],
+            experiment: None,
+            latency_ms: 1.0,
+        };
+        sinks[0].record_impression(&record).unwrap();
+        assert_eq!(storage.events_count(), 1);
+        assert_eq!(storage.requests_count(), 1);
+
+        drop(sinks);
+        drop(storage);
+        let _ = std::fs::remove_dir_all(&path);
+    }
+}
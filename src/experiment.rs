@@ -0,0 +1,260 @@
+//! A/B 实验 - 用户分桶与按变体聚合的效果报表
+//!
+//! 分桶: FNV-1a(experiment_id, uid) % 100 落在哪个变体的流量区间，同一用户始终命中同一变体。
+//! 报表: 请求日志中的每个返回物品视为一次曝光，若同一用户在归因窗口内对其产生 Click 事件，
+//! 则记为一次点击。
+
+use crate::model::{EventKind, ExperimentTag, InteractionEvent, LoggedRequest, RequestRecord};
+use crate::service::RankWeights;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+/// 点击归因窗口: 曝光后 30 分钟内的点击计入该次曝光
+pub const ATTRIBUTION_WINDOW_MS: u64 = 30 * 60 * 1000;
+
+/// 实验变体
+#[derive(Debug)]
+pub struct Variant {
+    pub name: &'static str,
+    /// 流量占比 (百分比，同一实验内合计为 100)
+    pub traffic: u32,
+    pub weights: RankWeights,
+}
+
+/// 实验定义
+#[derive(Debug)]
+pub struct Experiment {
+    pub id: &'static str,
+    pub variants: &'static [Variant],
+}
+
+/// 当前运行的实验 (只有第一个实验会影响 /recommend 的排序)
+pub const EXPERIMENTS: &[Experiment] = &[Experiment {
+    id: "rank-weights-v1",
+    variants: &[
+        Variant { name: "control", traffic: 50, weights: RankWeights { sim: 0.7, popularity: 0.3 } },
+        Variant { name: "sim_heavy", traffic: 50, weights: RankWeights { sim: 0.9, popularity: 0.1 } },
+    ],
+}];
+
+pub fn find(id: &str) -> Option<&'static Experiment> {
+    EXPERIMENTS.iter().find(|e| e.id == id)
+}
+
+/// FNV-1a 哈希 (跨版本稳定，保证分桶结果可复现)
+fn fnv1a(bytes: impl IntoIterator<Item = u8>) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for b in bytes {
+        hash ^= b as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+impl Experiment {
+    /// 为用户分配变体
+    pub fn assign(&self, uid: u64) -> &'static Variant {
+        let bucket = (fnv1a(self.id.bytes().chain(uid.to_le_bytes())) % 100) as u32;
+        let mut upper = 0;
+        for variant in self.variants {
+            upper += variant.traffic;
+            if bucket < upper {
+                return variant;
+            }
+        }
+        // 流量配置合计不足 100 时，剩余流量归入最后一个变体
+        self.variants.last().expect("experiment has no variants")
+    }
+
+    pub fn variant(&self, name: &str) -> Option<&'static Variant> {
+        self.variants.iter().find(|v| v.name == name)
+    }
+}
+
+/// 为用户分配当前生效的实验变体
+pub fn assign(uid: u64) -> Option<(ExperimentTag, RankWeights)> {
+    let experiment = EXPERIMENTS.first()?;
+    let variant = experiment.assign(uid);
+    let tag = ExperimentTag { experiment_id: experiment.id.to_string(), variant: variant.name.to_string() };
+    Some((tag, variant.weights))
+}
+
+/// 按实验标签查找排序权重 (实验已下线时返回 None)
+pub fn weights_for(tag: &ExperimentTag) -> Option<RankWeights> {
+    find(&tag.experiment_id)?.variant(&tag.variant).map(|v| v.weights)
+}
+
+// ============================================================================
+// 报表
+// ============================================================================
+
+/// 单个变体的效果指标
+#[derive(Debug, Clone, Serialize)]
+pub struct VariantReport {
+    pub variant: String,
+    pub requests: usize,
+    pub impressions: usize,
+    pub clicks: usize,
+    pub ctr: f64,
+    /// 曝光过的不同物品占全部物品的比例
+    pub coverage: f64,
+    /// 被点击物品在列表中的平均位置 (1-based)，无点击时为 None
+    pub avg_click_position: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExperimentReport {
+    pub experiment_id: String,
+    pub attribution_window_ms: u64,
+    pub variants: Vec<VariantReport>,
+}
+
+/// 点击索引: 判断某次曝光是否在归因窗口内被点击
+pub struct ClickIndex {
+    /// (uid, item_id) -> 点击时间戳
+    clicks: HashMap<(u64, u64), Vec<u64>>,
+}
+
+impl ClickIndex {
+    pub fn new<'a>(events: impl IntoIterator<Item = &'a InteractionEvent>) -> Self {
+        let mut clicks: HashMap<(u64, u64), Vec<u64>> = HashMap::new();
+        for event in events.into_iter().filter(|e| e.kind == EventKind::Click) {
+            clicks.entry((event.uid, event.item_id)).or_default().push(event.ts_ms);
+        }
+        Self { clicks }
+    }
+
+    /// uid 在 impression_ts 之后的归因窗口内是否点击过 item_id
+    pub fn clicked(&self, uid: u64, item_id: u64, impression_ts: u64) -> bool {
+        self.clicks.get(&(uid, item_id)).is_some_and(|ts_list| {
+            ts_list.iter().any(|&ts| ts >= impression_ts && ts - impression_ts <= ATTRIBUTION_WINDOW_MS)
+        })
+    }
+}
+
+#[derive(Default)]
+struct Accumulator {
+    requests: usize,
+    impressions: usize,
+    clicks: usize,
+    click_position_sum: usize,
+    items: HashSet<u64>,
+}
+
+/// 聚合请求日志 (曝光) 与 Click 事件，生成实验报表
+pub fn build_report(
+    experiment: &Experiment,
+    requests: &[RequestRecord],
+    events: &[InteractionEvent],
+    catalog_size: usize,
+) -> ExperimentReport {
+    let clicks = ClickIndex::new(events);
+
+    let mut acc: HashMap<String, Accumulator> = experiment.variants.iter()
+        .map(|v| (v.name.to_string(), Accumulator::default()))
+        .collect();
+
+    for record in requests {
+        let (LoggedRequest::Recommend { uid }, Some(tag)) = (&record.request, &record.experiment) else { continue };
+        if tag.experiment_id != experiment.id {
+            continue;
+        }
+        let Some(variant) = acc.get_mut(&tag.variant) else { continue };
+
+        variant.requests += 1;
+        for (pos, item_id) in record.item_ids.iter().enumerate() {
+            variant.impressions += 1;
+            variant.items.insert(*item_id);
+            if clicks.clicked(*uid, *item_id, record.ts_ms) {
+                variant.clicks += 1;
+                variant.click_position_sum += pos + 1;
+            }
+        }
+    }
+
+    let variants = experiment.variants.iter()
+        .map(|v| {
+            let a = &acc[v.name];
+            VariantReport {
+                variant: v.name.to_string(),
+                requests: a.requests,
+                impressions: a.impressions,
+                clicks: a.clicks,
+                ctr: if a.impressions == 0 { 0.0 } else { a.clicks as f64 / a.impressions as f64 },
+                coverage: if catalog_size == 0 { 0.0 } else { a.items.len() as f64 / catalog_size as f64 },
+                avg_click_position: (a.clicks > 0).then(|| a.click_position_sum as f64 / a.clicks as f64),
+            }
+        })
+        .collect();
+
+    ExperimentReport {
+        experiment_id: experiment.id.to_string(),
+        attribution_window_ms: ATTRIBUTION_WINDOW_MS,
+        variants,
+    }
+}
+
+// ============================================================================
+// 单元测试
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_EXPERIMENT: Experiment = Experiment {
+        id: "test",
+        variants: &[
+            Variant { name: "a", traffic: 50, weights: RankWeights { sim: 0.7, popularity: 0.3 } },
+            Variant { name: "b", traffic: 50, weights: RankWeights { sim: 1.0, popularity: 0.0 } },
+        ],
+    };
+
+    fn impression(uid: u64, variant: &str, ts_ms: u64, item_ids: Vec<u64>) -> RequestRecord {
+        RequestRecord {
+            ts_ms,
+            request: LoggedRequest::Recommend { uid },
+            scores: vec![0.0; item_ids.len()],
+            item_ids,
+            experiment: Some(ExperimentTag { experiment_id: "test".into(), variant: variant.into() }),
+            latency_ms: 0.0,
+        }
+    }
+
+    fn click(uid: u64, item_id: u64, ts_ms: u64) -> InteractionEvent {
+        InteractionEvent { uid, item_id, kind: EventKind::Click, ts_ms }
+    }
+
+    #[test]
+    fn test_assignment_is_stable() {
+        for uid in 0..100 {
+            assert_eq!(TEST_EXPERIMENT.assign(uid).name, TEST_EXPERIMENT.assign(uid).name);
+        }
+        let in_a = (0..1000).filter(|&uid| TEST_EXPERIMENT.assign(uid).name == "a").count();
+        assert!((350..650).contains(&in_a), "unbalanced split: {}", in_a);
+    }
+
+    #[test]
+    fn test_report_joins_clicks_within_window() {
+        let requests = vec![
+            impression(1, "a", 1_000, vec![10, 11, 12]),
+            impression(2, "b", 1_000, vec![20, 21]),
+        ];
+        let events = vec![
+            click(1, 11, 2_000),                             // 位置 2，窗口内
+            click(2, 20, 1_000 + ATTRIBUTION_WINDOW_MS + 1), // 超出窗口
+            click(1, 99, 2_000),                             // 未曝光
+        ];
+
+        let report = build_report(&TEST_EXPERIMENT, &requests, &events, 100);
+        let a = &report.variants[0];
+        assert_eq!((a.impressions, a.clicks), (3, 1));
+        assert!((a.ctr - 1.0 / 3.0).abs() < 1e-9);
+        assert_eq!(a.avg_click_position, Some(2.0));
+        assert!((a.coverage - 0.03).abs() < 1e-9);
+
+        let b = &report.variants[1];
+        assert_eq!((b.impressions, b.clicks), (2, 0));
+        assert_eq!(b.avg_click_position, None);
+    }
+}
@@ -0,0 +1,152 @@
+//! 训练数据导出 - 将曝光、点击与用户/物品特征拼接为二阶段排序模型的带标签样本
+//! (JSONL；Parquet 见 `columnar::write_training`，需要 `parquet` feature)
+//!
+//! 每条 /recommend 曝光 (请求日志中的一个返回物品) 生成一条样本，
+//! label = 1 表示该物品在归因窗口内被点击 (与 A/B 报表的归因口径一致)。
+
+use crate::experiment::ClickIndex;
+use crate::model::{EventKind, InteractionEvent, Item, LoggedRequest, RequestRecord, User};
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io::Write;
+
+/// 一条训练样本
+#[derive(Debug, Clone, Serialize)]
+pub struct TrainingExample {
+    pub uid: u64,
+    pub item_id: u64,
+    /// 曝光时间 (Unix 毫秒)
+    pub ts_ms: u64,
+    /// 列表中的位置 (1-based)
+    pub position: usize,
+    /// 是否被点击
+    pub label: u8,
+    pub variant: Option<String>,
+    // ---- 物品特征 ----
+    pub category: String,
+    pub price: f32,
+    pub popularity: f32,
+    // ---- 用户 x 物品特征 ----
+    /// 用户向量与物品向量的内积
+    pub sim_score: f32,
+    // ---- 用户特征 (仅统计曝光之前的行为，避免穿越) ----
+    pub user_views_before: usize,
+    pub user_clicks_before: usize,
+}
+
+/// 每个用户按时间排序的 View / Click 时间戳
+#[derive(Default)]
+struct UserHistory {
+    views: Vec<u64>,
+    clicks: Vec<u64>,
+}
+
+fn count_before(sorted_ts: &[u64], ts: u64) -> usize {
+    sorted_ts.partition_point(|&t| t < ts)
+}
+
+/// 拼接曝光、点击与特征，生成训练样本
+pub fn training_examples(
+    requests: &[RequestRecord],
+    events: &[InteractionEvent],
+    users: &[User],
+    items: &[Item],
+    item_map: &HashMap<u64, usize>,
+) -> Vec<TrainingExample> {
+    let clicks = ClickIndex::new(events);
+
+    let mut histories: HashMap<u64, UserHistory> = HashMap::new();
+    for event in events {
+        let history = histories.entry(event.uid).or_default();
+        match event.kind {
+            EventKind::View => history.views.push(event.ts_ms),
+            EventKind::Click => history.clicks.push(event.ts_ms),
+        }
+    }
+    for history in histories.values_mut() {
+        history.views.sort_unstable();
+        history.clicks.sort_unstable();
+    }
+
+    let user_map: HashMap<u64, &User> = users.iter().map(|u| (u.id, u)).collect();
+    let empty = UserHistory::default();
+
+    let mut examples = Vec::new();
+    for record in requests {
+        let LoggedRequest::Recommend { uid } = record.request else { continue };
+        let Some(user) = user_map.get(&uid) else { continue };
+        let history = histories.get(&uid).unwrap_or(&empty);
+
+        for (pos, item_id) in record.item_ids.iter().enumerate() {
+            let Some(&idx) = item_map.get(item_id) else { continue };
+            let item = &items[idx];
+            let sim_score: f32 = user.embedding.iter().zip(&item.embedding).map(|(a, b)| a * b).sum();
+
+            examples.push(TrainingExample {
+                uid,
+                item_id: *item_id,
+                ts_ms: record.ts_ms,
+                position: pos + 1,
+                label: clicks.clicked(uid, *item_id, record.ts_ms) as u8,
+                variant: record.experiment.as_ref().map(|t| t.variant.clone()),
+                category: item.category.clone(),
+                price: item.price,
+                popularity: item.popularity,
+                sim_score,
+                user_views_before: count_before(&history.views, record.ts_ms),
+                user_clicks_before: count_before(&history.clicks, record.ts_ms),
+            });
+        }
+    }
+    examples
+}
+
+/// 以 JSONL 格式写出样本，返回写出的行数
+pub fn write_jsonl(examples: &[TrainingExample], mut writer: impl Write) -> Result<usize> {
+    for example in examples {
+        serde_json::to_writer(&mut writer, example).context("Failed to serialize example")?;
+        writer.write_all(b"\n")?;
+    }
+    writer.flush()?;
+    Ok(examples.len())
+}
+
+// ============================================================================
+// 单元测试
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_training_examples_labels_and_history() {
+        let users = vec![User { id: 1, name: "u".into(), embedding: vec![1.0, 0.0] }];
+        let items = vec![
+            Item::new(10, "A", vec![1.0, 0.0]),
+            Item::new(11, "B", vec![0.0, 1.0]),
+        ];
+        let item_map: HashMap<u64, usize> = items.iter().enumerate().map(|(i, item)| (item.id, i)).collect();
+        let requests = vec![RequestRecord {
+            ts_ms: 100,
+            request: LoggedRequest::Recommend { uid: 1 },
+            item_ids: vec![10, 11],
+            scores: vec![0.9, 0.5],
+            experiment: None,
+            latency_ms: 1.0,
+        }];
+        let events = vec![
+            InteractionEvent { uid: 1, item_id: 10, kind: EventKind::View, ts_ms: 50 },
+            InteractionEvent { uid: 1, item_id: 11, kind: EventKind::Click, ts_ms: 200 },
+        ];
+
+        let examples = training_examples(&requests, &events, &users, &items, &item_map);
+        assert_eq!(examples.len(), 2);
+        assert_eq!((examples[0].label, examples[1].label), (0, 1));
+        assert_eq!(examples[1].position, 2);
+        assert!((examples[0].sim_score - 1.0).abs() < 1e-6);
+        // 曝光之后的点击不计入用户历史
+        assert_eq!((examples[0].user_views_before, examples[0].user_clicks_before), (1, 0));
+    }
+}
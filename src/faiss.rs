@@ -0,0 +1,163 @@
+//! FAISS 平坦索引文件读写 - 与其他工具交换向量与索引
+//!
+//! 导出为 `IndexIDMap(IndexFlatIP)`，与本服务的内积检索一致:
+//! `faiss.read_index("items.faiss").search(q, k)` 返回的就是物品 ID。
+//! 导入支持 `IndexFlatIP` / `IndexFlatL2` / `IndexFlat`，可以外面包一层 `IndexIDMap` / `IndexIDMap2`；
+//! 没有 ID 映射时行号即 ID。其余索引类型 (IVF、HNSW、PQ 等) 不保存原始向量，需先在 FAISS 中
+//! `reconstruct_n` 出向量再导出为平坦索引。
+
+use anyhow::{anyhow, bail, Context, Result};
+use std::io::{Read, Write};
+use tracing::warn;
+
+const METRIC_INNER_PRODUCT: i32 = 0;
+const METRIC_L2: i32 = 1;
+/// FAISS 索引头中两个历史遗留字段的固定值
+const HEADER_DUMMY: i64 = 1 << 20;
+
+/// 写出 `IndexIDMap(IndexFlatIP)`
+pub fn write_index(vectors: &[(u64, &[f32])], dim: usize, mut out: impl Write) -> Result<()> {
+    if let Some((id, vector)) = vectors.iter().find(|(_, v)| v.len() != dim) {
+        bail!("Item {} has {} dimensions, expected {}", id, vector.len(), dim);
+    }
+    let ntotal = vectors.len() as i64;
+
+    out.write_all(b"IxMp")?;
+    write_header(&mut out, dim, ntotal)?;
+    out.write_all(b"IxFI")?;
+    write_header(&mut out, dim, ntotal)?;
+    out.write_all(&((vectors.len() * dim) as u64).to_le_bytes())?;
+    for (_, vector) in vectors {
+        for value in *vector {
+            out.write_all(&value.to_le_bytes())?;
+        }
+    }
+    out.write_all(&(vectors.len() as u64).to_le_bytes())?;
+    for (id, _) in vectors {
+        out.write_all(&(*id as i64).to_le_bytes())?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+fn write_header(out: &mut impl Write, dim: usize, ntotal: i64) -> Result<()> {
+    out.write_all(&(dim as i32).to_le_bytes())?;
+    out.write_all(&ntotal.to_le_bytes())?;
+    out.write_all(&HEADER_DUMMY.to_le_bytes())?;
+    out.write_all(&HEADER_DUMMY.to_le_bytes())?;
+    out.write_all(&[1])?; // is_trained
+    out.write_all(&METRIC_INNER_PRODUCT.to_le_bytes())?;
+    Ok(())
+}
+
+/// 读取平坦索引中的 `id` -> 向量 映射
+pub fn read_index(mut input: impl Read) -> Result<Vec<(u64, Vec<f32>)>> {
+    let fourcc = read_array::<4>(&mut input)?;
+    let (ids, vectors) = match &fourcc {
+        b"IxMp" | b"IxM2" => {
+            read_header(&mut input)?;
+            let inner = read_array::<4>(&mut input)?;
+            let vectors = read_flat(&mut input, &inner)?;
+            let count = read_u64(&mut input)? as usize;
+            let ids = (0..count)
+                .map(|_| {
+                    let id = i64::from_le_bytes(read_array(&mut input)?);
+                    u64::try_from(id).map_err(|_| anyhow!("Negative id {}", id))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            (ids, vectors)
+        }
+        _ => {
+            let vectors = read_flat(&mut input, &fourcc)?;
+            ((0..vectors.len() as u64).collect(), vectors)
+        }
+    };
+    if ids.len() != vectors.len() {
+        bail!("Index has {} vectors but {} ids", vectors.len(), ids.len());
+    }
+    Ok(ids.into_iter().zip(vectors).collect())
+}
+
+/// 读取 `IndexFlat*` (fourcc 已读出)
+fn read_flat(input: &mut impl Read, fourcc: &[u8; 4]) -> Result<Vec<Vec<f32>>> {
+    if !matches!(fourcc, b"IxFI" | b"IxF2" | b"IxFl") {
+        bail!(
+            "Unsupported FAISS index type {:?}; only flat indexes keep the raw vectors (export with reconstruct_n into an IndexFlatIP)",
+            String::from_utf8_lossy(fourcc)
+        );
+    }
+    let (dim, ntotal, metric) = read_header(input)?;
+    if metric == METRIC_L2 {
+        warn!("⚠️  Importing an L2 index; mini-recsys ranks by inner product, so normalize the vectors if they are not already");
+    }
+    let floats = read_u64(input)? as usize;
+    if floats != dim * ntotal {
+        bail!("Flat index stores {} values, expected {} x {}", floats, ntotal, dim);
+    }
+    let mut data = vec![0u8; floats * 4];
+    input.read_exact(&mut data).context("Index data is truncated")?;
+    let values: Vec<f32> = data.chunks_exact(4).map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]])).collect();
+    Ok(values.chunks(dim.max(1)).map(<[f32]>::to_vec).collect())
+}
+
+/// 返回 (d, ntotal, metric_type)
+fn read_header(input: &mut impl Read) -> Result<(usize, usize, i32)> {
+    let dim = i32::from_le_bytes(read_array(input)?);
+    let ntotal = i64::from_le_bytes(read_array(input)?);
+    read_array::<16>(input)?; // 两个 dummy
+    read_array::<1>(input)?; // is_trained
+    let metric = i32::from_le_bytes(read_array(input)?);
+    if metric > METRIC_L2 {
+        read_array::<4>(input)?; // metric_arg
+    }
+    let dim = usize::try_from(dim).map_err(|_| anyhow!("Invalid dimension {}", dim))?;
+    let ntotal = usize::try_from(ntotal).map_err(|_| anyhow!("Invalid vector count {}", ntotal))?;
+    Ok((dim, ntotal, metric))
+}
+
+fn read_u64(input: &mut impl Read) -> Result<u64> {
+    Ok(u64::from_le_bytes(read_array(input)?))
+}
+
+fn read_array<const N: usize>(input: &mut impl Read) -> Result<[u8; N]> {
+    let mut buf = [0u8; N];
+    input.read_exact(&mut buf).context("Index file is truncated")?;
+    Ok(buf)
+}
+
+// ============================================================================
+// 单元测试
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let vectors: Vec<(u64, &[f32])> = vec![(42, &[1.0, 0.0]), (7, &[0.5, -0.5])];
+        let mut bytes = Vec::new();
+        write_index(&vectors, 2, &mut bytes).unwrap();
+        // fourcc + 头 (4 + 8 + 16 + 1 + 4) 两次，向量区与 ID 区各带 8 字节长度
+        assert_eq!(bytes.len(), 2 * (4 + 33) + 8 + 4 * 4 + 8 + 2 * 8);
+
+        let read = read_index(bytes.as_slice()).unwrap();
+        assert_eq!(read, vec![(42, vec![1.0, 0.0]), (7, vec![0.5, -0.5])]);
+
+        assert!(write_index(&vectors, 3, Vec::new()).is_err());
+        assert!(read_index(&bytes[..bytes.len() - 4]).is_err());
+    }
+
+    #[test]
+    fn test_bare_flat_uses_row_ids() {
+        let mut bytes = Vec::new();
+        write_index(&[(42, &[1.0, 2.0][..])], 2, &mut bytes).unwrap();
+        // 去掉 IndexIDMap 的 fourcc、头部与末尾的 ID 区
+        let flat = &bytes[4 + 33..bytes.len() - 16];
+        assert_eq!(read_index(flat).unwrap(), vec![(0, vec![1.0, 2.0])]);
+
+        let mut hnsw = b"IHNf".to_vec();
+        hnsw.extend_from_slice(&flat[4..]);
+        assert!(read_index(hnsw.as_slice()).is_err());
+    }
+}
@@ -2,14 +2,25 @@
 //!
 //! 这个模块是 Rust 与 C++ 交互的边界层。
 //! 所有的 `unsafe` 代码都集中在这里，业务层不应该直接接触 unsafe。
+//!
+//! 未启用 `cpp-hnsw` feature 时，同名函数由 `brute_force` 模块的纯 Rust 实现提供。
 
+#[cfg(feature = "cpp-hnsw")]
 use crate::model::Item;
+#[cfg(feature = "cpp-hnsw")]
 use libc::{c_float, c_int};
 
+#[cfg(not(feature = "cpp-hnsw"))]
+pub use crate::brute_force::{
+    add, add_item_to_hnsw, compute_dot_product, destroy_hnsw_index, get_hnsw_count, hnsw_search, init_hnsw_index,
+    load_hnsw_index, recommend_recall, save_hnsw_index, set_hnsw_ef, try_hnsw_search,
+};
+
 // ============================================================================
 // 外部 C 函数声明 (Raw FFI Bindings)
 // ============================================================================
 
+#[cfg(feature = "cpp-hnsw")]
 extern "C" {
     // 基础运算
     fn cpp_add(a: c_int, b: c_int) -> c_int;
@@ -23,7 +34,7 @@ extern "C" {
     fn hnsw_destroy();
     fn hnsw_get_count() -> c_int;
     fn hnsw_save_index(path: *const libc::c_char) -> c_int;
-    fn hnsw_load_index(path: *const libc::c_char, dim: c_int, max_elements: c_int) -> c_int;
+    fn hnsw_load_index(path: *const libc::c_char, dim: c_int, max_elements: c_int, M: c_int, ef_construction: c_int) -> c_int;
 
     // 旧版暴力搜索
     fn search_top_k(
@@ -42,11 +53,13 @@ extern "C" {
 // 基础运算 Safe Wrapper
 // ============================================================================
 
+#[cfg(feature = "cpp-hnsw")]
 pub fn add(a: i32, b: i32) -> i32 {
     // SAFETY: cpp_add 是纯函数，c_int 与 i32 兼容
     unsafe { cpp_add(a, b) }
 }
 
+#[cfg(feature = "cpp-hnsw")]
 pub fn compute_dot_product(vec_a: &[f32], vec_b: &[f32]) -> Option<f32> {
     if vec_a.len() != vec_b.len() {
         return None;
@@ -98,6 +111,7 @@ impl Default for HnswConfig {
 /// # Returns
 /// * `Ok(())` - 初始化成功
 /// * `Err(String)` - 初始化失败
+#[cfg(feature = "cpp-hnsw")]
 pub fn init_hnsw_index(config: &HnswConfig) -> Result<(), String> {
     // SAFETY: 所有参数都是基本类型，无指针操作
     let result = unsafe {
@@ -127,6 +141,7 @@ pub fn init_hnsw_index(config: &HnswConfig) -> Result<(), String> {
 /// # Returns
 /// * `Ok(())` - 添加成功
 /// * `Err(String)` - 添加失败
+#[cfg(feature = "cpp-hnsw")]
 pub fn add_item_to_hnsw(id: u64, embedding: &[f32]) -> Result<(), String> {
     // SAFETY: embedding 是有效切片，在调用期间不会被释放
     let result = unsafe { hnsw_add_item(id as c_int, embedding.as_ptr()) };
@@ -145,10 +160,17 @@ pub fn add_item_to_hnsw(id: u64, embedding: &[f32]) -> Result<(), String> {
 /// * `k` - 返回的最近邻数量
 /// 
 /// # Returns
-/// 返回 (item_id, similarity_score) 的列表，按相似度降序排列
+/// 返回 (item_id, similarity_score) 的列表，按相似度降序排列；搜索失败时返回空列表
+#[cfg(feature = "cpp-hnsw")]
 pub fn hnsw_search(query: &[f32], k: usize) -> Vec<(u64, f32)> {
+    try_hnsw_search(query, k).unwrap_or_default()
+}
+
+/// 同 `hnsw_search`，索引未初始化或搜索失败时返回错误
+#[cfg(feature = "cpp-hnsw")]
+pub fn try_hnsw_search(query: &[f32], k: usize) -> Result<Vec<(u64, f32)>, String> {
     if k == 0 {
-        return Vec::new();
+        return Ok(Vec::new());
     }
 
     let mut out_ids: Vec<c_int> = vec![0; k];
@@ -167,27 +189,37 @@ pub fn hnsw_search(query: &[f32], k: usize) -> Vec<(u64, f32)> {
     };
 
     if count < 0 {
-        return Vec::new();
+        return Err("HNSW search failed".to_string());
     }
 
-    (0..count as usize)
+    Ok((0..count as usize)
         .map(|i| (out_ids[i] as u64, out_scores[i]))
-        .collect()
+        .collect())
+}
+
+/// 调整查询时的搜索深度 (ef 越大召回率越高、查询越慢)
+#[cfg(feature = "cpp-hnsw")]
+pub fn set_hnsw_ef(ef_search: usize) {
+    // SAFETY: 参数是基本类型，无指针操作
+    unsafe { hnsw_set_ef(ef_search as c_int) };
 }
 
 /// 销毁 HNSW 索引并释放内存
+#[cfg(feature = "cpp-hnsw")]
 pub fn destroy_hnsw_index() {
     // SAFETY: 无需传递参数，仅释放全局索引
     unsafe { hnsw_destroy() };
 }
 
 /// 获取索引中的元素数量
+#[cfg(feature = "cpp-hnsw")]
 pub fn get_hnsw_count() -> usize {
     // SAFETY: 无参数，返回值是基本类型
     unsafe { hnsw_get_count() as usize }
 }
 
 /// 保存索引到文件
+#[cfg(feature = "cpp-hnsw")]
 pub fn save_hnsw_index(path: &str) -> Result<(), String> {
     use std::ffi::CString;
     let c_path = CString::new(path).map_err(|_| "Invalid path".to_string())?;
@@ -202,26 +234,33 @@ pub fn save_hnsw_index(path: &str) -> Result<(), String> {
     }
 }
 
-/// 加载索引 (若文件不存在则创建新索引)
+/// 加载索引 (若文件不存在则按 `config` 创建新索引；m / ef_construction 仅对新索引生效)
 /// 返回: Ok(true) = 已加载, Ok(false) = 创建了新索引, Err = 失败
-pub fn load_hnsw_index(path: &str, dim: usize, max_elements: usize, ef_search: usize) -> Result<bool, String> {
+#[cfg(feature = "cpp-hnsw")]
+pub fn load_hnsw_index(path: &str, config: &HnswConfig) -> Result<bool, String> {
     use std::ffi::CString;
     let c_path = CString::new(path).map_err(|_| "Invalid path".to_string())?;
     
     // SAFETY: c_path 是有效的以 null 结尾的 C 字符串
     let result = unsafe { 
-        hnsw_load_index(c_path.as_ptr(), dim as c_int, max_elements as c_int) 
+        hnsw_load_index(
+            c_path.as_ptr(),
+            config.dim as c_int,
+            config.max_elements as c_int,
+            config.m as c_int,
+            config.ef_construction as c_int,
+        )
     };
     
     match result {
         0 => {
             // 加载成功，设置 ef_search
-            unsafe { hnsw_set_ef(ef_search as c_int) };
+            unsafe { hnsw_set_ef(config.ef_search as c_int) };
             Ok(true)
         }
         1 => {
             // 创建了新索引
-            unsafe { hnsw_set_ef(ef_search as c_int) };
+            unsafe { hnsw_set_ef(config.ef_search as c_int) };
             Ok(false)
         }
         _ => Err("Failed to load HNSW index".to_string()),
@@ -233,6 +272,7 @@ pub fn load_hnsw_index(path: &str, dim: usize, max_elements: usize, ef_search: u
 // ============================================================================
 
 /// 召回阶段：从物品库中找出与用户最相似的 Top K 物品 (暴力搜索)
+#[cfg(feature = "cpp-hnsw")]
 pub fn recommend_recall(user_embedding: &[f32], items: &[Item], k: usize) -> Vec<(u64, f32)> {
     if items.is_empty() || k == 0 {
         return Vec::new();
@@ -278,6 +318,7 @@ pub fn recommend_recall(user_embedding: &[f32], items: &[Item], k: usize) -> Vec
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::model::Item;
 
     #[test]
     fn test_cpp_add() {
@@ -0,0 +1,110 @@
+//! 响应字段裁剪 - `?fields=item_id,name,final_score`
+//!
+//! 带宽受限的客户端只取需要的字段 (例如不要图片 URL 与各项分数)；未指定时返回完整对象。
+//! 裁剪只作用于结果列表中的每个物品 (或 `GET /items/:id` 返回的单个物品)，响应外层的字段不变。
+
+use serde::ser::{Error as _, SerializeSeq};
+use serde::{Serialize, Serializer};
+use serde_json::Value;
+
+/// 客户端选择的字段
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldSet(Vec<String>);
+
+impl FieldSet {
+    /// 解析逗号分隔的字段列表；为空或含有不在 `allowed` 中的字段时返回错误
+    pub fn parse(spec: &str, allowed: &[&str]) -> Result<Self, String> {
+        let mut fields: Vec<String> = Vec::new();
+        for name in spec.split(',').map(str::trim).filter(|name| !name.is_empty()) {
+            if !allowed.contains(&name) {
+                return Err(format!("Unknown field {:?} (expected some of {})", name, allowed.join(", ")));
+            }
+            if !fields.iter().any(|f| f == name) {
+                fields.push(name.to_string());
+            }
+        }
+        if fields.is_empty() {
+            return Err("fields must name at least one field".to_string());
+        }
+        Ok(Self(fields))
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.0.iter().any(|f| f == name)
+    }
+}
+
+/// 只保留 `fields` 中的字段 (`fields` 为 None 时原样转换)
+pub fn project<T: Serialize>(item: &T, fields: Option<&FieldSet>) -> serde_json::Result<Value> {
+    let mut value = serde_json::to_value(item)?;
+    if let (Some(fields), Value::Object(map)) = (fields, &mut value) {
+        map.retain(|key, _| fields.contains(key));
+    }
+    Ok(value)
+}
+
+/// 按 `fields` 裁剪后序列化的列表 (`fields` 为 None 时原样序列化)
+#[derive(Debug, Clone)]
+pub struct Sparse<T> {
+    items: Vec<T>,
+    fields: Option<FieldSet>,
+}
+
+impl<T> Sparse<T> {
+    pub fn new(items: Vec<T>, fields: Option<FieldSet>) -> Self {
+        Self { items, fields }
+    }
+}
+
+impl<T: Serialize> Serialize for Sparse<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let Some(fields) = &self.fields else { return self.items.serialize(serializer) };
+        let mut seq = serializer.serialize_seq(Some(self.items.len()))?;
+        for item in &self.items {
+            seq.serialize_element(&project(item, Some(fields)).map_err(S::Error::custom)?)?;
+        }
+        seq.end()
+    }
+}
+
+// ============================================================================
+// 单元测试
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const ALLOWED: &[&str] = &["item_id", "name", "image_url", "final_score"];
+
+    #[derive(Serialize)]
+    struct Row {
+        item_id: u64,
+        name: &'static str,
+        image_url: &'static str,
+        final_score: f32,
+    }
+
+    #[test]
+    fn test_parse_fields() {
+        let fields = FieldSet::parse(" item_id, name,,item_id", ALLOWED).unwrap();
+        assert_eq!(fields, FieldSet(vec!["item_id".into(), "name".into()]));
+        assert!(FieldSet::parse("item_id,price", ALLOWED).is_err());
+        assert!(FieldSet::parse(" , ", ALLOWED).is_err());
+    }
+
+    #[test]
+    fn test_sparse_serialization() {
+        let rows = || vec![Row { item_id: 1, name: "A", image_url: "https://img/1.png", final_score: 0.5 }];
+        let full = serde_json::to_value(Sparse::new(rows(), None)).unwrap();
+        assert_eq!(full[0]["image_url"], "https://img/1.png");
+
+        let fields = FieldSet::parse("item_id,final_score", ALLOWED).unwrap();
+        let sparse = serde_json::to_value(Sparse::new(rows(), Some(fields))).unwrap();
+        assert_eq!(sparse, json!([{ "item_id": 1, "final_score": 0.5 }]));
+
+        let fields = FieldSet::parse("name", ALLOWED).unwrap();
+        assert_eq!(project(&rows()[0], Some(&fields)).unwrap(), json!({ "name": "A" }));
+    }
+}
@@ -0,0 +1,135 @@
+//! gRPC 接口 (需要 `grpc` feature) - 与 HTTP 共用同一个 RecSys、请求日志与流水线指标
+//!
+//! 协议定义见 `proto/recsys.proto`，由 build.rs 通过 tonic-build 生成代码。
+//! 由 `server.grpc_bind` 启用，与 HTTP 监听在同一个进程、同一条退出路径中运行。
+
+use crate::error::ApiError;
+use crate::metrics;
+use crate::model::{ExperimentTag, LoggedRequest};
+use crate::catalog::Catalog;
+use crate::recsys::{RecSys, RecommendOptions, Recommendation, SearchOptions};
+use crate::service::{self, SearchOutput};
+use anyhow::{Context, Result};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Instant;
+use tonic::{Request, Response, Status};
+
+/// tonic-build 生成的消息与服务代码
+pub mod pb {
+    tonic::include_proto!("mini_recsys.v1");
+}
+
+use pb::rec_sys_server::{RecSys as RecSysRpc, RecSysServer};
+
+/// gRPC 服务需要的共享状态 (由 HTTP 服务的 AppState 实现)
+pub trait GrpcState: Send + Sync + 'static {
+    /// 预热完成前返回 `ApiError::WarmingUp`
+    fn recsys(&self) -> Result<&RecSys, ApiError>;
+
+    /// 记录一次已返回的请求 (与 HTTP 请求写入同一份请求日志)
+    fn log_request(&self, request: LoggedRequest, results: Vec<(u64, f32)>, experiment: Option<ExperimentTag>, latency_ms: f64);
+}
+
+pub struct GrpcService<S> {
+    state: Arc<S>,
+}
+
+/// 监听 `bind` (TCP 地址) 并提供 gRPC 服务，直到出错
+pub async fn serve<S: GrpcState>(state: Arc<S>, bind: &str) -> Result<()> {
+    let addr: SocketAddr = bind.parse().with_context(|| format!("Invalid server.grpc_bind {}", bind))?;
+    tonic::transport::Server::builder()
+        .add_service(RecSysServer::new(GrpcService { state }))
+        .serve(addr)
+        .await
+        .context("gRPC server failed")
+}
+
+impl<S: GrpcState> GrpcService<S> {
+    /// 在阻塞线程池中执行同步的 RecSys 调用 (ANN 搜索、Bloom Filter 反序列化与编码不占用异步工作线程)
+    async fn run_blocking<T: Send + 'static>(
+        &self,
+        wrap: impl FnOnce(anyhow::Error) -> ApiError,
+        call: impl FnOnce(&RecSys) -> Result<T> + Send + 'static,
+    ) -> Result<T, ApiError> {
+        self.state.recsys()?;
+        let state = Arc::clone(&self.state);
+        match tokio::task::spawn_blocking(move || call(state.recsys().expect("published before spawning"))).await {
+            Ok(result) => result.map_err(wrap),
+            Err(e) => Err(wrap(e.into())),
+        }
+    }
+}
+
+/// 物品消息 (物品已不在目录中时返回 None)
+fn item_message(catalog: &Catalog, item_id: u64, score: f32) -> Option<pb::Item> {
+    let item = catalog.item(item_id)?;
+    Some(pb::Item {
+        item_id,
+        name: item.name.clone(),
+        category: item.category.clone(),
+        image_url: item.image_url.clone(),
+        price: item.price,
+        score,
+    })
+}
+
+#[tonic::async_trait]
+impl<S: GrpcState> RecSysRpc for GrpcService<S> {
+    async fn recommend(&self, request: Request<pb::RecommendRequest>) -> Result<Response<pb::RecommendResponse>, Status> {
+        let start = Instant::now();
+        let uid = request.into_inner().uid;
+        let recsys = self.state.recsys()?;
+
+        let Recommendation { output, experiment } = self
+            .run_blocking(ApiError::internal("Recommendation failed"), move |recsys| recsys.recommend(uid, RecommendOptions::default()))
+            .await?
+            .ok_or(ApiError::UserNotFound(uid))?;
+        metrics::PIPELINE.record_recommend(&output.counts, output.filtered_count);
+
+        self.state.log_request(
+            LoggedRequest::Recommend { uid },
+            output.ranked.iter().map(|r| (r.item_id, r.final_score)).collect(),
+            experiment.clone(),
+            service::elapsed_ms(start),
+        );
+
+        let (experiment_id, variant) = experiment.map_or_else(Default::default, |t| (t.experiment_id, t.variant));
+        let catalog = recsys.catalog();
+        Ok(Response::new(pb::RecommendResponse {
+            items: output.ranked.iter().filter_map(|r| item_message(&catalog, r.item_id, r.final_score)).collect(),
+            experiment_id,
+            variant,
+            filtered_count: output.filtered_count as u32,
+        }))
+    }
+
+    async fn search(&self, request: Request<pb::SearchRequest>) -> Result<Response<pb::SearchResponse>, Status> {
+        let start = Instant::now();
+        let pb::SearchRequest { query, fusion } = request.into_inner();
+        let recsys = self.state.recsys()?;
+        let strategy = match fusion.as_str() {
+            "" => recsys.config().search.fusion,
+            other => other.parse().map_err(Status::invalid_argument)?,
+        };
+
+        let q = query.clone();
+        let SearchOutput { results, counts, .. } = self
+            .run_blocking(ApiError::internal("Search failed"), move |recsys| recsys.search(&q, SearchOptions { fusion: strategy }))
+            .await?
+            .ok_or(ApiError::ModelUnavailable)?;
+        metrics::PIPELINE.record_search(&counts);
+
+        self.state.log_request(
+            LoggedRequest::Search { q: query, fusion: strategy },
+            results.iter().map(|r| (r.id, r.score)).collect(),
+            None,
+            service::elapsed_ms(start),
+        );
+
+        let catalog = recsys.catalog();
+        Ok(Response::new(pb::SearchResponse {
+            items: results.iter().filter_map(|r| item_message(&catalog, r.id, r.score)).collect(),
+        }))
+    }
+}
@@ -1,41 +1,258 @@
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
 /// Reciprocal Rank Fusion
 /// Score = 1 / (k + rank)
 const RRF_K: f32 = 60.0;
 
+/// Recall channel a ranked list came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SourceLabel {
+    Vector,
+    Keyword,
+    Covisit,
+    Popular,
+}
+
+/// One ranked input list for fusion, best match first
+#[derive(Debug, Clone)]
+pub struct RankedSource {
+    pub label: SourceLabel,
+    pub results: Vec<(u64, f32)>, // (id, source-native score)
+}
+
+impl RankedSource {
+    pub fn new(label: SourceLabel, results: Vec<(u64, f32)>) -> Self {
+        Self { label, results }
+    }
+}
+
+/// 融合策略
+/// - `Rrf`: 只看排名，对分数量纲不敏感
+/// - `MinMax` / `ZScore`: 先对各来源分数归一化再求和 (CombSUM)，保留分数差距
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FusionStrategy {
+    #[default]
+    Rrf,
+    MinMax,
+    ZScore,
+}
+
+/// 单个来源对某条结果的贡献 (用于调试融合权重)
+#[derive(Debug, Clone, Serialize)]
+pub struct SourceContribution {
+    pub source: SourceLabel,
+    pub rank: usize,        // 1-based rank within the source
+    pub raw_score: f32,     // source-native score
+    pub partial_score: f32, // contribution to the fused score
+}
+
+impl std::str::FromStr for FusionStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "rrf" => Ok(FusionStrategy::Rrf),
+            "minmax" => Ok(FusionStrategy::MinMax),
+            "zscore" => Ok(FusionStrategy::ZScore),
+            other => Err(format!("Unknown fusion strategy: {} (expected rrf | minmax | zscore)", other)),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct SearchResult {
-    pub id: u32,
-    pub score: f32, // RRF score
+    pub id: u64,
+    pub score: f32,                              // fused score
+    pub contributions: Vec<SourceContribution>,  // one entry per channel that returned this id
+}
+
+impl SearchResult {
+    fn new(id: u64) -> Self {
+        Self { id, score: 0.0, contributions: Vec::new() }
+    }
+
+    fn add(&mut self, source: SourceLabel, rank: usize, raw_score: f32, partial_score: f32) {
+        // 同一来源内重复出现时只记最靠前的一次 (分数也只累加一次)
+        if self.contributions.iter().any(|c| c.source == source) {
+            return;
+        }
+        self.score += partial_score;
+        self.contributions.push(SourceContribution { source, rank: rank + 1, raw_score, partial_score });
+    }
+
+    /// Channels that returned this id, in input order
+    pub fn sources(&self) -> Vec<SourceLabel> {
+        self.contributions.iter().map(|c| c.source).collect()
+    }
+}
+
+pub fn rrf_merge(sources: &[RankedSource]) -> Vec<SearchResult> {
+    let mut merged: HashMap<u64, SearchResult> = HashMap::new();
+
+    // 1. Accumulate RRF contributions from every source
+    for source in sources {
+        for (rank, (id, raw_score)) in source.results.iter().enumerate() {
+            let rrf_score = 1.0 / (RRF_K + rank as f32 + 1.0);
+            merged.entry(*id)
+                .or_insert_with(|| SearchResult::new(*id))
+                .add(source.label, rank, *raw_score, rrf_score);
+        }
+    }
+
+    // 2. Convert to Vec and Sort
+    sort_merged(merged)
 }
 
-pub fn rrf_merge(
-    vector_results: Vec<(u32, f32)>, // (id, similarity)
-    keyword_results: Vec<u32>,       // id only, rank implies score
-) -> Vec<SearchResult> {
-    let mut scores: HashMap<u32, f32> = HashMap::new();
+/// Score-normalized fusion: each source's scores are rescaled independently, then summed
+pub fn normalized_merge(sources: &[RankedSource], strategy: FusionStrategy) -> Vec<SearchResult> {
+    let mut merged: HashMap<u64, SearchResult> = HashMap::new();
 
-    // 1. Process Vector Results
-    for (rank, (id, _sim)) in vector_results.iter().enumerate() {
-        let rrf_score = 1.0 / (RRF_K + rank as f32 + 1.0);
-        *scores.entry(*id).or_insert(0.0) += rrf_score;
+    for source in sources {
+        let normalized = match strategy {
+            FusionStrategy::ZScore => z_score(&source.results),
+            _ => min_max(&source.results),
+        };
+        for (rank, ((id, norm_score), (_, raw_score))) in normalized.into_iter().zip(&source.results).enumerate() {
+            merged.entry(id)
+                .or_insert_with(|| SearchResult::new(id))
+                .add(source.label, rank, *raw_score, norm_score);
+        }
     }
 
-    // 2. Process Keyword Results
-    for (rank, id) in keyword_results.iter().enumerate() {
-        let rrf_score = 1.0 / (RRF_K + rank as f32 + 1.0);
-        *scores.entry(*id).or_insert(0.0) += rrf_score;
+    sort_merged(merged)
+}
+
+/// 按策略融合多路召回结果
+pub fn fuse(sources: &[RankedSource], strategy: FusionStrategy) -> Vec<SearchResult> {
+    match strategy {
+        FusionStrategy::Rrf => rrf_merge(sources),
+        FusionStrategy::MinMax | FusionStrategy::ZScore => normalized_merge(sources, strategy),
+    }
+}
+
+/// (s - min) / (max - min)；所有分数相同 (包括只有一条结果) 时统一记为 0.0，与 z_score 一致，
+/// 否则单条结果的来源会拿满分压过其他来源的头部结果
+fn min_max(results: &[(u64, f32)]) -> Vec<(u64, f32)> {
+    let min = results.iter().map(|(_, s)| *s).fold(f32::INFINITY, f32::min);
+    let max = results.iter().map(|(_, s)| *s).fold(f32::NEG_INFINITY, f32::max);
+    let range = max - min;
+    results.iter()
+        .map(|(id, s)| {
+            let norm = if range > f32::EPSILON { (s - min) / range } else { 0.0 };
+            (*id, norm)
+        })
+        .collect()
+}
+
+/// (s - mean) / std；标准差为 0 时统一记为 0.0
+fn z_score(results: &[(u64, f32)]) -> Vec<(u64, f32)> {
+    if results.is_empty() {
+        return Vec::new();
     }
+    let n = results.len() as f32;
+    let mean = results.iter().map(|(_, s)| *s).sum::<f32>() / n;
+    let variance = results.iter().map(|(_, s)| (s - mean).powi(2)).sum::<f32>() / n;
+    let std = variance.sqrt();
+    results.iter()
+        .map(|(id, s)| {
+            let norm = if std > f32::EPSILON { (s - mean) / std } else { 0.0 };
+            (*id, norm)
+        })
+        .collect()
+}
 
-    // 3. Convert to Vec and Sort
-    let mut results: Vec<SearchResult> = scores
-        .into_iter()
-        .map(|(id, score)| SearchResult { id, score })
-        .collect();
+fn sort_merged(merged: HashMap<u64, SearchResult>) -> Vec<SearchResult> {
+    let mut results: Vec<SearchResult> = merged.into_values().collect();
 
-    // Sort descending by score
-    results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+    // Sort descending by score, ties by id (HashMap 的迭代顺序不固定；调用方已丢弃 NaN 输入，这里的 NaN 同样被丢弃)
+    results.sort_unstable_by_key(|r| r.id);
+    crate::score::sort_desc(&mut results, |r| r.score);
 
     results
 }
+
+// ============================================================================
+// 单元测试
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rrf_merge_tracks_sources() {
+        let sources = vec![
+            RankedSource::new(SourceLabel::Vector, vec![(1, 0.9), (2, 0.8)]),
+            RankedSource::new(SourceLabel::Keyword, vec![(2, 3.1), (3, 2.0)]),
+            RankedSource::new(SourceLabel::Popular, vec![(4, 0.7)]),
+        ];
+
+        let results = rrf_merge(&sources);
+        assert_eq!(results.len(), 4);
+        // id=2 出现在两个来源中，应排第一
+        assert_eq!(results[0].id, 2);
+        assert_eq!(results[0].sources(), vec![SourceLabel::Vector, SourceLabel::Keyword]);
+        assert_eq!(results[0].contributions[0].rank, 2);
+        assert_eq!(results[0].contributions[1].rank, 1);
+
+        let popular = results.iter().find(|r| r.id == 4).unwrap();
+        assert_eq!(popular.sources(), vec![SourceLabel::Popular]);
+    }
+
+    #[test]
+    fn test_rrf_merge_dedups_within_source_and_breaks_ties_by_id() {
+        let sources = vec![
+            RankedSource::new(SourceLabel::Vector, vec![(5, 0.9), (5, 0.8)]),
+            RankedSource::new(SourceLabel::Keyword, vec![(3, 2.0)]),
+        ];
+
+        let results = rrf_merge(&sources);
+        // id=5 在 Vector 中重复出现，只按第一次计分，与 id=3 同分
+        assert_eq!(results.iter().map(|r| r.id).collect::<Vec<_>>(), vec![3, 5]);
+        assert_eq!(results[0].score, results[1].score);
+        assert_eq!(results[1].contributions.len(), 1);
+    }
+
+    #[test]
+    fn test_rrf_merge_empty() {
+        assert!(rrf_merge(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_min_max_respects_score_gaps() {
+        // 各来源归一化到 [0, 1] 后相加: id=2 = 0.8/0.85 + 4.9/5.0
+        let sources = vec![
+            RankedSource::new(SourceLabel::Vector, vec![(1, 0.95), (2, 0.90), (5, 0.10)]),
+            RankedSource::new(SourceLabel::Keyword, vec![(3, 5.0), (2, 4.9), (4, 0.0)]),
+        ];
+
+        let results = fuse(&sources, FusionStrategy::MinMax);
+        assert_eq!(results[0].id, 2);
+        assert!((results[0].score - (0.8 / 0.85 + 0.98)).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_min_max_single_item_source() {
+        // 只有一条结果的来源不拿满分，长来源的第一名仍排在最前
+        let sources = vec![
+            RankedSource::new(SourceLabel::Vector, (1..=20).map(|id| (id, 1.0 - id as f32 * 0.01)).collect()),
+            RankedSource::new(SourceLabel::Popular, vec![(99, 0.7)]),
+        ];
+
+        let results = fuse(&sources, FusionStrategy::MinMax);
+        assert_eq!(results[0].id, 1);
+        assert!((results[0].score - 1.0).abs() < 1e-6);
+        let popular = results.iter().find(|r| r.id == 99).unwrap();
+        assert_eq!(popular.score, 0.0);
+    }
+
+    #[test]
+    fn test_z_score_constant_source() {
+        let sources = vec![RankedSource::new(SourceLabel::Popular, vec![(1, 0.5), (2, 0.5)])];
+        let results = fuse(&sources, FusionStrategy::ZScore);
+        assert!(results.iter().all(|r| r.score == 0.0));
+    }
+}
@@ -0,0 +1,66 @@
+//! 多语言标题 - 语言标签规范化与 Accept-Language 协商
+//!
+//! 物品的 `title` 是 `i18n.default_lang` 语言的标题，`titles` 中是其他语言的译名。
+//! 展示语言按 `?lang=` > `Accept-Language` > `i18n.default_lang` 的顺序选择，
+//! 只会选中 `i18n.languages` 中的语言；物品没有该语言的译名时显示 `title`。
+
+/// 规范化语言标签: 小写，`_` 换成 `-` (`zh_CN` -> `zh-cn`)
+pub fn normalize(tag: &str) -> String {
+    tag.trim().replace('_', "-").to_ascii_lowercase()
+}
+
+/// 主语言子标签 (`zh-cn` -> `zh`)
+pub fn primary(tag: &str) -> &str {
+    tag.split('-').next().unwrap_or(tag)
+}
+
+/// 按权重从高到低返回 Accept-Language 中的语言 (权重相同保持原顺序)；忽略 `*` 与 `q=0`
+pub fn parse_accept_language(header: &str) -> Vec<String> {
+    let mut weighted: Vec<(String, f32)> = header.split(',')
+        .filter_map(|part| {
+            let mut params = part.split(';');
+            let tag = normalize(params.next()?);
+            let q = params
+                .filter_map(|p| p.trim().strip_prefix("q="))
+                .find_map(|q| q.trim().parse::<f32>().ok())
+                .unwrap_or(1.0);
+            (!tag.is_empty() && tag != "*" && q > 0.0).then_some((tag, q))
+        })
+        .collect();
+    weighted.sort_by(|a, b| b.1.total_cmp(&a.1));
+    weighted.into_iter().map(|(tag, _)| tag).collect()
+}
+
+/// 在 `supported` (已规范化) 中选出第一个与 `requested` 匹配的语言: 先完全匹配，再按主语言匹配
+pub fn negotiate<'a>(requested: &[String], supported: &'a [String]) -> Option<&'a str> {
+    requested.iter().find_map(|want| {
+        supported.iter()
+            .find(|have| *have == want)
+            .or_else(|| supported.iter().find(|have| primary(have) == primary(want)))
+            .map(String::as_str)
+    })
+}
+
+// ============================================================================
+// 单元测试
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_accept_language() {
+        assert_eq!(parse_accept_language("zh-CN,zh;q=0.9,en;q=0.8"), vec!["zh-cn", "zh", "en"]);
+        assert_eq!(parse_accept_language("en;q=0.5, fr, *;q=0.1, de;q=0"), vec!["fr", "en"]);
+        assert!(parse_accept_language("").is_empty());
+    }
+
+    #[test]
+    fn test_negotiate() {
+        let supported = vec!["en".to_string(), "zh".to_string()];
+        assert_eq!(negotiate(&parse_accept_language("zh-CN,en;q=0.8"), &supported), Some("zh"));
+        assert_eq!(negotiate(&["fr".into(), "en-gb".into()], &supported), Some("en"));
+        assert_eq!(negotiate(&["fr".into()], &supported), None);
+    }
+}
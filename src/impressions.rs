@@ -0,0 +1,201 @@
+//! 近期曝光 - 刷新页面时轮换推荐列表
+//!
+//! 已看过滤器只在 /mark_seen 或浏览事件之后生效，在此之前同一用户每次请求都会拿到相同的前 10 个结果。
+//! 这里按用户记录最近返回过但没有被点击的物品 (保留 `impressions.ttl_secs`)，
+//! 下一次推荐时按 `impressions.penalty` 压低它们的分数，让排在后面的候选有机会露出。
+//!
+//! 只保存在本进程内存中，重启后清空；与已看过滤器不同，曝光过期后物品会恢复原来的排序。
+
+use crate::score;
+use crate::service::ScoredItem;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// 单个用户的曝光: 物品 -> 过期时间，以及最近一次记录的时间与序号
+struct UserImpressions {
+    items: HashMap<u64, Instant>,
+    last_seen: Instant,
+    seq: u64,
+}
+
+#[derive(Default)]
+struct Inner {
+    users: HashMap<u64, UserImpressions>,
+    /// 记录序号 -> uid，最早记录的在前；淘汰与过期清理都从头部开始，不需要扫描全部用户
+    order: BTreeMap<u64, u64>,
+    next_seq: u64,
+}
+
+impl Inner {
+    fn remove(&mut self, uid: u64) {
+        if let Some(user) = self.users.remove(&uid) {
+            self.order.remove(&user.seq);
+        }
+    }
+}
+
+/// 用户 -> (物品 -> 过期时间)，按最近一次记录的时间淘汰
+pub struct RecentImpressions {
+    ttl: Duration,
+    max_users: usize,
+    inner: Mutex<Inner>,
+}
+
+impl RecentImpressions {
+    /// 曝光保留 `ttl`，最多跟踪 `max_users` 个用户 (至少 1 个)
+    pub fn new(ttl: Duration, max_users: usize) -> Self {
+        Self { ttl, max_users: max_users.max(1), inner: Mutex::new(Inner::default()) }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Inner> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// 记录返回给 `uid` 的物品；超出用户数上限时淘汰最久没有记录的用户 (O(log n))
+    pub fn record(&self, uid: u64, item_ids: &[u64]) {
+        if item_ids.is_empty() {
+            return;
+        }
+        let now = Instant::now();
+        let expires = now + self.ttl;
+        let mut inner = self.lock();
+        let Inner { users, order, next_seq } = &mut *inner;
+        let seq = *next_seq;
+        *next_seq += 1;
+        let user = users.entry(uid).or_insert_with(|| UserImpressions { items: HashMap::new(), last_seen: now, seq });
+        order.remove(&user.seq);
+        order.insert(seq, uid);
+        user.last_seen = now;
+        user.seq = seq;
+        user.items.extend(item_ids.iter().map(|&id| (id, expires)));
+
+        while users.len() > self.max_users {
+            let Some((_, stale)) = order.pop_first() else { break };
+            users.remove(&stale);
+        }
+    }
+
+    /// 用户点击了物品: 不再视为被忽略的曝光
+    pub fn clicked(&self, uid: u64, item_id: u64) {
+        let mut inner = self.lock();
+        let Some(user) = inner.users.get_mut(&uid) else { return };
+        user.items.remove(&item_id);
+        if user.items.is_empty() {
+            inner.remove(uid);
+        }
+    }
+
+    /// 用户未过期的曝光
+    pub fn recent(&self, uid: u64) -> HashSet<u64> {
+        let now = Instant::now();
+        let mut inner = self.lock();
+        let Some(user) = inner.users.get_mut(&uid) else { return HashSet::new() };
+        user.items.retain(|_, expires| *expires > now);
+        let recent: HashSet<u64> = user.items.keys().copied().collect();
+        if recent.is_empty() {
+            inner.remove(uid);
+        }
+        recent
+    }
+
+    /// 删除所有曝光都已过期的用户 (`impressions_prune` 任务)；返回删除的用户数
+    ///
+    /// 用户的曝光最晚在最近一次记录后 `ttl` 过期，所以只需要从最早记录的用户开始检查
+    pub fn prune(&self) -> usize {
+        let now = Instant::now();
+        let mut inner = self.lock();
+        let mut removed = 0;
+        while let Some((_, &uid)) = inner.order.first_key_value() {
+            if inner.users[&uid].last_seen + self.ttl > now {
+                break;
+            }
+            inner.remove(uid);
+            removed += 1;
+        }
+        removed
+    }
+
+    /// 跟踪中的用户数 (含尚未清理的过期记录)
+    pub fn users(&self) -> usize {
+        self.lock().users.len()
+    }
+}
+
+/// 把 `recent` 中物品的分数按 `penalty` 比例压低 (按绝对值，负分同样降低) 并重新排序；返回被压低的数量
+pub fn penalize(ranked: &mut Vec<ScoredItem>, recent: &HashSet<u64>, penalty: f32) -> usize {
+    let mut penalized = 0;
+    for item in ranked.iter_mut().filter(|item| recent.contains(&item.item_id)) {
+        item.final_score -= penalty * item.final_score.abs();
+        penalized += 1;
+    }
+    if penalized > 0 {
+        score::sort_desc(ranked, |item| item.final_score);
+    }
+    penalized
+}
+
+// ============================================================================
+// 单元测试
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scored(item_id: u64, final_score: f32) -> ScoredItem {
+        ScoredItem { item_id, sim_score: 0.0, popularity: 0.0, final_score }
+    }
+
+    #[test]
+    fn test_penalize_rotates_recent_items() {
+        let mut ranked = vec![scored(1, 0.9), scored(2, 0.8), scored(3, 0.7), scored(4, -0.1)];
+        let recent = HashSet::from([1, 4]);
+        assert_eq!(penalize(&mut ranked, &recent, 0.5), 2);
+        let ids: Vec<u64> = ranked.iter().map(|r| r.item_id).collect();
+        assert_eq!(ids, vec![2, 3, 1, 4]);
+        assert!((ranked[2].final_score - 0.45).abs() < 1e-6);
+        assert!((ranked[3].final_score + 0.15).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_record_click_and_expiry() {
+        let impressions = RecentImpressions::new(Duration::from_secs(60), 2);
+        impressions.record(1, &[10, 11]);
+        impressions.clicked(1, 10);
+        assert_eq!(impressions.recent(1), HashSet::from([11]));
+        assert!(impressions.recent(2).is_empty());
+
+        // 超出用户数上限时淘汰最久未曝光的用户
+        impressions.record(2, &[20]);
+        impressions.record(3, &[30]);
+        assert_eq!(impressions.users(), 2);
+        assert!(impressions.recent(1).is_empty());
+
+        // 最近重新记录过的用户不会被淘汰
+        impressions.record(2, &[21]);
+        impressions.record(4, &[40]);
+        assert_eq!(impressions.recent(2), HashSet::from([20, 21]));
+        assert!(impressions.recent(3).is_empty());
+
+        let expired = RecentImpressions::new(Duration::ZERO, 2);
+        expired.record(1, &[10]);
+        assert!(expired.recent(1).is_empty());
+        assert_eq!(expired.users(), 0);
+    }
+
+    #[test]
+    fn test_prune_removes_expired_users() {
+        let expired = RecentImpressions::new(Duration::ZERO, 10);
+        expired.record(1, &[10]);
+        expired.record(2, &[20]);
+        assert_eq!(expired.users(), 2);
+        assert_eq!(expired.prune(), 2);
+        assert_eq!(expired.users(), 0);
+
+        let fresh = RecentImpressions::new(Duration::from_secs(60), 10);
+        fresh.record(1, &[10]);
+        assert_eq!(fresh.prune(), 0);
+        assert_eq!(fresh.recent(1), HashSet::from([10]));
+    }
+}
@@ -0,0 +1,265 @@
+//! 后台任务调度 - 按配置的周期在 tokio 中运行维护任务，运行状态通过 `GET /stats` 查看
+//!
+//! 每个任务的周期写作 `"30s"` / `"10m"` / `"1h"` / `"1d"`，`"off"` 表示不运行。
+//! 首次运行在启动 (预热完成) 后一个周期；任务本身是阻塞操作，在 `spawn_blocking` 中执行，
+//! 同一任务不会重叠运行，上一次超时则顺延。
+
+use crate::model::now_ms;
+use crate::service;
+use anyhow::{anyhow, bail, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+use tokio::time::MissedTickBehavior;
+use tracing::{info, warn};
+
+/// 任务周期上限 (更长的周期在计算下次运行时间时会溢出)
+const MAX_PERIOD_SECS: u64 = 365 * 24 * 60 * 60;
+
+/// 任务周期
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Schedule {
+    Off,
+    Every(Duration),
+}
+
+impl Schedule {
+    pub fn every_secs(secs: u64) -> Self {
+        Schedule::Every(Duration::from_secs(secs))
+    }
+}
+
+impl std::str::FromStr for Schedule {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        if s.eq_ignore_ascii_case("off") {
+            return Ok(Schedule::Off);
+        }
+        let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+        let (digits, unit) = s.split_at(split);
+        let n: u64 = digits.parse().map_err(|_| anyhow!("Invalid schedule {:?} (expected e.g. 30s, 10m, 1h, 1d or off)", s))?;
+        let unit_secs: u64 = match unit {
+            "s" => 1,
+            "m" => 60,
+            "h" => 60 * 60,
+            "d" => 24 * 60 * 60,
+            _ => bail!("Invalid schedule {:?} (expected e.g. 30s, 10m, 1h, 1d or off)", s),
+        };
+        let secs = n.checked_mul(unit_secs)
+            .filter(|&secs| secs <= MAX_PERIOD_SECS)
+            .ok_or_else(|| anyhow!("Invalid schedule {:?}: the period must be at most 365d", s))?;
+        if secs == 0 {
+            bail!("Invalid schedule {:?}: the period must be positive (use \"off\" to disable)", s);
+        }
+        Ok(Schedule::every_secs(secs))
+    }
+}
+
+impl fmt::Display for Schedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let secs = match self {
+            Schedule::Off => return f.write_str("off"),
+            Schedule::Every(period) => period.as_secs(),
+        };
+        match secs {
+            s if s % 86_400 == 0 => write!(f, "{}d", s / 86_400),
+            s if s % 3_600 == 0 => write!(f, "{}h", s / 3_600),
+            s if s % 60 == 0 => write!(f, "{}m", s / 60),
+            s => write!(f, "{}s", s),
+        }
+    }
+}
+
+impl TryFrom<String> for Schedule {
+    type Error = anyhow::Error;
+
+    fn try_from(s: String) -> Result<Self> {
+        s.parse()
+    }
+}
+
+impl From<Schedule> for String {
+    fn from(schedule: Schedule) -> Self {
+        schedule.to_string()
+    }
+}
+
+/// 各后台任务的周期 (修改后需重启生效)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct JobsConfig {
+    /// 保存 HNSW 索引并刷写数据库，缩小崩溃时丢失的数据
+    pub index_save: Schedule,
+    /// 删除超过 `request_log.retention_hours` 的请求分析日志
+    pub request_log_prune: Schedule,
+    /// 保存索引并把数据库快照与索引上传到 `[remote]` (未配置时不运行)
+    pub snapshot_upload: Schedule,
+    /// 按请求日志中的曝光与交互日志中的点击重算物品热门度
+    pub popularity_refresh: Schedule,
+    /// 删除超过 `bloom.ttl_days` 没有写入的已看过滤器 (`ttl_days = 0` 时不运行)
+    pub history_prune: Schedule,
+    /// 从内存中删除曝光已全部过期的用户 (见 `impressions.rs`)
+    pub impressions_prune: Schedule,
+}
+
+impl Default for JobsConfig {
+    fn default() -> Self {
+        Self {
+            index_save: Schedule::every_secs(10 * 60),
+            request_log_prune: Schedule::every_secs(60 * 60),
+            snapshot_upload: Schedule::every_secs(60 * 60),
+            popularity_refresh: Schedule::every_secs(60 * 60),
+            history_prune: Schedule::every_secs(60 * 60),
+            impressions_prune: Schedule::every_secs(60),
+        }
+    }
+}
+
+/// 单个任务的运行状态 (`GET /stats` 中的 `jobs`)
+#[derive(Debug, Clone, Serialize)]
+pub struct JobStatus {
+    pub name: &'static str,
+    pub schedule: Schedule,
+    pub running: bool,
+    pub runs: u64,
+    pub failures: u64,
+    /// 上一次开始运行的 Unix 毫秒时间戳
+    pub last_run_ms: Option<u64>,
+    pub last_duration_ms: Option<f64>,
+    pub last_error: Option<String>,
+    pub next_run_ms: Option<u64>,
+}
+
+impl JobStatus {
+    fn new(name: &'static str, schedule: Schedule) -> Self {
+        Self {
+            name,
+            schedule,
+            running: false,
+            runs: 0,
+            failures: 0,
+            last_run_ms: None,
+            last_duration_ms: None,
+            last_error: None,
+            next_run_ms: None,
+        }
+    }
+
+    fn start(&mut self) {
+        self.running = true;
+        self.next_run_ms = None;
+        self.last_run_ms = Some(now_ms());
+    }
+
+    fn finish(&mut self, started: Instant, result: &Result<()>) {
+        self.running = false;
+        self.runs += 1;
+        self.last_duration_ms = Some(service::elapsed_ms(started));
+        self.last_error = result.as_ref().err().map(|e| format!("{:#}", e));
+        if result.is_err() {
+            self.failures += 1;
+        }
+    }
+}
+
+/// 后台任务调度器
+#[derive(Default)]
+pub struct Scheduler {
+    jobs: RwLock<Vec<Arc<Mutex<JobStatus>>>>,
+}
+
+impl Scheduler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 登记并启动一个周期任务 (需在 tokio 运行时中调用)；`Schedule::Off` 时只登记、不运行
+    pub fn spawn<F>(&self, name: &'static str, schedule: Schedule, job: F)
+    where
+        F: Fn() -> Result<()> + Send + Sync + 'static,
+    {
+        let status = Arc::new(Mutex::new(JobStatus::new(name, schedule)));
+        self.jobs.write().unwrap_or_else(|e| e.into_inner()).push(Arc::clone(&status));
+
+        let Schedule::Every(period) = schedule else {
+            info!(job = name, "⏸️  Job disabled");
+            return;
+        };
+        info!(job = name, schedule = %schedule, "⏰ Job scheduled");
+
+        let job = Arc::new(job);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            loop {
+                lock(&status).next_run_ms = Some(now_ms() + period.as_millis() as u64);
+                interval.tick().await;
+
+                lock(&status).start();
+                let started = Instant::now();
+                let job = Arc::clone(&job);
+                let result = tokio::task::spawn_blocking(move || job())
+                    .await
+                    .unwrap_or_else(|e| Err(anyhow!("job panicked: {}", e)));
+                if let Err(e) = &result {
+                    warn!(job = name, error = %format!("{:#}", e), "⚠️  Job failed");
+                }
+                lock(&status).finish(started, &result);
+            }
+        });
+    }
+
+    /// 所有已登记任务的状态 (按登记顺序)
+    pub fn status(&self) -> Vec<JobStatus> {
+        self.jobs.read().unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .map(|status| lock(status).clone())
+            .collect()
+    }
+}
+
+fn lock(status: &Mutex<JobStatus>) -> std::sync::MutexGuard<'_, JobStatus> {
+    status.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+// ============================================================================
+// 单元测试
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schedule_parse_and_display() {
+        assert_eq!("off".parse::<Schedule>().unwrap(), Schedule::Off);
+        assert_eq!("90s".parse::<Schedule>().unwrap(), Schedule::every_secs(90));
+        assert_eq!("10m".parse::<Schedule>().unwrap(), Schedule::every_secs(600));
+        assert_eq!("2d".parse::<Schedule>().unwrap(), Schedule::every_secs(2 * 86_400));
+        assert_eq!("365d".parse::<Schedule>().unwrap(), Schedule::every_secs(365 * 86_400));
+        for bad in ["", "10", "m", "5w", "0s", "-1h", "366d", "18446744073709551615d", "99999999999999999999s"] {
+            assert!(bad.parse::<Schedule>().is_err(), "{:?} should be rejected", bad);
+        }
+
+        assert_eq!(Schedule::every_secs(90).to_string(), "90s");
+        assert_eq!(Schedule::every_secs(3600).to_string(), "1h");
+        assert_eq!(Schedule::Off.to_string(), "off");
+    }
+
+    #[test]
+    fn test_job_status_bookkeeping() {
+        let mut status = JobStatus::new("index_save", Schedule::every_secs(60));
+        status.start();
+        assert!(status.running && status.last_run_ms.is_some());
+
+        status.finish(Instant::now(), &Err(anyhow!("disk full")));
+        status.start();
+        status.finish(Instant::now(), &Ok(()));
+        assert_eq!((status.runs, status.failures, status.running), (2, 1, false));
+        assert!(status.last_error.is_none());
+    }
+}
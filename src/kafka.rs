@@ -0,0 +1,91 @@
+//! Kafka / Redpanda 交互事件消费者 (需要 `kafka` feature)
+//!
+//! 从 `kafka.topic` 读取 JSON 事件，例如 `{"uid": 1, "item_id": 42, "kind": "view"}`
+//! (`ts_ms` 可选，缺省为消费时间)，交给调用方写入已看过滤器与交互日志。
+//! 处理完一条才提交它的 offset (至少一次)；无法解析的消息记录警告后跳过。
+
+use crate::config::KafkaConfig;
+use crate::model::{now_ms, EventKind, InteractionEvent};
+use anyhow::{Context, Result};
+use rdkafka::consumer::{Consumer, StreamConsumer};
+use rdkafka::{ClientConfig, Message};
+use serde::Deserialize;
+use tracing::{debug, info, warn};
+
+/// topic 中的一条事件
+#[derive(Debug, Deserialize)]
+struct EventMessage {
+    uid: u64,
+    item_id: u64,
+    kind: EventKind,
+    ts_ms: Option<u64>,
+}
+
+fn parse_event(payload: &[u8]) -> Result<InteractionEvent> {
+    let message: EventMessage = serde_json::from_slice(payload).context("Invalid event JSON")?;
+    Ok(InteractionEvent {
+        uid: message.uid,
+        item_id: message.item_id,
+        kind: message.kind,
+        ts_ms: message.ts_ms.unwrap_or_else(now_ms),
+    })
+}
+
+/// 持续消费事件，直到消费者无法创建或订阅失败 (单条消息的错误只记录警告)
+pub async fn run(config: &KafkaConfig, handle: impl Fn(InteractionEvent) -> Result<()>) -> Result<()> {
+    let consumer: StreamConsumer = ClientConfig::new()
+        .set("bootstrap.servers", &config.brokers)
+        .set("group.id", &config.group_id)
+        .set("enable.auto.offset.store", "false")
+        .set("auto.offset.reset", if config.from_beginning { "earliest" } else { "latest" })
+        .create()
+        .context("Failed to create Kafka consumer")?;
+    consumer.subscribe(&[&config.topic])
+        .with_context(|| format!("Failed to subscribe to {}", config.topic))?;
+    info!(brokers = %config.brokers, topic = %config.topic, group = %config.group_id, "📥 Consuming interaction events");
+
+    loop {
+        let message = match consumer.recv().await {
+            Ok(message) => message,
+            Err(e) => {
+                warn!(error = %e, "⚠️  Kafka receive failed");
+                continue;
+            }
+        };
+
+        match parse_event(message.payload().unwrap_or_default()) {
+            Ok(event) => {
+                debug!(uid = event.uid, item_id = event.item_id, kind = ?event.kind, "Consumed event");
+                if let Err(e) = handle(event) {
+                    warn!(error = %format!("{:#}", e), offset = message.offset(), "⚠️  Failed to record event");
+                }
+            }
+            Err(e) => warn!(error = %format!("{:#}", e), offset = message.offset(), "⚠️  Skipping malformed event"),
+        }
+
+        if let Err(e) = consumer.store_offset_from_message(&message) {
+            warn!(error = %e, "⚠️  Failed to store Kafka offset");
+        }
+    }
+}
+
+// ============================================================================
+// 单元测试
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_event() {
+        let event = parse_event(br#"{"uid": 1, "item_id": 42, "kind": "click", "ts_ms": 1700000000000}"#).unwrap();
+        assert_eq!((event.uid, event.item_id, event.kind, event.ts_ms), (1, 42, EventKind::Click, 1_700_000_000_000));
+
+        let event = parse_event(br#"{"uid": 1, "item_id": 42, "kind": "view"}"#).unwrap();
+        assert!(event.ts_ms > 0);
+
+        assert!(parse_event(br#"{"uid": 1, "kind": "view"}"#).is_err());
+        assert!(parse_event(b"").is_err());
+    }
+}
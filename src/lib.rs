@@ -0,0 +1,51 @@
+//! Mini-RecSys 核心库 - 存储、向量检索、语义编码与排序流水线
+//!
+//! 嵌入其他 Rust 服务时通常只需要 [`recsys::RecSys`] 门面；
+//! `main.rs` 中的 HTTP 服务与 benches/ 中的基准测试都基于这里导出的模块。
+
+pub mod ffi;
+#[cfg(not(feature = "cpp-hnsw"))]
+mod brute_force;
+pub mod model;
+pub mod i18n;
+pub mod storage;
+pub mod embedding;
+pub mod text_search;
+pub mod hybrid;
+pub mod ann;
+pub mod service;
+pub mod eval;
+pub mod simulate;
+pub mod replay;
+pub mod experiment;
+pub mod popularity;
+pub mod lookalike;
+pub mod export;
+pub mod metrics;
+pub mod slowlog;
+pub mod error;
+pub mod config;
+pub mod bench;
+pub mod catalog;
+pub mod recsys;
+pub mod warmup;
+pub mod jobs;
+pub mod cache;
+pub mod pagination;
+pub mod impressions;
+pub mod fields;
+pub mod events;
+pub mod remote;
+pub mod rerank;
+pub mod score;
+pub mod telemetry;
+pub mod webhooks;
+pub mod npy;
+pub mod csv_import;
+pub mod faiss;
+#[cfg(feature = "parquet")]
+pub mod columnar;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+#[cfg(feature = "kafka")]
+pub mod kafka;
@@ -0,0 +1,179 @@
+//! 相似用户召回 - "和你相似的用户也看过"
+//!
+//! 用户向量与物品向量在同一空间，按点积找出最相似的用户 (`GET /users/{id}/similar`)，
+//! 再把这些用户最近浏览 / 点击过的物品作为 /recommend 的另一路召回，
+//! 给只依赖内容相似度的召回补上协同信号。
+//!
+//! 用户数远少于物品数，用户索引是对目录用户的精确暴力检索。
+//! 浏览历史按用户保存在内存中: 启动时从交互日志加载，之后随每条交互更新，每个用户只保留最近 `lookalike.history_len` 个物品。
+
+use crate::ann::AnnIndex;
+use crate::ffi::compute_dot_product;
+use crate::model::{InteractionEvent, User};
+use crate::score;
+use std::collections::{HashMap, VecDeque};
+use std::sync::RwLock;
+
+/// 对目录用户逐个计算相似度
+#[derive(Debug, Clone, Copy)]
+pub struct UserIndex<'a> {
+    users: &'a [User],
+}
+
+impl<'a> UserIndex<'a> {
+    pub fn new(users: &'a [User]) -> Self {
+        Self { users }
+    }
+
+    /// 与 `user` 最相似的 `k` 个其他用户 (uid, similarity)，按相似度降序
+    pub fn neighbors(&self, user: &User, k: usize) -> Vec<(u64, f32)> {
+        let hits = self.search(&user.embedding, k + 1).unwrap_or_default();
+        hits.into_iter().filter(|&(uid, _)| uid != user.id).take(k).collect()
+    }
+}
+
+impl AnnIndex for UserIndex<'_> {
+    fn search(&self, query: &[f32], k: usize) -> Result<Vec<(u64, f32)>, String> {
+        let mut hits: Vec<(u64, f32)> = self.users.iter()
+            .filter_map(|user| Some((user.id, compute_dot_product(query, &user.embedding)?)))
+            .collect();
+        score::sort_desc(&mut hits, |&(_, similarity)| similarity);
+        hits.truncate(k);
+        Ok(hits)
+    }
+
+    fn len(&self) -> usize {
+        self.users.len()
+    }
+
+    fn name(&self) -> &'static str {
+        "users"
+    }
+}
+
+/// 用户 -> 最近交互过的物品 (由新到旧，不重复)
+pub struct UserHistories {
+    history_len: usize,
+    users: RwLock<HashMap<u64, VecDeque<u64>>>,
+}
+
+impl UserHistories {
+    /// 每个用户最多保留 `history_len` 个物品
+    pub fn new(history_len: usize) -> Self {
+        Self { history_len, users: RwLock::new(HashMap::new()) }
+    }
+
+    /// 按时间顺序回放交互日志
+    pub fn load<'a>(&self, events: impl IntoIterator<Item = &'a InteractionEvent>) {
+        for event in events {
+            self.record(event.uid, event.item_id);
+        }
+    }
+
+    /// 记录一次交互；已在历史中的物品移到最前
+    pub fn record(&self, uid: u64, item_id: u64) {
+        if self.history_len == 0 {
+            return;
+        }
+        let mut users = self.users.write().unwrap_or_else(|e| e.into_inner());
+        let history = users.entry(uid).or_default();
+        history.retain(|&id| id != item_id);
+        history.push_front(item_id);
+        history.truncate(self.history_len);
+    }
+
+    /// 用户最近交互过的物品 (由新到旧)
+    pub fn items(&self, uid: u64) -> Vec<u64> {
+        let users = self.users.read().unwrap_or_else(|e| e.into_inner());
+        users.get(&uid).map(|history| history.iter().copied().collect()).unwrap_or_default()
+    }
+
+    /// 有历史记录的用户数
+    pub fn users(&self) -> usize {
+        self.users.read().unwrap_or_else(|e| e.into_inner()).len()
+    }
+}
+
+/// 相似用户交互过的物品作为召回候选: 分数为交互过它的最相似用户的相似度 × `weight`
+pub fn recall(neighbors: &[(u64, f32)], histories: &UserHistories, weight: f32) -> Vec<(u64, f32)> {
+    let mut scores: HashMap<u64, f32> = HashMap::new();
+    for &(uid, similarity) in neighbors.iter().filter(|(_, similarity)| *similarity > 0.0) {
+        for item_id in histories.items(uid) {
+            let score = scores.entry(item_id).or_insert(f32::MIN);
+            *score = score.max(similarity * weight);
+        }
+    }
+    let mut candidates: Vec<(u64, f32)> = scores.into_iter().collect();
+    score::sort_desc(&mut candidates, |&(_, score)| score);
+    candidates
+}
+
+/// 把相似用户召回并入 ANN 召回: 两路都有的物品取较高的分数；返回只来自相似用户的候选数
+pub fn merge(candidates: &mut Vec<(u64, f32)>, lookalike: Vec<(u64, f32)>) -> usize {
+    let positions: HashMap<u64, usize> = candidates.iter().enumerate().map(|(idx, &(id, _))| (id, idx)).collect();
+    let mut added = 0;
+    for (item_id, score) in lookalike {
+        match positions.get(&item_id) {
+            Some(&idx) => candidates[idx].1 = candidates[idx].1.max(score),
+            None => {
+                candidates.push((item_id, score));
+                added += 1;
+            }
+        }
+    }
+    added
+}
+
+// ============================================================================
+// 单元测试
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user(id: u64, embedding: Vec<f32>) -> User {
+        User { id, name: format!("user {}", id), embedding }
+    }
+
+    #[test]
+    fn test_neighbors_exclude_self() {
+        let users = vec![user(1, vec![1.0, 0.0]), user(2, vec![0.6, 0.8]), user(3, vec![0.0, 1.0]), user(4, vec![0.8, 0.6])];
+        let neighbors = UserIndex::new(&users).neighbors(&users[0], 2);
+        let ids: Vec<u64> = neighbors.iter().map(|&(id, _)| id).collect();
+        assert_eq!(ids, vec![4, 2]);
+        assert!((neighbors[0].1 - 0.8).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_histories_keep_recent_unique_items() {
+        let histories = UserHistories::new(3);
+        for item_id in [1, 2, 3, 2, 4] {
+            histories.record(7, item_id);
+        }
+        assert_eq!(histories.items(7), vec![4, 2, 3]);
+        assert!(histories.items(8).is_empty());
+
+        let disabled = UserHistories::new(0);
+        disabled.record(7, 1);
+        assert_eq!(disabled.users(), 0);
+    }
+
+    #[test]
+    fn test_recall_and_merge() {
+        let histories = UserHistories::new(10);
+        histories.record(2, 10);
+        histories.record(2, 11);
+        histories.record(3, 11);
+        histories.record(4, 12);
+
+        // 用户 4 不相似 (相似度 <= 0) 不参与召回
+        let neighbors = vec![(3, 0.9), (2, 0.5), (4, -0.2)];
+        let lookalike = recall(&neighbors, &histories, 0.5);
+        assert_eq!(lookalike, vec![(11, 0.45), (10, 0.25)]);
+
+        let mut candidates = vec![(11, 0.3), (20, 0.2)];
+        assert_eq!(merge(&mut candidates, lookalike), 1);
+        assert_eq!(candidates, vec![(11, 0.45), (20, 0.2), (10, 0.25)]);
+    }
+}
@@ -1,46 +1,138 @@
 //! Mini-RecSys - 混合 Rust/C++ 推荐系统 Demo
 
-mod ffi;
-mod model;
-mod storage;
-mod embedding;
-mod text_search;
-mod hybrid;
-
 use anyhow::Result;
+use mini_recsys::{bench, csv_import, eval, experiment, export, metrics, model, replay, service, simulate};
+use mini_recsys::config::{Config, PathOverrides, RerankConfig, ServerConfig, TlsConfig};
+use mini_recsys::cache::{CachedRecommendation, RecommendationCache};
+use mini_recsys::remote::RemoteStore;
+use mini_recsys::rerank::{self, HttpReranker, RerankCandidate, RerankContext};
+use mini_recsys::telemetry::{self, Telemetry};
+use mini_recsys::error::ApiError;
+use mini_recsys::jobs::{JobStatus, Scheduler};
+use mini_recsys::catalog::Catalog;
+use mini_recsys::fields::{self, FieldSet, Sparse};
+use mini_recsys::i18n;
+use mini_recsys::pagination::{PageSnapshots, PageToken, Snapshot};
+use mini_recsys::recsys::{RecSys, RecommendOptions, Recommendation, SearchOptions};
+use mini_recsys::slowlog::{SlowQueryLog, SlowRequest};
+use mini_recsys::ann::AnnIndex;
+use mini_recsys::warmup::{Warmup, WarmupStatus};
+use mini_recsys::webhooks::WebhookEvent;
+use clap::{Parser, Subcommand};
 use axum::{
-    extract::{Query, State},
-    http::StatusCode,
+    extract::{Path, Query, State},
+    http::HeaderMap,
     response::Json,
     routing::{get, post},
     Router,
 };
-use fastbloom_rs::Membership;
-use ffi::{add_item_to_hnsw, get_hnsw_count, hnsw_search, load_hnsw_index, save_hnsw_index};
-use model::{generate_category_embedding, generate_user_embedding, generate_random_embedding, Item, ItemJson, User, DIM};
+use mini_recsys::hybrid::{FusionStrategy, SourceContribution};
+use mini_recsys::model::{EventKind, ExperimentTag, InteractionEvent, ItemJson, LoggedRequest, RequestRecord, User};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
-use storage::Storage;
-use text_search::TextSearch;
-use tower_http::cors::CorsLayer;
-use axum::http::{Method, HeaderValue};
-
-const INDEX_PATH: &str = "data/index.bin";
-const DB_PATH: &str = "data/db";
-const MIN_RECOMMENDATIONS: usize = 5;
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::Instant;
+use mini_recsys::service::{RankOutput, RankWeights, ScoredItem, StageCounts, StageTimings, MAX_RECOMMENDATIONS};
+use mini_recsys::storage::Storage;
+use tower_http::cors::{AllowOrigin, CorsLayer};
+use tracing::{error, info, warn};
+use tracing_subscriber::{fmt::format::FmtSpan, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter, Layer};
+use mini_recsys::config::TelemetryConfig;
+use axum::http::{header, Method, HeaderValue};
 
 // ============================================================================
 // AppState
 // ============================================================================
 
 pub struct AppState {
-    pub storage: Arc<Storage>,
-    pub users: Vec<User>,
-    pub items: Vec<Item>,
-    pub item_map: HashMap<u64, usize>,
-    pub embedding_model: Option<Arc<embedding::EmbeddingModel>>,
-    pub text_search: Arc<TextSearch>,
+    /// 预热完成前为空
+    pub recsys: OnceLock<RecSys>,
+    pub warmup: Warmup,
+    /// 启动时加载的配置，预热完成前使用
+    pub initial_config: Arc<Config>,
+    pub slow_log: SlowQueryLog,
+    /// 预热完成后登记后台任务
+    pub scheduler: Scheduler,
+    /// Redis 推荐结果缓存 (配置了 `[cache]` 时)
+    pub cache: Option<RecommendationCache>,
+    /// 快照与索引所在的对象存储 (配置了 `[remote]` 时)
+    pub remote: Option<RemoteStore>,
+    /// 外部重排服务 (配置了 `[rerank]` 时)
+    pub reranker: Option<HttpReranker>,
+    /// /recommend 翻页快照
+    pub pages: PageSnapshots,
+}
+
+impl AppState {
+    fn recsys(&self) -> Result<&RecSys, ApiError> {
+        self.recsys.get().ok_or(ApiError::WarmingUp)
+    }
+
+    fn storage(&self) -> Result<&Storage, ApiError> {
+        Ok(self.recsys()?.storage())
+    }
+
+    /// 当前生效的配置
+    fn config(&self) -> Arc<Config> {
+        self.recsys.get().map_or_else(|| Arc::clone(&self.initial_config), RecSys::config)
+    }
+
+    /// 配置了 `[rerank]` 且对该接口启用时，交给外部服务重排前 K 个结果；返回是否应用了重排
+    ///
+    /// `enabled` 选择 `rerank.recommend` 或 `rerank.search`，`score` 取出本地排序分。
+    async fn rerank<T>(
+        &self,
+        recsys: &RecSys,
+        enabled: impl Fn(&RerankConfig) -> bool,
+        context: RerankContext,
+        items: Vec<T>,
+        id_and_score: impl Fn(&T) -> (u64, f32),
+    ) -> (Vec<T>, bool) {
+        let config = self.config();
+        let (Some(reranker), Some(rerank_config)) = (&self.reranker, config.rerank.as_ref().filter(|c| enabled(c))) else {
+            return (items, false);
+        };
+        let catalog = recsys.catalog();
+        let describe = |item: &T| {
+            let (id, score) = id_and_score(item);
+            let (name, category) = catalog.item(id).map(|i| (i.name.clone(), i.category.clone())).unwrap_or_default();
+            RerankCandidate { id, name, category, score }
+        };
+        rerank::rerank_top_k(reranker, rerank_config.top_k, rerank_config.timeout(), &context, items, describe).await
+    }
+
+    /// 记录一次已返回的请求 (供 replay / eval / CTR 统计使用)，写入失败只打印警告
+    ///
+    /// 推荐结果同时记为该用户的近期曝光。
+    fn log_request(
+        &self,
+        request: LoggedRequest,
+        results: Vec<(u64, f32)>,
+        experiment: Option<ExperimentTag>,
+        latency_ms: f64,
+    ) {
+        let (item_ids, scores): (Vec<u64>, Vec<f32>) = results.into_iter().unzip();
+        let Some(recsys) = self.recsys.get() else { return };
+        if let LoggedRequest::Recommend { uid } = request {
+            recsys.record_impressions(uid, &item_ids);
+        }
+        let record = RequestRecord { ts_ms: model::now_ms(), request, item_ids, scores, experiment, latency_ms };
+        if let Err(e) = recsys.log_impression(&record) {
+            warn!(error = %e, "Failed to log request");
+        }
+    }
+}
+
+#[cfg(feature = "grpc")]
+impl mini_recsys::grpc::GrpcState for AppState {
+    fn recsys(&self) -> Result<&RecSys, ApiError> {
+        AppState::recsys(self)
+    }
+
+    fn log_request(&self, request: LoggedRequest, results: Vec<(u64, f32)>, experiment: Option<ExperimentTag>, latency_ms: f64) {
+        AppState::log_request(self, request, results, experiment, latency_ms)
+    }
 }
 
 // ============================================================================
@@ -48,7 +140,34 @@ pub struct AppState {
 // ============================================================================
 
 #[derive(Deserialize)]
-struct RecommendQuery { uid: u64 }
+struct RecommendQuery {
+    uid: u64,
+    /// 为 true 时在响应中附带各阶段耗时与候选数量
+    #[serde(default)]
+    debug: bool,
+    /// 每页结果数 (1..=50)；设置后响应带 `next_page_token`
+    page_size: Option<usize>,
+    /// 上一页响应中的 `next_page_token`，从同一份快照中取下一页
+    page_token: Option<String>,
+    /// 只返回这些物品字段，逗号分隔 (例如 `item_id,name,final_score`)
+    fields: Option<String>,
+    /// 物品标题的语言，优先于 Accept-Language
+    lang: Option<String>,
+    /// 覆盖 `recall.k` (1..=recall.max_k)
+    recall_k: Option<usize>,
+    /// 覆盖 `recall.min_results` (0..=50)
+    min_results: Option<usize>,
+}
+
+/// /recommend 每页结果数上限
+const MAX_PAGE_SIZE: usize = 50;
+
+/// 调试信息 (仅 debug=true)
+#[derive(Serialize)]
+struct DebugInfo {
+    timing: StageTimings,
+    counts: StageCounts,
+}
 
 #[derive(Serialize)]
 struct RecommendItem {
@@ -60,347 +179,1067 @@ struct RecommendItem {
     sim_score: f32,
     popularity: f32,
     final_score: f32,
+    /// 各召回来源的排名与分数贡献 (仅 /search?debug=true)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fusion: Option<Vec<SourceContribution>>,
 }
 
+/// `?fields=` 可选的物品字段
+const ITEM_FIELDS: &[&str] = &["item_id", "name", "category", "image_url", "price", "sim_score", "popularity", "final_score", "fusion"];
+
+/// `GET /items/:id` 返回的物品 (没有排序分数)
 #[derive(Serialize)]
-struct UserInfo { id: u64, name: String }
+struct ItemDetail {
+    item_id: u64,
+    name: String,
+    category: String,
+    image_url: String,
+    price: f32,
+    popularity: f32,
+}
+
+/// `GET /items/:id` 的 `?fields=` 可选字段
+const ITEM_DETAIL_FIELDS: &[&str] = &["item_id", "name", "category", "image_url", "price", "popularity"];
+
+/// 结果物品的展示方式
+struct ItemView {
+    /// 标题语言 (已规范化)
+    lang: String,
+    /// `?fields=`；为 None 时返回完整的物品对象
+    fields: Option<FieldSet>,
+}
+
+impl ItemView {
+    /// 展示语言按 `?lang=` > Accept-Language > `i18n.default_lang` 选择 (只选 `i18n.languages` 中的语言)
+    fn new(config: &Config, lang: Option<&str>, fields: Option<&str>, headers: &HeaderMap) -> Result<Self, ApiError> {
+        Self::with_fields(config, lang, fields, ITEM_FIELDS, headers)
+    }
+
+    /// 同 `new`，`?fields=` 只能从 `allowed` 中选择
+    fn with_fields(config: &Config, lang: Option<&str>, fields: Option<&str>, allowed: &[&str], headers: &HeaderMap) -> Result<Self, ApiError> {
+        let fields = fields.map(|spec| FieldSet::parse(spec, allowed).map_err(ApiError::InvalidInput)).transpose()?;
+        let mut requested: Vec<String> = lang.map(i18n::normalize).into_iter().collect();
+        if let Some(accept) = headers.get(header::ACCEPT_LANGUAGE).and_then(|v| v.to_str().ok()) {
+            requested.extend(i18n::parse_accept_language(accept));
+        }
+        let supported = config.i18n.supported();
+        let lang = i18n::negotiate(&requested, &supported).unwrap_or(&supported[0]).to_string();
+        Ok(Self { lang, fields })
+    }
+
+    fn select(&self, items: Vec<RecommendItem>) -> Sparse<RecommendItem> {
+        Sparse::new(items, self.fields.clone())
+    }
+}
 
 #[derive(Serialize)]
-struct RecommendResponse { user: UserInfo, recommendations: Vec<RecommendItem>, filtered_count: usize }
+struct UserInfo { id: u64, name: String }
 
 #[derive(Serialize)]
-struct ErrorResponse { error: String }
+struct RecommendResponse {
+    user: UserInfo,
+    /// 物品标题的语言
+    lang: String,
+    recommendations: Sparse<RecommendItem>,
+    filtered_count: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    experiment: Option<ExperimentTag>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    debug: Option<DebugInfo>,
+    /// 预热期间返回的是按热门度排序的兜底推荐
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    warming: bool,
+    /// 结果来自 Redis 共享缓存
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    cached: bool,
+    /// 前 K 个结果经过外部服务重排
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    reranked: bool,
+    /// ANN 索引不可用，召回降级为暴力检索
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    degraded: bool,
+    /// 翻页时还有下一页
+    #[serde(skip_serializing_if = "Option::is_none")]
+    next_page_token: Option<String>,
+}
 
 #[derive(Serialize)]
 struct UsersResponse { users: Vec<UserInfo> }
 
+/// /users/:id/similar 默认与最多返回的用户数
+const DEFAULT_SIMILAR_USERS: usize = 10;
+const MAX_SIMILAR_USERS: usize = 100;
+
+#[derive(Deserialize)]
+struct SimilarUsersQuery { k: Option<usize> }
+
+#[derive(Deserialize)]
+struct ItemQuery {
+    /// 只返回这些物品字段，逗号分隔 (见 `ITEM_DETAIL_FIELDS`)
+    fields: Option<String>,
+    /// 物品标题的语言 (同 /recommend)
+    lang: Option<String>,
+}
+
+#[derive(Serialize)]
+struct ItemResponse {
+    /// 物品标题的语言
+    lang: String,
+    /// 按 `?fields=` 裁剪后的物品
+    item: serde_json::Value,
+}
+
+#[derive(Serialize)]
+struct SimilarUser {
+    id: u64,
+    name: String,
+    /// 用户向量的点积 (向量已归一化，即余弦相似度)
+    similarity: f32,
+}
+
+#[derive(Serialize)]
+struct SimilarUsersResponse { user: UserInfo, similar: Vec<SimilarUser> }
+
 #[derive(Deserialize)]
 struct MarkSeenRequest { uid: u64, item_ids: Vec<u64> }
 
 #[derive(Serialize)]
-struct MarkSeenResponse { marked: usize }
+struct MarkSeenResponse {
+    /// 写入已看过滤器的物品数 (去重后)
+    marked: usize,
+    /// 目录中不存在、未写入的 ID (按提交顺序)
+    unknown: Vec<u64>,
+}
+
+/// `/mark_seen` 单次请求的 ID 数上限
+const MAX_MARK_SEEN_ITEMS: usize = 1000;
+
+#[derive(Deserialize)]
+struct FeedbackRequest {
+    uid: u64,
+    item_id: u64,
+    #[serde(default = "default_feedback_kind")]
+    kind: EventKind,
+}
+
+fn default_feedback_kind() -> EventKind { EventKind::Click }
+
+#[derive(Serialize)]
+struct FeedbackResponse { recorded: bool }
+
+#[derive(Serialize)]
+struct CsvValidation {
+    /// 通过校验的行数
+    valid: usize,
+    /// 其中目录中尚不存在的 ID 数
+    new: usize,
+    /// 其中会覆盖已有物品的 ID 数
+    updated: usize,
+    errors: Vec<csv_import::RowError>,
+}
+
+#[derive(Serialize)]
+struct CsvImportResponse {
+    /// 写入 (或覆盖) 的物品数
+    imported: usize,
+    /// 被跳过的行
+    errors: Vec<csv_import::RowError>,
+}
+
+#[derive(Serialize)]
+struct IngestResponse { item_id: u64 }
+
+/// 单条文本或一批文本
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum EmbedInput {
+    One(String),
+    Many(Vec<String>),
+}
 
 #[derive(Deserialize)]
-struct SearchQuery { q: String }
+struct EmbedRequest {
+    texts: EmbedInput,
+}
 
 #[derive(Serialize)]
-struct SearchResponse { query: String, results: Vec<RecommendItem> }
+struct EmbedResponse {
+    dim: usize,
+    /// 与 `texts` 顺序一致的 L2 归一化向量
+    embeddings: Vec<Vec<f32>>,
+}
+
+/// `/embed` 单次请求的文本数上限
+const MAX_EMBED_TEXTS: usize = 256;
+
+#[derive(Deserialize)]
+struct RequestLogQuery {
+    #[serde(default)]
+    since_ms: u64,
+    #[serde(default = "default_request_log_limit")]
+    limit: usize,
+}
+
+fn default_request_log_limit() -> usize { 100 }
+
+#[derive(Deserialize)]
+struct SearchQuery {
+    q: String,
+    /// 融合策略: rrf / minmax / zscore，未指定时使用 `search.fusion`
+    fusion: Option<FusionStrategy>,
+    /// 为 true 时在每条结果中附带融合明细，并在响应中附带各阶段耗时与候选数量
+    #[serde(default)]
+    debug: bool,
+    /// 只返回这些物品字段，逗号分隔 (同 /recommend)
+    fields: Option<String>,
+    /// 物品标题的语言 (同 /recommend)
+    lang: Option<String>,
+}
+
+#[derive(Serialize)]
+struct SearchResponse {
+    query: String,
+    /// 物品标题的语言
+    lang: String,
+    results: Sparse<RecommendItem>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fusion_strategy: Option<FusionStrategy>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    debug: Option<DebugInfo>,
+    /// 前 K 个结果经过外部服务重排
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    reranked: bool,
+    /// ANN 索引不可用，向量召回降级为暴力检索
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    degraded: bool,
+}
 
 // ============================================================================
 // Handlers
 // ============================================================================
 
+#[tracing::instrument(skip_all, fields(uid = params.uid))]
 async fn recommend_handler(
     State(state): State<Arc<AppState>>,
     Query(params): Query<RecommendQuery>,
-) -> Result<Json<RecommendResponse>, (StatusCode, Json<ErrorResponse>)> {
-    let user = state.users.iter()
-        .find(|u| u.id == params.uid)
-        .ok_or_else(|| (StatusCode::NOT_FOUND, Json(ErrorResponse {
-            error: format!("User {} not found", params.uid),
-        })))?;
-
-    // Step A: 召回 Top-100
-    let candidates = hnsw_search(&user.embedding, 100);
-
-    // Step B: 获取用户的 Bloom Filter
-    let filter = state.storage.get_user_filter(params.uid)
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse {
-            error: format!("Failed to get filter: {}", e),
-        })))?;
-
-    // Step C: 过滤已看过的商品
-    let mut filtered_count = 0;
-    let mut recommendations: Vec<RecommendItem> = candidates.into_iter()
-        .filter_map(|(item_id, sim_score)| {
-            // 检查是否已看过
-            if filter.contains(&item_id.to_le_bytes()) {
-                filtered_count += 1;
-                return None;
-            }
-            
-            let idx = *state.item_map.get(&item_id)?;
-            let item = &state.items[idx];
-            let final_score = sim_score * 0.7 + item.popularity * 0.3;
+    headers: HeaderMap,
+) -> Result<Json<RecommendResponse>, ApiError> {
+    let start = Instant::now();
+    let view = ItemView::new(&state.config(), params.lang.as_deref(), params.fields.as_deref(), &headers)?;
+    let Ok(recsys) = state.recsys() else {
+        return warming_recommendations(&state, params.uid, view);
+    };
+    let catalog = recsys.catalog();
+    let user = catalog.user(params.uid)
+        .ok_or(ApiError::UserNotFound(params.uid))?;
+
+    let paged = params.page_size.is_some() || params.page_token.is_some();
+    let page_size = params.page_size.unwrap_or(MAX_RECOMMENDATIONS);
+    if !(1..=MAX_PAGE_SIZE).contains(&page_size) {
+        return Err(ApiError::InvalidInput(format!("page_size must be between 1 and {}", MAX_PAGE_SIZE)));
+    }
+    let max_recall_k = state.config().recall.max_k;
+    if params.recall_k.is_some_and(|k| !(1..=max_recall_k).contains(&k)) {
+        return Err(ApiError::InvalidInput(format!("recall_k must be between 1 and {}", max_recall_k)));
+    }
+    if params.min_results.is_some_and(|n| n > MAX_PAGE_SIZE) {
+        return Err(ApiError::InvalidInput(format!("min_results must be at most {}", MAX_PAGE_SIZE)));
+    }
+    let overridden = params.recall_k.is_some() || params.min_results.is_some();
+    if let Some(token) = &params.page_token {
+        return snapshot_page(&state, &catalog, user, token, page_size, view, start);
+    }
+
+    // 共享缓存 (debug 请求需要各阶段耗时，总是重新计算；翻页的第一页需要完整结果；
+    // 有近期曝光时结果会轮换，缓存的列表已经过时；覆盖了召回参数的结果不同于默认结果)
+    let cache = state.cache.as_ref().filter(|_| !params.debug && !paged && !overridden && !recsys.rotates(user.id));
+    if let Some(cached) = match cache { Some(cache) => cache.get(user.id).await, None => None } {
+        let CachedRecommendation { ranked, filtered_count, experiment } = cached;
+        state.log_request(
+            LoggedRequest::Recommend { uid: user.id },
+            ranked.iter().map(|r| (r.item_id, r.final_score)).collect(),
+            experiment.clone(),
+            service::elapsed_ms(start),
+        );
+        return Ok(Json(RecommendResponse {
+            user: UserInfo { id: user.id, name: user.name.clone() },
+            recommendations: view.select(recommend_items(&catalog, ranked, &view.lang)),
+            lang: view.lang,
+            filtered_count,
+            experiment,
+            debug: None,
+            warming: false,
+            cached: true,
+            reranked: false,
+            degraded: false,
+            next_page_token: None,
+        }));
+    }
+
+    let opts = RecommendOptions {
+        limit: paged.then(|| state.config().pagination.max_results),
+        recall_k: params.recall_k,
+        min_results: params.min_results,
+        ..RecommendOptions::default()
+    };
+    let (uid, queued) = (user.id, Instant::now());
+    let Recommendation { output, experiment } = run_blocking(&state, ApiError::internal("Recommendation failed"), move |recsys| {
+        let queue_ms = service::elapsed_ms(queued);
+        Ok(recsys.recommend(uid, opts)?.map(|mut rec| {
+            rec.output.timings.queue_ms = queue_ms;
+            rec
+        }))
+    })
+        .await?
+        .ok_or(ApiError::UserNotFound(params.uid))?;
+    let RankOutput { ranked, filtered_count, mut timings, counts } = output;
+    metrics::PIPELINE.record_recommend(&counts, filtered_count);
+
+    let rerank_start = Instant::now();
+    let context = RerankContext::User { uid: user.id, name: user.name.clone() };
+    let (ranked, reranked) = state.rerank(recsys, |c| c.recommend, context, ranked, |r| (r.item_id, r.final_score)).await;
+    if reranked {
+        timings.rerank_ms = service::elapsed_ms(rerank_start);
+    }
+
+    // 翻页时保存完整结果的快照，只返回第一页 (一页放得下时不保存)
+    let (ranked, next_page_token) = if paged && ranked.len() > page_size {
+        let page = ranked[..page_size].to_vec();
+        let snapshot = Snapshot { uid: user.id, ranked, filtered_count, experiment: experiment.clone(), reranked, degraded: counts.degraded };
+        let token = PageToken { snapshot: state.pages.insert(snapshot), offset: page_size };
+        (page, Some(token.to_string()))
+    } else {
+        (ranked, None)
+    };
+
+    state.log_request(
+        LoggedRequest::Recommend { uid: user.id },
+        ranked.iter().map(|r| (r.item_id, r.final_score)).collect(),
+        experiment.clone(),
+        service::elapsed_ms(start),
+    );
+    // 降级结果不写入共享缓存，索引恢复后即可返回正常召回的结果
+    if let Some(cache) = cache.filter(|_| !counts.degraded) {
+        let entry = CachedRecommendation { ranked: ranked.clone(), filtered_count, experiment: experiment.clone() };
+        cache.put(user.id, &entry).await;
+    }
+
+    let recommendations = view.select(recommend_items(&catalog, ranked, &view.lang));
+    let degraded = counts.degraded;
+
+    timings.total_ms = service::elapsed_ms(start);
+    state.slow_log.record(SlowRequest::Recommend { uid: user.id, experiment: experiment.clone() }, &timings, &counts);
+    Ok(Json(RecommendResponse {
+        user: UserInfo { id: user.id, name: user.name.clone() },
+        lang: view.lang,
+        recommendations,
+        filtered_count,
+        experiment,
+        debug: params.debug.then_some(DebugInfo { timing: timings, counts }),
+        warming: false,
+        cached: false,
+        reranked,
+        degraded,
+        next_page_token,
+    }))
+}
+
+/// 从翻页快照中切出 `token` 指向的一页，不重新召回
+fn snapshot_page(
+    state: &AppState,
+    catalog: &Catalog,
+    user: &User,
+    token: &str,
+    page_size: usize,
+    view: ItemView,
+    start: Instant,
+) -> Result<Json<RecommendResponse>, ApiError> {
+    let token: PageToken = token.parse().map_err(ApiError::InvalidInput)?;
+    let snapshot = state.pages.get(token.snapshot).ok_or(ApiError::PageTokenExpired)?;
+    if snapshot.uid != user.id {
+        return Err(ApiError::InvalidInput(format!("page_token was not issued for user {}", user.id)));
+    }
+
+    let (page, next) = snapshot.page(token.offset, page_size);
+    state.log_request(
+        LoggedRequest::Recommend { uid: user.id },
+        page.iter().map(|r| (r.item_id, r.final_score)).collect(),
+        snapshot.experiment.clone(),
+        service::elapsed_ms(start),
+    );
+    Ok(Json(RecommendResponse {
+        user: UserInfo { id: user.id, name: user.name.clone() },
+        recommendations: view.select(recommend_items(catalog, page.to_vec(), &view.lang)),
+        lang: view.lang,
+        filtered_count: snapshot.filtered_count,
+        experiment: snapshot.experiment.clone(),
+        debug: None,
+        warming: false,
+        cached: false,
+        reranked: snapshot.reranked,
+        degraded: snapshot.degraded,
+        next_page_token: next.map(|offset| PageToken { snapshot: token.snapshot, offset }.to_string()),
+    }))
+}
+
+/// 用本地目录补全排序结果中的物品详情 (已不在目录中的物品跳过)，标题使用 `lang` 语言
+fn recommend_items(catalog: &Catalog, ranked: Vec<ScoredItem>, lang: &str) -> Vec<RecommendItem> {
+    ranked.into_iter()
+        .filter_map(|scored| {
+            let item = catalog.item(scored.item_id)?;
             Some(RecommendItem {
-                item_id,
-                name: item.name.clone(),
+                item_id: scored.item_id,
+                name: item.title(lang).to_string(),
                 category: item.category.clone(),
                 image_url: item.image_url.clone(),
                 price: item.price,
-                sim_score,
-                popularity: item.popularity,
-                final_score,
+                sim_score: scored.sim_score,
+                popularity: scored.popularity,
+                final_score: scored.final_score,
+                fusion: None,
             })
         })
-        .collect();
+        .collect()
+}
 
-    recommendations.sort_by(|a, b| b.final_score.partial_cmp(&a.final_score).unwrap());
-    
-    // Step D: 降级填充 (Fallback)
-    if recommendations.len() < MIN_RECOMMENDATIONS {
-        // 从热门商品中随机补充
-        let mut popular_items: Vec<_> = state.items.iter()
-            .filter(|item| !filter.contains(&item.id.to_le_bytes()))
-            .collect();
-        popular_items.sort_by(|a, b| b.popularity.partial_cmp(&a.popularity).unwrap());
-        
-        for item in popular_items.into_iter().take(MIN_RECOMMENDATIONS - recommendations.len()) {
-            if !recommendations.iter().any(|r| r.item_id == item.id) {
-                recommendations.push(RecommendItem {
-                    item_id: item.id,
-                    name: item.name.clone(),
-                    category: item.category.clone(),
-                    image_url: item.image_url.clone(),
-                    price: item.price,
-                    sim_score: 0.0,
-                    popularity: item.popularity,
-                    final_score: item.popularity * 0.3,
-                });
-            }
-        }
+/// 预热期间的兜底推荐: 不过滤已看、不记录请求日志
+fn warming_recommendations(state: &AppState, uid: u64, view: ItemView) -> Result<Json<RecommendResponse>, ApiError> {
+    let users = state.warmup.users();
+    if users.is_empty() {
+        return Err(ApiError::WarmingUp);
     }
-    
-    recommendations.truncate(10);
+    let user = users.into_iter()
+        .find(|u| u.id == uid)
+        .ok_or(ApiError::UserNotFound(uid))?;
+
+    let recommendations = state.warmup.fallback().into_iter()
+        .map(|item| RecommendItem {
+            item_id: item.id,
+            name: item.title(&view.lang).to_string(),
+            category: item.category,
+            image_url: item.image_url,
+            price: item.price,
+            sim_score: 0.0,
+            popularity: item.popularity,
+            final_score: item.popularity,
+            fusion: None,
+        })
+        .collect();
 
     Ok(Json(RecommendResponse {
-        user: UserInfo { id: user.id, name: user.name.clone() },
-        recommendations,
-        filtered_count,
+        user: UserInfo { id: user.id, name: user.name },
+        recommendations: view.select(recommendations),
+        lang: view.lang,
+        filtered_count: 0,
+        experiment: None,
+        debug: None,
+        warming: true,
+        cached: false,
+        reranked: false,
+        degraded: false,
+        next_page_token: None,
     }))
 }
 
 async fn mark_seen_handler(
     State(state): State<Arc<AppState>>,
     Json(payload): Json<MarkSeenRequest>,
-) -> Result<Json<MarkSeenResponse>, (StatusCode, Json<ErrorResponse>)> {
-    // 加载用户的 Filter
-    let mut filter = state.storage.get_user_filter(payload.uid)
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse {
-            error: format!("Failed to get filter: {}", e),
-        })))?;
-    
-    // 插入所有 item_id
-    for item_id in &payload.item_ids {
-        filter.add(&item_id.to_le_bytes());
+) -> Result<Json<MarkSeenResponse>, ApiError> {
+    if payload.item_ids.len() > MAX_MARK_SEEN_ITEMS {
+        return Err(ApiError::InvalidInput(format!("item_ids may hold at most {} entries, got {}", MAX_MARK_SEEN_ITEMS, payload.item_ids.len())));
     }
-    
-    // 保存回 Sled
-    state.storage.save_user_filter(payload.uid, &filter)
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse {
-            error: format!("Failed to save filter: {}", e),
-        })))?;
-    
-    Ok(Json(MarkSeenResponse { marked: payload.item_ids.len() }))
+    let recsys = state.recsys()?;
+    let catalog = recsys.catalog();
+    if catalog.user(payload.uid).is_none() {
+        return Err(ApiError::UserNotFound(payload.uid));
+    }
+
+    // Bloom Filter 无法删除元素，写入前去重并剔除目录中不存在的 ID
+    let mut submitted = HashSet::new();
+    let (known, unknown): (Vec<u64>, Vec<u64>) = payload.item_ids.into_iter()
+        .filter(|id| submitted.insert(*id))
+        .partition(|id| catalog.item(*id).is_some());
+    if !unknown.is_empty() {
+        warn!(uid = payload.uid, unknown = unknown.len(), "⚠️  Ignoring unknown item ids in /mark_seen");
+    }
+    if known.is_empty() {
+        return Ok(Json(MarkSeenResponse { marked: 0, unknown }));
+    }
+
+    // 写入用户的 Bloom Filter (读取、反序列化与写回都在阻塞线程中)
+    let (uid, ids) = (payload.uid, known.clone());
+    run_blocking(&state, ApiError::storage("Failed to update filter"), move |recsys| recsys.mark_seen(uid, &ids)).await?;
+    if let Some(cache) = &state.cache {
+        cache.invalidate(payload.uid).await;
+    }
+
+    // 记录交互日志 (离线评估使用)
+    for item_id in &known {
+        let event = InteractionEvent::new(payload.uid, *item_id, EventKind::View);
+        recsys.log_interaction(&event)
+            .map_err(ApiError::storage("Failed to log event"))?;
+    }
+
+    Ok(Json(MarkSeenResponse { marked: known.len(), unknown }))
+}
+
+async fn feedback_handler(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<FeedbackRequest>,
+) -> Result<Json<FeedbackResponse>, ApiError> {
+    let event = InteractionEvent::new(payload.uid, payload.item_id, payload.kind);
+    let recsys = state.recsys()?;
+    recsys.log_interaction(&event)
+        .map_err(ApiError::storage("Failed to log event"))?;
+    recsys.notify(&WebhookEvent::Feedback { uid: event.uid, item_id: event.item_id, kind: event.kind });
+    Ok(Json(FeedbackResponse { recorded: true }))
+}
+
+async fn experiment_report_handler(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<Json<experiment::ExperimentReport>, ApiError> {
+    let exp = experiment::find(&id)
+        .ok_or_else(|| ApiError::ExperimentNotFound(id.clone()))?;
+
+    let requests: Vec<RequestRecord> = state.storage()?.iter_requests_since(0).collect::<Result<_>>()
+        .map_err(ApiError::storage("Failed to read request log"))?;
+    let events: Vec<InteractionEvent> = state.storage()?.iter_events().collect::<Result<_>>()
+        .map_err(ApiError::storage("Failed to read event log"))?;
+
+    Ok(Json(experiment::build_report(exp, &requests, &events, state.recsys()?.catalog().items().len())))
+}
+
+async fn export_training_handler(
+    State(state): State<Arc<AppState>>,
+) -> Result<([(header::HeaderName, &'static str); 1], Vec<u8>), ApiError> {
+    let examples = state.recsys()?.training_examples().map_err(ApiError::storage("Failed to read logs"))?;
+    let mut body = Vec::new();
+    export::write_jsonl(&examples, &mut body).map_err(ApiError::internal("Failed to export training data"))?;
+    Ok(([(header::CONTENT_TYPE, "application/x-ndjson")], body))
+}
+
+/// 按 `[csv]` 映射校验上传的 CSV 目录 (不写入)，返回逐行错误
+async fn validate_csv_handler(
+    State(state): State<Arc<AppState>>,
+    body: String,
+) -> Result<Json<CsvValidation>, ApiError> {
+    let recsys = state.recsys()?;
+    let catalog = csv_import::parse_catalog(body.as_bytes(), &state.config().csv)
+        .map_err(|e| ApiError::InvalidInput(format!("{:#}", e)))?;
+    let existing = recsys.catalog();
+    let updated = catalog.items.iter().filter(|item| existing.item(item.id).is_some()).count();
+    Ok(Json(CsvValidation {
+        valid: catalog.items.len(),
+        new: catalog.items.len() - updated,
+        updated,
+        errors: catalog.errors,
+    }))
+}
+
+/// 按 `[csv]` 映射导入上传的 CSV 目录 (同 `import --format csv`)，跳过有错误的行
+///
+/// 导入后重建索引；重建期间的请求召回降级为暴力检索。
+async fn import_csv_handler(
+    State(state): State<Arc<AppState>>,
+    body: String,
+) -> Result<Json<CsvImportResponse>, ApiError> {
+    let catalog = csv_import::parse_catalog(body.as_bytes(), &state.config().csv)
+        .map_err(|e| ApiError::InvalidInput(format!("{:#}", e)))?;
+    let errors = catalog.errors;
+    let items = catalog.items;
+    let imported = run_blocking(&state, ApiError::internal("CSV import failed"), move |recsys| recsys.import_items(items)).await?;
+    info!(imported, skipped = errors.len(), "✅ CSV catalog imported");
+    Ok(Json(CsvImportResponse { imported, errors }))
+}
+
+/// 写入 (或覆盖) 单个物品，之后的推荐与搜索即可返回它
+async fn ingest_item_handler(
+    State(state): State<Arc<AppState>>,
+    Json(item): Json<ItemJson>,
+) -> Result<Json<IngestResponse>, ApiError> {
+    if item.name.trim().is_empty() || item.category.trim().is_empty() {
+        return Err(ApiError::InvalidInput("title and category must not be empty".into()));
+    }
+    if !(item.price.is_finite() && item.price >= 0.0) {
+        return Err(ApiError::InvalidInput(format!("invalid price {}", item.price)));
+    }
+    let item_id = item.id;
+    run_blocking(&state, ApiError::internal("Failed to ingest item"), move |recsys| recsys.ingest(item)).await?;
+    Ok(Json(IngestResponse { item_id }))
+}
+
+/// 在阻塞线程池中执行同步的 RecSys 调用，错误 (含 panic) 经 `wrap` 转为 ApiError
+///
+/// ANN 搜索、Bloom Filter 反序列化、编码与索引重建都是同步的 CPU 密集操作，
+/// 直接在处理器中执行会占住 tokio 工作线程，并发时同一线程上的其他请求只能排队。
+async fn run_blocking<T: Send + 'static>(
+    state: &Arc<AppState>,
+    wrap: impl FnOnce(anyhow::Error) -> ApiError,
+    call: impl FnOnce(&RecSys) -> Result<T> + Send + 'static,
+) -> Result<T, ApiError> {
+    state.recsys()?;
+    let state = Arc::clone(state);
+    match tokio::task::spawn_blocking(move || call(state.recsys().expect("published before spawning"))).await {
+        Ok(result) => result.map_err(wrap),
+        Err(e) => Err(wrap(e.into())),
+    }
+}
+
+#[tracing::instrument(skip_all)]
+async fn embed_handler(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<EmbedRequest>,
+) -> Result<Json<EmbedResponse>, ApiError> {
+    let texts = match payload.texts {
+        EmbedInput::One(text) => vec![text],
+        EmbedInput::Many(texts) => texts,
+    };
+    if texts.is_empty() || texts.len() > MAX_EMBED_TEXTS {
+        return Err(ApiError::InvalidInput(format!("texts must hold between 1 and {} entries, got {}", MAX_EMBED_TEXTS, texts.len())));
+    }
+    let embeddings = run_blocking(&state, ApiError::internal("Embedding failed"), move |recsys| recsys.embed(&texts))
+        .await?
+        .ok_or(ApiError::ModelUnavailable)?;
+    Ok(Json(EmbedResponse { dim: state.config().embedding.dim, embeddings }))
+}
+
+async fn request_log_handler(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<RequestLogQuery>,
+) -> Result<Json<Vec<RequestRecord>>, ApiError> {
+    let records = state.storage()?.iter_requests_since(params.since_ms)
+        .take(params.limit)
+        .collect::<Result<Vec<_>>>()
+        .map_err(ApiError::storage("Failed to read request log"))?;
+    Ok(Json(records))
+}
+
+async fn metrics_handler() -> Json<metrics::PipelineSnapshot> {
+    Json(metrics::PIPELINE.snapshot())
 }
 
 async fn users_handler(State(state): State<Arc<AppState>>) -> Json<UsersResponse> {
-    let users = state.users.iter()
-        .map(|u| UserInfo { id: u.id, name: u.name.clone() })
-        .collect();
+    let to_info = |u: &User| UserInfo { id: u.id, name: u.name.clone() };
+    let users = match state.recsys.get() {
+        Some(recsys) => recsys.catalog().users().iter().map(to_info).collect(),
+        None => state.warmup.users().iter().map(to_info).collect(),
+    };
     Json(UsersResponse { users })
 }
 
+async fn similar_users_handler(
+    State(state): State<Arc<AppState>>,
+    Path(uid): Path<u64>,
+    Query(params): Query<SimilarUsersQuery>,
+) -> Result<Json<SimilarUsersResponse>, ApiError> {
+    let k = params.k.unwrap_or(DEFAULT_SIMILAR_USERS);
+    if !(1..=MAX_SIMILAR_USERS).contains(&k) {
+        return Err(ApiError::InvalidInput(format!("k must be between 1 and {}", MAX_SIMILAR_USERS)));
+    }
+    let user = state.recsys()?.catalog().user(uid).map(|u| UserInfo { id: u.id, name: u.name.clone() })
+        .ok_or(ApiError::UserNotFound(uid))?;
+    let similar = run_blocking(&state, ApiError::internal("Similar user search failed"), move |recsys| {
+        Ok(recsys.similar_users(uid, k).unwrap_or_default())
+    }).await?;
+    let similar = similar.into_iter()
+        .map(|(u, similarity)| SimilarUser { id: u.id, name: u.name, similarity })
+        .collect();
+    Ok(Json(SimilarUsersResponse { user, similar }))
+}
+
+async fn item_handler(
+    State(state): State<Arc<AppState>>,
+    Path(item_id): Path<u64>,
+    Query(params): Query<ItemQuery>,
+    headers: HeaderMap,
+) -> Result<Json<ItemResponse>, ApiError> {
+    let view = ItemView::with_fields(&state.config(), params.lang.as_deref(), params.fields.as_deref(), ITEM_DETAIL_FIELDS, &headers)?;
+    let catalog = state.recsys()?.catalog();
+    let item = catalog.item(item_id).ok_or(ApiError::ItemNotFound(item_id))?;
+    let detail = ItemDetail {
+        item_id,
+        name: item.title(&view.lang).to_string(),
+        category: item.category.clone(),
+        image_url: item.image_url.clone(),
+        price: item.price,
+        popularity: item.popularity,
+    };
+    let item = fields::project(&detail, view.fields.as_ref())
+        .map_err(|e| ApiError::internal("Failed to serialize item")(e.into()))?;
+    Ok(Json(ItemResponse { lang: view.lang, item }))
+}
+
+async fn status_handler(State(state): State<Arc<AppState>>) -> Json<WarmupStatus> {
+    Json(state.warmup.status())
+}
+
+#[derive(Serialize)]
+struct StatsResponse {
+    users: usize,
+    items: usize,
+    /// HNSW 索引中的向量数
+    indexed: usize,
+    events: usize,
+    requests: usize,
+    jobs: Vec<JobStatus>,
+}
+
+async fn stats_handler(State(state): State<Arc<AppState>>) -> Result<Json<StatsResponse>, ApiError> {
+    let recsys = state.recsys()?;
+    let storage = recsys.storage();
+    let catalog = recsys.catalog();
+    Ok(Json(StatsResponse {
+        users: catalog.users().len(),
+        items: catalog.items().len(),
+        indexed: recsys.hnsw_index().len(),
+        events: storage.events_count(),
+        requests: storage.requests_count(),
+        jobs: state.scheduler.status(),
+    }))
+}
+
+#[tracing::instrument(skip_all, fields(q = %params.q))]
 async fn search_handler(
     State(state): State<Arc<AppState>>,
     Query(params): Query<SearchQuery>,
-) -> Result<Json<SearchResponse>, (StatusCode, Json<ErrorResponse>)> {
-    let model = state.embedding_model.as_ref()
-        .ok_or_else(|| (StatusCode::SERVICE_UNAVAILABLE, Json(ErrorResponse {
-            error: "Embedding model not loaded".to_string(),
-        })))?;
-
-    // 1. Semantic Search (Vector)
-    let query_vec = model.encode(&params.q)
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse {
-            error: format!("Encoding failed: {}", e),
-        })))?;
-    
-    let vec_candidates = hnsw_search(&query_vec, 50); // Top 50 vector results
-    let vec_results: Vec<(u32, f32)> = vec_candidates.into_iter()
-        .map(|(id, score)| (id as u32, score))
-        .collect();
+    headers: HeaderMap,
+) -> Result<Json<SearchResponse>, ApiError> {
+    let start = Instant::now();
+    let view = ItemView::new(&state.config(), params.lang.as_deref(), params.fields.as_deref(), &headers)?;
+    let recsys = state.recsys()?;
+    let strategy = params.fusion.unwrap_or(recsys.config().search.fusion);
+    let (query, queued) = (params.q.clone(), Instant::now());
+    let service::SearchOutput { results: merged_results, mut timings, counts } = run_blocking(&state, ApiError::internal("Search failed"), move |recsys| {
+        let queue_ms = service::elapsed_ms(queued);
+        Ok(recsys.search(&query, SearchOptions { fusion: strategy })?.map(|mut output| {
+            output.timings.queue_ms = queue_ms;
+            output
+        }))
+    })
+        .await?
+        .ok_or(ApiError::ModelUnavailable)?;
+    metrics::PIPELINE.record_search(&counts);
 
-    // 2. Keyword Search (Tantivy)
-    let kw_results = state.text_search.search(&params.q, 50)
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse {
-            error: format!("Text search failed: {}", e),
-        })))?;
+    let rerank_start = Instant::now();
+    let context = RerankContext::Query { q: params.q.clone() };
+    let (merged_results, reranked) = state.rerank(recsys, |c| c.search, context, merged_results, |r| (r.id, r.score)).await;
+    if reranked {
+        timings.rerank_ms = service::elapsed_ms(rerank_start);
+    }
 
-    // 3. RRF Merge
-    let merged_results = hybrid::rrf_merge(vec_results, kw_results);
+    state.log_request(
+        LoggedRequest::Search { q: params.q.clone(), fusion: strategy },
+        merged_results.iter().map(|r| (r.id, r.score)).collect(),
+        None,
+        service::elapsed_ms(start),
+    );
 
-    // 4. Transform to Response
+    let catalog = recsys.catalog();
     let results: Vec<RecommendItem> = merged_results.into_iter()
-        .take(20)
         .filter_map(|res| {
-            let idx = *state.item_map.get(&(res.id as u64))?;
-            let item = &state.items[idx];
+            let item = catalog.item(res.id)?;
             Some(RecommendItem {
-                item_id: res.id as u64,
-                name: item.name.clone(),
+                item_id: res.id,
+                name: item.title(&view.lang).to_string(),
                 category: item.category.clone(),
                 image_url: item.image_url.clone(),
                 price: item.price,
-                sim_score: res.score, // RRF Score
+                sim_score: res.score, // Fused Score
                 popularity: item.popularity,
                 final_score: res.score,
+                fusion: params.debug.then_some(res.contributions),
             })
         })
         .collect();
 
-    Ok(Json(SearchResponse { query: params.q, results }))
+    let degraded = counts.degraded;
+    timings.total_ms = service::elapsed_ms(start);
+    state.slow_log.record(SlowRequest::Search { q: params.q.clone(), fusion: strategy }, &timings, &counts);
+    Ok(Json(SearchResponse {
+        query: params.q,
+        results: view.select(results),
+        lang: view.lang,
+        fusion_strategy: params.debug.then_some(strategy),
+        debug: params.debug.then_some(DebugInfo { timing: timings, counts }),
+        reranked,
+        degraded,
+    }))
 }
 
 async fn health_handler() -> &'static str { "OK" }
 
 // ============================================================================
-// 数据初始化
+// 后台任务
 // ============================================================================
 
-fn init_users() -> Vec<User> {
-    vec![
-        // 明确单一兴趣的用户
-        User { id: 1, name: "程序员小明 (Electronics + Books)".into(), embedding: generate_user_embedding(&["Electronics", "Books"]) },
-        User { id: 2, name: "居家达人小红 (Home)".into(), embedding: generate_user_embedding(&["Home"]) },
-        User { id: 3, name: "时尚达人小美 (Clothing)".into(), embedding: generate_user_embedding(&["Clothing"]) },
-        
-        // 双兴趣用户
-        User { id: 4, name: "极客玩家 (Electronics)".into(), embedding: generate_user_embedding(&["Electronics"]) },
-        User { id: 5, name: "书虫 (Books)".into(), embedding: generate_user_embedding(&["Books"]) },
-        User { id: 6, name: "生活家 (Home + Clothing)".into(), embedding: generate_user_embedding(&["Home", "Clothing"]) },
-        
-        // 混合兴趣用户
-        User { id: 7, name: "全能选手 (All Categories)".into(), embedding: generate_user_embedding(&["Electronics", "Books", "Home", "Clothing"]) },
-        User { id: 8, name: "科技宅 (Electronics + Home)".into(), embedding: generate_user_embedding(&["Electronics", "Home"]) },
-        
-        // 噪声用户 - 使用随机embedding
-        User { id: 9, name: "新用户A (Random)".into(), embedding: generate_random_embedding() },
-        User { id: 10, name: "新用户B (Random)".into(), embedding: generate_random_embedding() },
-    ]
-}
-
-fn load_items_from_json(embedding_model: &embedding::EmbeddingModel) -> Result<Vec<Item>> {
-    use rand::Rng;
-    let json_str = std::fs::read_to_string("assets/products.json")?;
-    let items_json: Vec<ItemJson> = serde_json::from_str(&json_str)?;
-    let mut rng = rand::thread_rng();
-    let total = items_json.len();
-    println!("🧠 Encoding {} items with ONNX model...", total);
-    
-    let items: Vec<Item> = items_json.into_iter()
-        .enumerate()
-        .map(|(i, json)| {
-            // 使用 ONNX 模型生成真实语义向量
-            let embedding = embedding_model.encode(&json.name)
-                .unwrap_or_else(|_| generate_category_embedding(&json.category));
-            let popularity = rng.gen::<f32>();
-            if (i + 1) % 50 == 0 {
-                println!("   Encoded {}/{} items", i + 1, total);
-            }
-            Item::from_json(json, embedding, popularity)
-        })
-        .collect();
-    
-    println!("✅ All {} items encoded with semantic vectors", total);
-    Ok(items)
-}
-
-fn load_items_from_json_fallback() -> Result<Vec<Item>> {
-    use rand::Rng;
-    let json_str = std::fs::read_to_string("assets/products.json")?;
-    let items_json: Vec<ItemJson> = serde_json::from_str(&json_str)?;
-    let mut rng = rand::thread_rng();
-    Ok(items_json.into_iter()
-        .map(|json| {
-            let embedding = generate_category_embedding(&json.category);
-            let popularity = rng.gen::<f32>();
-            Item::from_json(json, embedding, popularity)
-        })
-        .collect())
-}
-
-fn init_data_with_storage(
-    storage: Arc<Storage>,
-    embedding_model: Option<Arc<embedding::EmbeddingModel>>,
-    text_search: Arc<TextSearch>
-) -> Result<Arc<AppState>> {
-    let items = if storage.items_count() == 0 {
-        println!("📂 Database empty, loading from products.json...");
-        let items = match &embedding_model {
-            Some(model) => load_items_from_json(model)?,
-            None => {
-                println!("⚠️  No embedding model, using category-based vectors");
-                load_items_from_json_fallback()?
-            }
-        };
-        for item in &items { storage.save_item(item)?; }
-        println!("💾 Saved {} items to database", items.len());
-        
-        // Hydrate Tantivy
-        println!("🔍 Building text search index...");
-        for item in &items {
-            text_search.index_item(item)?;
+/// 预热完成后登记周期任务 (周期见配置 `[jobs]`)
+fn spawn_jobs(state: &Arc<AppState>) {
+    let jobs = state.config().jobs.clone();
+
+    let job_state = Arc::clone(state);
+    state.scheduler.spawn("index_save", jobs.index_save, move || {
+        job_state.recsys()?.persist()
+    });
+
+    let job_state = Arc::clone(state);
+    state.scheduler.spawn("request_log_prune", jobs.request_log_prune, move || {
+        let recsys = job_state.recsys()?;
+        let retention_ms = recsys.config().request_log.retention_hours * 60 * 60 * 1000;
+        let cutoff = model::now_ms().saturating_sub(retention_ms);
+        let removed = recsys.storage().prune_requests_before(cutoff)?;
+        if removed > 0 {
+            info!(removed, remaining = recsys.storage().requests_count(), "🧹 Rotated request log");
         }
-        text_search.commit()?;
-        println!("✅ Text index built");
+        Ok(())
+    });
 
-        items
-    } else {
-        println!("📂 Loading items from database...");
-        let items: Vec<Item> = storage.iter_items().filter_map(|r| r.ok()).collect();
-        println!("📦 Loaded {} items from database", items.len());
-        items
-    };
+    let job_state = Arc::clone(state);
+    state.scheduler.spawn("popularity_refresh", jobs.popularity_refresh, move || {
+        refresh_popularity(job_state.recsys()?)
+    });
 
-    let users = if storage.users_count() == 0 {
-        let users = init_users();
-        for user in &users { storage.save_user(user)?; }
-        println!("💾 Saved {} users to database", users.len());
-        users
-    } else {
-        storage.get_all_users()?
-    };
+    // impressions.penalty 可热加载，所以总是注册；没有曝光时只检查一次空表
+    let job_state = Arc::clone(state);
+    state.scheduler.spawn("impressions_prune", jobs.impressions_prune, move || {
+        let recsys = job_state.recsys()?;
+        let removed = recsys.prune_impressions();
+        if removed > 0 {
+            info!(removed, remaining = recsys.impression_users(), "🧹 Expired recent impressions");
+        }
+        Ok(())
+    });
+
+    if state.config().bloom.ttl_days > 0 {
+        let job_state = Arc::clone(state);
+        state.scheduler.spawn("history_prune", jobs.history_prune, move || {
+            let recsys = job_state.recsys()?;
+            let ttl_ms = recsys.config().bloom.ttl_days.saturating_mul(24 * 60 * 60 * 1000);
+            let cutoff = model::now_ms().saturating_sub(ttl_ms);
+            let removed = recsys.storage().prune_user_filters_before(cutoff)?;
+            if removed > 0 {
+                info!(removed, remaining = recsys.storage().user_filters_count(), "🧹 Expired seen-item filters");
+            }
+            Ok(())
+        });
+    }
 
-    let item_map: HashMap<u64, usize> = items.iter().enumerate().map(|(i, item)| (item.id, i)).collect();
+    if state.remote.is_some() {
+        let job_state = Arc::clone(state);
+        state.scheduler.spawn("snapshot_upload", jobs.snapshot_upload, move || {
+            let recsys = job_state.recsys()?;
+            let remote = job_state.remote.as_ref().expect("registered only with [remote]");
+            recsys.persist()?;
+            let config = recsys.config();
+            let upload = remote.upload(Arc::clone(recsys.storage()), &config.paths);
+            tokio::runtime::Handle::current().block_on(upload)
+        });
+    }
+}
 
-    Ok(Arc::new(AppState { storage, users, items, item_map, embedding_model, text_search }))
+/// 重算热门度并打印摘要 (`popularity_refresh` 任务与 `popularity` 命令共用)
+fn refresh_popularity(recsys: &RecSys) -> Result<()> {
+    let refresh = recsys.refresh_popularity()?;
+    let ctr = refresh.clicks as f64 / refresh.impressions.max(1) as f64;
+    info!(
+        items = refresh.items, impressed = refresh.impressed,
+        impressions = refresh.impressions, clicks = refresh.clicks, ctr = format!("{:.4}", ctr),
+        "🔥 Item popularity refreshed"
+    );
+    Ok(())
 }
 
 // ============================================================================
-// 索引初始化 (Hydration)
+// 交互事件消费
 // ============================================================================
 
-fn init_hnsw_with_hydration(items: &[Item]) -> Result<()> {
-    let max_elements = items.len() + 1000;
-    
-    println!("🔧 Loading HNSW index from {}...", INDEX_PATH);
-    let loaded = load_hnsw_index(INDEX_PATH, DIM, max_elements, 100)
-        .map_err(|e| anyhow::anyhow!(e))?;
-    
-    let index_count = get_hnsw_count();
-    let db_count = items.len();
-    
-    if loaded && index_count == db_count {
-        println!("✅ HNSW index loaded: {} items (consistent with DB)", index_count);
-        return Ok(());
+/// 配置了 `[kafka]` 时在后台消费交互事件，写入已看过滤器与交互日志 (与 /mark_seen、/feedback 相同)
+#[cfg(feature = "kafka")]
+fn spawn_event_consumer(state: &Arc<AppState>) {
+    let Some(kafka) = state.config().kafka.clone() else { return };
+    let consumer_state = Arc::clone(state);
+    tokio::spawn(async move {
+        let record = |event: InteractionEvent| -> Result<()> { consumer_state.recsys()?.record_event(&event) };
+        if let Err(e) = mini_recsys::kafka::run(&kafka, record).await {
+            error!(error = %format!("{:#}", e), "❌ Event consumer stopped");
+        }
+    });
+}
+
+/// `Config::validate` 已拒绝未启用 `kafka` feature 时的 `[kafka]` 配置
+#[cfg(not(feature = "kafka"))]
+fn spawn_event_consumer(_state: &Arc<AppState>) {}
+
+// ============================================================================
+// 监听
+// ============================================================================
+
+/// 按配置同时运行全部监听: HTTP (`server.bind`)、指标 (`server.metrics_bind`) 与 gRPC (`server.grpc_bind`)
+///
+/// 所有监听共用同一个 AppState；任意一个出错时整体返回该错误，其余监听随之停止。
+/// 保存索引由调用方负责: 监听出错与 Ctrl+C 时都会先执行 `graceful_shutdown` 再退出。
+async fn serve(state: Arc<AppState>, app: Router, server: &ServerConfig) -> Result<()> {
+    let metrics = async {
+        let Some(bind) = &server.metrics_bind else { return std::future::pending().await };
+        info!("📈 Metrics listening on http://{}", bind);
+        serve_tcp(metrics_router(Arc::clone(&state)), bind).await
+    };
+    let grpc = async {
+        let Some(bind) = &server.grpc_bind else { return std::future::pending().await };
+        info!("📡 gRPC listening on {}", bind);
+        serve_grpc(Arc::clone(&state), bind).await
+    };
+    tokio::try_join!(serve_http(app, server), metrics, grpc)?;
+    Ok(())
+}
+
+/// 指标监听只暴露只读的运维接口
+fn metrics_router(state: Arc<AppState>) -> Router {
+    Router::new()
+        .route("/health", get(health_handler))
+        .route("/status", get(status_handler))
+        .route("/stats", get(stats_handler))
+        .route("/admin/metrics", get(metrics_handler))
+        .with_state(state)
+}
+
+/// 按 `server.bind` 监听 TCP 端口或 Unix socket；配置了 TLS 时由 rustls 终止 HTTPS
+async fn serve_http(app: Router, server: &ServerConfig) -> Result<()> {
+    #[cfg(unix)]
+    if let Some(path) = server.unix_socket() {
+        return serve_unix(app, path).await;
     }
-    
-    if !loaded {
-        println!("📝 Index file not found, created new empty index");
-    } else {
-        println!("⚠️  Index count ({}) != DB count ({}), rebuilding...", index_count, db_count);
+
+    match &server.tls {
+        Some(tls) => serve_tls(app, &server.bind, tls).await,
+        None => serve_tcp(app, &server.bind).await,
     }
-    
-    println!("🔄 Hydrating index from database...");
-    let mut success = 0;
-    for item in items {
-        if add_item_to_hnsw(item.id, &item.embedding).is_ok() {
-            success += 1;
-        }
+}
+
+async fn serve_tcp(app: Router, addr: &str) -> Result<()> {
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    axum::serve(listener, app).await?;
+    Ok(())
+}
+
+#[cfg(feature = "grpc")]
+async fn serve_grpc(state: Arc<AppState>, bind: &str) -> Result<()> {
+    mini_recsys::grpc::serve(state, bind).await
+}
+
+/// `Config::validate` 已拒绝未启用 `grpc` feature 时的 gRPC 配置，这里只是兜底
+#[cfg(not(feature = "grpc"))]
+async fn serve_grpc(_state: Arc<AppState>, _bind: &str) -> Result<()> {
+    anyhow::bail!("server.grpc_bind is set but mini-recsys was built without the `grpc` feature")
+}
+
+/// axum 0.7 的 `axum::serve` 只接受 TcpListener，这里用 hyper-util 逐个驱动连接
+#[cfg(unix)]
+async fn serve_unix(app: Router, path: &str) -> Result<()> {
+    use hyper_util::rt::{TokioExecutor, TokioIo};
+    use hyper_util::server::conn::auto::Builder;
+    use hyper_util::service::TowerToHyperService;
+    use std::os::unix::fs::FileTypeExt;
+
+    // 清理上次未正常退出时残留的 socket 文件 (只删除 socket，避免误删普通文件)
+    if std::fs::symlink_metadata(path).is_ok_and(|m| m.file_type().is_socket()) {
+        std::fs::remove_file(path)?;
     }
-    println!("✅ HNSW index rebuilt with {} items", success);
-    
+    let listener = tokio::net::UnixListener::bind(path)?;
+
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let service = TowerToHyperService::new(app.clone());
+        tokio::spawn(async move {
+            let result = Builder::new(TokioExecutor::new())
+                .serve_connection_with_upgrades(TokioIo::new(stream), service)
+                .await;
+            if let Err(e) = result {
+                tracing::debug!(error = %e, "Unix socket connection closed with error");
+            }
+        });
+    }
+}
+
+#[cfg(feature = "tls")]
+async fn serve_tls(app: Router, addr: &str, tls: &TlsConfig) -> Result<()> {
+    use anyhow::Context;
+    let rustls = axum_server::tls_rustls::RustlsConfig::from_pem_file(&tls.cert, &tls.key).await
+        .with_context(|| format!("Failed to load TLS cert {} / key {}", tls.cert, tls.key))?;
+    info!(cert = %tls.cert, "🔒 TLS enabled");
+
+    let listener = std::net::TcpListener::bind(addr)?;
+    listener.set_nonblocking(true)?;
+    axum_server::from_tcp_rustls(listener, rustls)
+        .serve(app.into_make_service())
+        .await?;
+    Ok(())
+}
+
+/// `Config::validate` 已拒绝未启用 `tls` feature 时的 TLS 配置，这里只是兜底
+#[cfg(not(feature = "tls"))]
+async fn serve_tls(_app: Router, _addr: &str, _tls: &TlsConfig) -> Result<()> {
+    anyhow::bail!("server.tls is set but mini-recsys was built without the `tls` feature")
+}
+
+// ============================================================================
+// 预热
+// ============================================================================
+
+/// 在阻塞线程中加载数据与索引，完成后发布 RecSys 并启动请求日志轮转
+///
+/// 预热失败时返回的 Receiver 收到错误，服务随之退出。
+fn spawn_warmup(state: Arc<AppState>, config: Config) -> tokio::sync::oneshot::Receiver<anyhow::Error> {
+    let (failed_tx, failed_rx) = tokio::sync::oneshot::channel();
+    tokio::task::spawn_blocking(move || {
+        let hydrated = match &state.remote {
+            Some(remote) => tokio::runtime::Handle::current().block_on(remote.hydrate(&config.paths)).map(drop),
+            None => Ok(()),
+        };
+        match hydrated.and_then(|()| RecSys::open_with_progress(config, &state.warmup)) {
+            Ok(recsys) => {
+                if state.recsys.set(recsys).is_err() {
+                    unreachable!("warmup runs once");
+                }
+                state.warmup.finish();
+                info!(elapsed_ms = state.warmup.status().elapsed_ms, "✅ Warmup finished, serving full recommendations");
+                spawn_jobs(&state);
+                spawn_event_consumer(&state);
+            }
+            Err(e) => {
+                error!(error = %format!("{:#}", e), "❌ Warmup failed");
+                state.warmup.fail(&e);
+                let _ = failed_tx.send(e);
+            }
+        }
+    });
+    failed_rx
+}
+
+// ============================================================================
+// 配置热加载
+// ============================================================================
+
+/// 收到 SIGHUP 时重新读取配置文件与环境变量 (保留启动时的命令行路径覆盖) 并应用可热加载的配置项；
+/// 加载失败时保留当前配置
+#[cfg(unix)]
+fn spawn_config_reload(state: Arc<AppState>, overrides: PathOverrides) -> Result<()> {
+    use tokio::signal::unix::{signal, SignalKind};
+    let mut hangup = signal(SignalKind::hangup())?;
+    tokio::spawn(async move {
+        while hangup.recv().await.is_some() {
+            info!("🔄 SIGHUP received, reloading config");
+            let Some(recsys) = state.recsys.get() else {
+                warn!("⚠️  Still warming up, config reload ignored");
+                continue;
+            };
+            match Config::load_with(&overrides).and_then(|config| recsys.reload(config)) {
+                Ok(changed) if changed.is_empty() => info!("✅ Config reloaded, nothing changed"),
+                Ok(changed) => info!(changed = ?changed, "✅ Config reloaded"),
+                Err(e) => warn!(error = %e, "⚠️  Config reload failed, keeping current config"),
+            }
+        }
+    });
     Ok(())
 }
 
@@ -408,20 +1247,220 @@ fn init_hnsw_with_hydration(items: &[Item]) -> Result<()> {
 // 优雅退出
 // ============================================================================
 
-async fn graceful_shutdown(storage: Arc<Storage>) {
-    println!("\n🛑 Shutting down...");
-    
-    match save_hnsw_index(INDEX_PATH) {
-        Ok(()) => println!("💾 HNSW index saved to {}", INDEX_PATH),
-        Err(e) => eprintln!("❌ Failed to save index: {}", e),
+async fn graceful_shutdown(state: &AppState) {
+    info!("🛑 Shutting down");
+
+    match state.recsys.get() {
+        Some(recsys) => {
+            if let Err(e) = recsys.persist() {
+                error!(error = %e, "❌ Failed to persist index or database");
+            }
+            recsys.flush_webhooks(WEBHOOK_FLUSH_TIMEOUT);
+            recsys.flush_events(EVENT_FLUSH_TIMEOUT);
+        }
+        None => warn!("⚠️  Shutting down before warmup finished, index not saved"),
     }
-    
-    match storage.flush() {
-        Ok(()) => println!("💾 Sled database flushed"),
-        Err(e) => eprintln!("❌ Failed to flush database: {}", e),
+
+    info!("👋 Goodbye!");
+}
+
+/// 退出时等待 Webhook 投递完成的上限
+const WEBHOOK_FLUSH_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// 退出时等待事件记录写入 (Postgres) 的上限
+const EVENT_FLUSH_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// panic 后等待保存完成的上限
+const PANIC_PERSIST_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// 安装 panic hook: 在默认 hook 打印 panic 信息后，尽力保存 HNSW 索引并刷写数据库
+///
+/// handler 中的 panic 由 tokio 捕获，服务继续运行；保存是为了在进程随后崩溃或被杀时
+/// 不丢掉运行期间写入的数据。保存在独立线程中进行并限时等待 (panic 的线程可能正持有
+/// 索引锁)，同一时间只进行一次保存。
+fn install_panic_hook(state: Arc<AppState>) {
+    static PERSISTING: AtomicBool = AtomicBool::new(false);
+
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        default_hook(info);
+        if state.recsys.get().is_none() || PERSISTING.swap(true, Ordering::AcqRel) {
+            return;
+        }
+
+        let (done_tx, done_rx) = std::sync::mpsc::channel();
+        let persist_state = Arc::clone(&state);
+        let spawned = std::thread::Builder::new().name("panic-persist".into()).spawn(move || {
+            let result = persist_state.recsys.get().map_or(Ok(()), RecSys::persist);
+            PERSISTING.store(false, Ordering::Release);
+            let _ = done_tx.send(result);
+        });
+
+        let outcome = match spawned {
+            Ok(_) => done_rx.recv_timeout(PANIC_PERSIST_TIMEOUT)
+                .unwrap_or_else(|_| Err(anyhow::anyhow!("no result within {:?}", PANIC_PERSIST_TIMEOUT))),
+            Err(e) => {
+                PERSISTING.store(false, Ordering::Release);
+                Err(e.into())
+            }
+        };
+        match outcome {
+            Ok(()) => error!("💾 Panic: index and database persisted"),
+            Err(e) => error!(error = %format!("{:#}", e), "❌ Panic: failed to persist index or database"),
+        }
+    }));
+}
+
+// ============================================================================
+// 日志
+// ============================================================================
+
+/// 初始化 tracing
+/// - 级别由 `RUST_LOG` 控制 (默认 info，例如 `RUST_LOG=mini_recsys=debug` 可看到各阶段 span 耗时)
+/// - `LOG_FORMAT=json` 输出 JSON 行，便于日志系统检索
+/// - 配置了 OTLP 地址时同一批 span 也导出到 OpenTelemetry 后端 (同样受 `RUST_LOG` 过滤)
+fn init_tracing(config: &TelemetryConfig) -> Result<Option<Telemetry>> {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let fmt = tracing_subscriber::fmt::layer().with_span_events(FmtSpan::CLOSE);
+    let fmt = if std::env::var("LOG_FORMAT").as_deref() == Ok("json") { fmt.json().boxed() } else { fmt.boxed() };
+    let telemetry = Telemetry::start(config)?;
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(fmt)
+        .with(telemetry.as_ref().map(|telemetry| telemetry.layer()))
+        .init();
+    if telemetry.is_some() {
+        info!(endpoint = %config.endpoint().unwrap_or_default(), "🔭 Exporting traces and metrics over OTLP");
     }
-    
-    println!("👋 Goodbye!");
+    Ok(telemetry)
+}
+
+// ============================================================================
+// 命令行模式
+// ============================================================================
+
+/// Mini-RecSys - 混合 Rust/C++ 推荐系统
+///
+/// 所有子命令共用同一套存储 / 索引 / 模型初始化流程，未指定子命令时等同于 `serve`。
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+    #[command(flatten)]
+    paths: PathOverrides,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// 启动 Web Server
+    Serve,
+    /// 从 JSON (格式同 assets/products.json) 或 CSV 文件导入物品，已存在的 ID 会被覆盖，随后重建索引
+    Import {
+        file: String,
+        /// 默认按扩展名判断 (.csv 为 CSV，其余为 JSON)；CSV 列名映射见配置中的 [csv]
+        #[arg(long, value_enum)]
+        format: Option<CatalogFormat>,
+    },
+    /// 用外部计算的向量替换已有物品的向量 (.parquet、.npy 或 FAISS 平坦索引)，随后重建索引
+    ImportEmbeddings {
+        /// Parquet (`id` + `embedding` 列)、.npy 矩阵 (items, dim) 或 .faiss / .index 文件
+        file: String,
+        /// .npy 矩阵每一行对应的物品 ID (一维整数 .npy)
+        #[arg(long)]
+        ids: Option<String>,
+    },
+    /// 从数据库重建 HNSW 与全文索引
+    Reindex,
+    /// 按请求日志中的曝光与交互日志中的点击重算物品热门度
+    Popularity,
+    /// 基于交互日志的离线评估
+    Eval {
+        #[arg(long, default_value_t = eval::EvalOptions::default().k)]
+        k: usize,
+        #[arg(long, default_value_t = eval::EvalOptions::default().test_ratio)]
+        test_ratio: f32,
+    },
+    /// 生成合成用户行为
+    Simulate {
+        #[arg(long, default_value_t = simulate::SimOptions::default().users)]
+        users: usize,
+        #[arg(long, default_value_t = simulate::SimOptions::default().sessions)]
+        sessions: usize,
+        /// 偏好类别物品的点击概率
+        #[arg(long, default_value_t = simulate::SimOptions::default().click_prob_preferred)]
+        click_prob: f32,
+        /// 随机种子 (默认使用配置中的 demo.seed)
+        #[arg(long)]
+        seed: Option<u64>,
+    },
+    /// 用当前构建重放请求日志并对比结果
+    Replay {
+        /// 只重放最近 N 小时的请求
+        #[arg(long)]
+        hours: Option<u64>,
+        #[arg(long)]
+        limit: Option<usize>,
+        /// 覆盖 /search 的融合策略: rrf / minmax / zscore
+        #[arg(long)]
+        fusion: Option<FusionStrategy>,
+        /// 覆盖 /recommend 的相似度权重 (热门度权重 = 1 - sim)
+        #[arg(long)]
+        sim_weight: Option<f32>,
+        /// 打印变化最大的前 N 条请求
+        #[arg(long, default_value_t = replay::ReplayOptions::default().show)]
+        show: usize,
+    },
+    /// 导出排序模型训练样本 (JSONL，或 Parquet: 需要 `parquet` feature)
+    #[command(alias = "export-training")]
+    Export {
+        #[arg(long, value_enum, default_value_t = TrainingFormat::Jsonl)]
+        format: TrainingFormat,
+        /// 默认 data/training.jsonl 或 data/training.parquet
+        #[arg(long)]
+        out: Option<String>,
+    },
+    /// 导出物品元数据与向量 (Parquet，需要 `parquet` feature)
+    ExportCatalog {
+        #[arg(long, default_value = "data/catalog.parquet")]
+        out: String,
+    },
+    /// 导出物品向量: FAISS `IndexIDMap(IndexFlatIP)`，或 .npy 矩阵加同名 `.ids.npy`
+    ExportVectors {
+        #[arg(long, value_enum, default_value_t = VectorFormat::Faiss)]
+        format: VectorFormat,
+        /// 默认 data/items.faiss 或 data/vectors.npy
+        #[arg(long)]
+        out: Option<String>,
+    },
+    /// 保存索引并把数据库快照与索引上传到配置的对象存储 ([remote])
+    PushSnapshot,
+    /// 端到端延迟压测 (推荐 + 搜索)
+    Bench {
+        #[arg(long, default_value_t = bench::BenchOptions::default().iterations)]
+        iterations: usize,
+        /// 同时发起请求的线程数
+        #[arg(long, default_value_t = bench::BenchOptions::default().concurrency)]
+        concurrency: usize,
+    },
+}
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+enum CatalogFormat {
+    Json,
+    Csv,
+}
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+enum TrainingFormat {
+    Jsonl,
+    Parquet,
+}
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+enum VectorFormat {
+    Faiss,
+    Npy,
 }
 
 // ============================================================================
@@ -430,60 +1469,321 @@ async fn graceful_shutdown(storage: Arc<Storage>) {
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    println!("🚀 Initializing Mini-RecSys...\n");
+    let cli = Cli::parse();
+    // 先读配置: OTLP 地址来自配置
+    let config = Config::load_with(&cli.paths)?;
+    let telemetry = init_tracing(&config.telemetry)?;
+    info!("🚀 Initializing Mini-RecSys");
 
-    // 1. 初始化 ONNX 模型
-    let embedding_model = match embedding::EmbeddingModel::new() {
-        Ok(model) => {
-            println!("🧠 Embedding model loaded (dimension: {})\n", model.dimension());
-            Some(Arc::new(model))
+    config.paths.create_dirs()?;
+    let traced = telemetry.is_some();
+    let result = run(cli.command.unwrap_or(Command::Serve), config, cli.paths, traced).await;
+    if let Some(telemetry) = telemetry {
+        telemetry.shutdown().await;
+    }
+    result
+}
+
+async fn run(command: Command, config: Config, overrides: PathOverrides, traced: bool) -> Result<()> {
+    match command {
+        Command::Serve => run_server(config, overrides, traced).await,
+        Command::PushSnapshot => push_snapshot(config).await,
+        command => {
+            // 服务在预热中恢复 (见 spawn_warmup)，离线命令在打开数据库前恢复
+            if let Some(remote) = &config.remote {
+                RemoteStore::new(remote)?.hydrate(&config.paths).await?;
+            }
+            run_command(command, config)
+        }
+    }
+}
+
+/// 保存索引，并把数据库快照与索引上传到 `[remote]`
+async fn push_snapshot(config: Config) -> Result<()> {
+    let remote_config = config.remote.clone()
+        .ok_or_else(|| anyhow::anyhow!("push-snapshot needs a [remote] section in the config"))?;
+    let remote = RemoteStore::new(&remote_config)?;
+    let recsys = RecSys::open(config)?;
+    recsys.persist()?;
+    remote.upload(Arc::clone(recsys.storage()), &recsys.config().paths).await
+}
+
+/// 执行离线子命令 (同步加载全部数据后运行)
+fn run_command(command: Command, config: Config) -> Result<()> {
+    let recsys = RecSys::open(config)?;
+    let storage = Arc::clone(recsys.storage());
+    let catalog = recsys.catalog();
+
+    match command {
+        Command::Serve | Command::PushSnapshot => unreachable!("async commands are handled in main"),
+        Command::Import { file, format } => {
+            let format = format.unwrap_or(if file.ends_with(".csv") { CatalogFormat::Csv } else { CatalogFormat::Json });
+            match format {
+                CatalogFormat::Json => {
+                    recsys.import(&file)?;
+                }
+                CatalogFormat::Csv => {
+                    let input = std::fs::File::open(&file)?;
+                    let catalog = csv_import::parse_catalog(input, &recsys.config().csv)?;
+                    for error in &catalog.errors {
+                        warn!(line = error.line, error = %error.message, "⚠️  Skipping CSV row");
+                    }
+                    let imported = recsys.import_items(catalog.items)?;
+                    info!(imported, skipped = catalog.errors.len(), path = %file, "✅ CSV catalog imported");
+                }
+            }
+            recsys.flush_webhooks(WEBHOOK_FLUSH_TIMEOUT);
         }
-        Err(e) => {
-            eprintln!("⚠️  Failed to load embedding model: {}\n   /search will be unavailable\n", e);
-            None
+        Command::ImportEmbeddings { file, ids } => {
+            let vectors = read_embeddings(&file, ids.as_deref())?;
+            let summary = recsys.import_embeddings(vectors)?;
+            recsys.flush_webhooks(WEBHOOK_FLUSH_TIMEOUT);
+            info!(updated = summary.updated, unknown = summary.unknown, path = %file, "✅ Embeddings imported");
         }
-    };
+        Command::Reindex => {
+            recsys.reindex()?;
+        }
+        Command::Popularity => {
+            refresh_popularity(&recsys)?;
+            recsys.persist()?;
+        }
+        Command::Eval { k, test_ratio } => {
+            let opts = eval::EvalOptions { k, test_ratio };
+            info!(events = storage.events_count(), k, test_ratio, "📐 Evaluating logged events");
+            let reports = recsys.evaluate(opts)?;
+            eval::print_report(&reports, opts.k);
+        }
+        Command::Simulate { users, sessions, click_prob, seed } => {
+            let opts = simulate::SimOptions {
+                users,
+                sessions,
+                click_prob_preferred: click_prob,
+                seed: seed.or(recsys.config().demo.seed),
+                ..simulate::SimOptions::default()
+            };
+            info!(users, sessions, "🎲 Simulating users");
+            let summary = simulate::run(&storage, catalog.users(), catalog.items(), catalog.item_map(), opts)?;
+            storage.flush()?;
+            info!(users = summary.users, sessions = summary.sessions, views = summary.views, clicks = summary.clicks,
+                elapsed_ms = summary.elapsed_ms as u64, "✅ Simulation finished");
+        }
+        Command::Replay { hours, limit, fusion, sim_weight, show } => {
+            let opts = replay::ReplayOptions {
+                since_ms: hours.map_or(0, |h| model::now_ms().saturating_sub(h * 3600 * 1000)),
+                limit,
+                fusion,
+                weights: sim_weight.map(|sim| RankWeights { sim, popularity: 1.0 - sim }),
+                show,
+                recall: recsys.config().recall,
+            };
+            info!("⏪ Replaying logged requests");
+            let summary = replay::run(
+                &storage, catalog.users(), catalog.items(), catalog.item_map(),
+                recsys.embedding_model(), recsys.text_search(), opts,
+            )?;
+            info!(total = summary.total, skipped = summary.skipped, identical = summary.identical,
+                mean_overlap = summary.mean_overlap, "✅ Replay finished");
+        }
+        Command::Export { format, out } => {
+            let out = out.unwrap_or_else(|| match format {
+                TrainingFormat::Jsonl => "data/training.jsonl".into(),
+                TrainingFormat::Parquet => "data/training.parquet".into(),
+            });
+            let examples = recsys.training_examples()?;
+            let written = match format {
+                TrainingFormat::Jsonl => export::write_jsonl(&examples, std::io::BufWriter::new(std::fs::File::create(&out)?))?,
+                TrainingFormat::Parquet => export_training_parquet(&examples, &out)?,
+            };
+            info!(count = written, path = %out, "✅ Exported training examples");
+        }
+        Command::ExportVectors { format, out } => {
+            let items = recsys.items_with_embeddings(&catalog)?;
+            let dim = recsys.config().embedding.dim;
+            let out = out.unwrap_or_else(|| match format {
+                VectorFormat::Faiss => "data/items.faiss".into(),
+                VectorFormat::Npy => "data/vectors.npy".into(),
+            });
+            let file = std::io::BufWriter::new(std::fs::File::create(&out)?);
+            match format {
+                VectorFormat::Faiss => {
+                    let vectors: Vec<(u64, &[f32])> = items.iter().map(|item| (item.id, item.embedding.as_slice())).collect();
+                    mini_recsys::faiss::write_index(&vectors, dim, file)?;
+                }
+                VectorFormat::Npy => {
+                    mini_recsys::npy::write_matrix(file, items.iter().map(|item| item.embedding.as_slice()), dim)?;
+                    let ids_path = std::path::Path::new(&out).with_extension("ids.npy");
+                    let ids: Vec<u64> = items.iter().map(|item| item.id).collect();
+                    mini_recsys::npy::write_ids(std::io::BufWriter::new(std::fs::File::create(&ids_path)?), &ids)?;
+                    info!(path = %ids_path.display(), "💾 Wrote item ids");
+                }
+            }
+            info!(count = items.len(), path = %out, "✅ Exported vectors");
+        }
+        Command::ExportCatalog { out } => {
+            let written = export_catalog(&recsys, &out)?;
+            info!(count = written, path = %out, "✅ Exported catalog");
+        }
+        Command::Bench { iterations, concurrency } => {
+            info!(iterations, concurrency, "⏱️  Benchmarking request latency");
+            let report = bench::run(
+                &storage, catalog.users(), catalog.items(), catalog.item_map(),
+                recsys.embedding_model(), recsys.text_search(),
+                recsys.config().ranking, recsys.config().recall, bench::BenchOptions { iterations, concurrency },
+            )?;
+            bench::print_report(&report);
+        }
+    }
+    Ok(())
+}
 
-    let storage = Arc::new(Storage::new(DB_PATH)?);
-    println!("💾 Sled database opened at {}", DB_PATH);
+/// 按扩展名读取 `id` -> 向量 映射
+fn read_embeddings(file: &str, ids: Option<&str>) -> Result<Vec<(u64, Vec<f32>)>> {
+    use anyhow::Context;
+    let path = std::path::Path::new(file);
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("npy") => {
+            let ids = ids.ok_or_else(|| anyhow::anyhow!("--ids <ids.npy> is required with a .npy matrix"))?;
+            let ids = mini_recsys::npy::read_ids(std::path::Path::new(ids))?;
+            let matrix = mini_recsys::npy::read_matrix(path)?;
+            if ids.len() != matrix.len() {
+                anyhow::bail!("{} has {} rows but --ids has {} entries", file, matrix.len(), ids.len());
+            }
+            Ok(ids.into_iter().zip(matrix).collect())
+        }
+        Some("parquet") => read_parquet_embeddings(path),
+        Some("faiss" | "index") => {
+            let input = std::io::BufReader::new(std::fs::File::open(path)?);
+            mini_recsys::faiss::read_index(input).with_context(|| format!("Invalid FAISS index {}", file))
+        }
+        _ => anyhow::bail!("Unsupported embeddings file {} (expected .parquet, .npy, .faiss or .index)", file),
+    }
+}
+
+#[cfg(feature = "parquet")]
+fn read_parquet_embeddings(path: &std::path::Path) -> Result<Vec<(u64, Vec<f32>)>> {
+    mini_recsys::columnar::read_embeddings(path)
+}
+
+#[cfg(not(feature = "parquet"))]
+fn read_parquet_embeddings(_path: &std::path::Path) -> Result<Vec<(u64, Vec<f32>)>> {
+    anyhow::bail!("Reading Parquet needs mini-recsys built with the `parquet` feature")
+}
 
-    let text_search = Arc::new(TextSearch::new("data/tantivy_index")?);
-    println!("🔍 Text search index initialized at data/tantivy_index\n");
+#[cfg(feature = "parquet")]
+fn export_catalog(recsys: &RecSys, out: &str) -> Result<usize> {
+    let catalog = recsys.catalog();
+    let items = recsys.items_with_embeddings(&catalog)?;
+    let file = std::fs::File::create(out)?;
+    mini_recsys::columnar::write_catalog(&items, recsys.config().embedding.dim, file)
+}
 
-    let state = init_data_with_storage(Arc::clone(&storage), embedding_model, text_search)?;
-    println!("📊 Loaded {} users, {} items", state.users.len(), state.items.len());
+#[cfg(not(feature = "parquet"))]
+fn export_catalog(_recsys: &RecSys, _out: &str) -> Result<usize> {
+    anyhow::bail!("export-catalog needs mini-recsys built with the `parquet` feature")
+}
 
-    init_hnsw_with_hydration(&state.items)?;
-    println!();
+#[cfg(feature = "parquet")]
+fn export_training_parquet(examples: &[export::TrainingExample], out: &str) -> Result<usize> {
+    mini_recsys::columnar::write_training(examples, std::fs::File::create(out)?)
+}
 
+#[cfg(not(feature = "parquet"))]
+fn export_training_parquet(_examples: &[export::TrainingExample], _out: &str) -> Result<usize> {
+    anyhow::bail!("export --format parquet needs mini-recsys built with the `parquet` feature")
+}
+
+/// 启动 Web Server: 立即开始监听，数据与索引在后台预热
+/// `traced` 时每个请求建立接入调用方链路的 server span (见 `telemetry::trace_request`)
+async fn run_server(config: Config, overrides: PathOverrides, traced: bool) -> Result<()> {
+    let slow_log = SlowQueryLog::open(&config.paths.slow_log, config.slow_query)?;
+    info!(path = %config.paths.slow_log, recommend_ms = config.slow_query.recommend_ms,
+        search_ms = config.slow_query.search_ms, "🐢 Slow query log opened");
+    let cache = match &config.cache {
+        Some(cache_config) => {
+            let cache = RecommendationCache::connect(cache_config).await?;
+            info!(ttl_secs = cache_config.ttl_secs, "🗄️  Redis recommendation cache connected");
+            Some(cache)
+        }
+        None => None,
+    };
+    let state = Arc::new(AppState {
+        recsys: OnceLock::new(),
+        warmup: Warmup::new(),
+        initial_config: Arc::new(config.clone()),
+        slow_log,
+        scheduler: Scheduler::new(),
+        cache,
+        remote: config.remote.as_ref().map(RemoteStore::new).transpose()?,
+        reranker: config.rerank.as_ref().map(HttpReranker::new).transpose()?,
+        pages: PageSnapshots::new(config.pagination.ttl(), config.pagination.max_snapshots),
+    });
+    install_panic_hook(Arc::clone(&state));
+
+    let mut warmup_failed = spawn_warmup(Arc::clone(&state), config.clone());
+    #[cfg(unix)]
+    spawn_config_reload(Arc::clone(&state), overrides)?;
+    #[cfg(not(unix))]
+    let _ = overrides;
+
+    // 每次请求读取当前配置，热加载后立即生效
+    let cors_state = Arc::clone(&state);
     let cors = CorsLayer::new()
-        .allow_origin("http://localhost:5173".parse::<HeaderValue>().unwrap())
+        .allow_origin(AllowOrigin::predicate(move |origin: &HeaderValue, _| {
+            origin.as_bytes() == cors_state.config().server.cors_origin.as_bytes()
+        }))
         .allow_methods([Method::GET, Method::POST])
         .allow_headers([axum::http::header::CONTENT_TYPE]);
 
     let app = Router::new()
         .route("/health", get(health_handler))
+        .route("/status", get(status_handler))
+        .route("/stats", get(stats_handler))
         .route("/users", get(users_handler))
+        .route("/users/:id/similar", get(similar_users_handler))
+        .route("/items/:id", get(item_handler))
         .route("/recommend", get(recommend_handler))
         .route("/search", get(search_handler))
         .route("/mark_seen", post(mark_seen_handler))
-        .layer(cors)
-        .with_state(Arc::clone(&state));
+        .route("/feedback", post(feedback_handler))
+        .route("/embed", post(embed_handler))
+        .route("/admin/experiments/:id/report", get(experiment_report_handler))
+        .route("/admin/export/training", get(export_training_handler))
+        .route("/admin/import/csv", post(import_csv_handler))
+        .route("/admin/import/csv/validate", post(validate_csv_handler))
+        .route("/admin/items", post(ingest_item_handler))
+        .route("/admin/metrics", get(metrics_handler))
+        .route("/admin/requests", get(request_log_handler));
+    let app = if traced { app.route_layer(axum::middleware::from_fn(telemetry::trace_request)) } else { app };
+    let app = app.layer(cors).with_state(Arc::clone(&state));
 
-    let addr = "0.0.0.0:3000";
-    println!("🌐 Server running at http://{}", addr);
-    println!("   GET  /search?q=<query> - 语义搜索");
-    println!("   Press Ctrl+C to shutdown gracefully\n");
+    let addr = config.server.bind.clone();
+    match (config.server.unix_socket(), config.server.tls.is_some()) {
+        (Some(_), _) => info!("🌐 Server listening on {}", addr),
+        (None, true) => info!("🌐 Server running at https://{}", addr),
+        (None, false) => info!("🌐 Server running at http://{}", addr),
+    }
+    info!("   GET  /status - 预热进度 (预热完成前 /recommend 返回热门兜底推荐)");
+    info!("   GET  /search?q=<query>[&fusion=rrf|minmax|zscore] - 语义搜索");
+    info!("   POST /feedback - 上报点击");
+    info!("   POST /embed - 文本向量 (单条或批量)");
+    info!("   GET  /admin/experiments/<id>/report - A/B 实验报表");
+    info!("   GET  /admin/export/training - 导出排序训练样本 (JSONL)");
+    info!("   GET  /admin/requests?since_ms=<ts>&limit=<n> - 请求分析日志");
+    info!("   POST /admin/import/csv/validate - 校验 CSV 物品目录");
+    info!("   Press Ctrl+C to shutdown gracefully, send SIGHUP to reload config");
 
-    let listener = tokio::net::TcpListener::bind(addr).await?;
-    
-    let storage_for_shutdown = Arc::clone(&storage);
     tokio::select! {
-        result = axum::serve(listener, app) => {
-            result?;
+        result = serve(Arc::clone(&state), app, &config.server) => {
+            if let Err(e) = result {
+                graceful_shutdown(&state).await;
+                return Err(e);
+            }
         }
         _ = tokio::signal::ctrl_c() => {
-            graceful_shutdown(storage_for_shutdown).await;
+            graceful_shutdown(&state).await;
+        }
+        Ok(e) = &mut warmup_failed => {
+            return Err(e.context("Warmup failed"));
         }
     }
     
@@ -0,0 +1,168 @@
+//! 流水线指标 - 召回质量退化的预警信号
+//!
+//! 进程内原子计数器，服务重启后清零。比例类指标在快照时计算:
+//! - Bloom 命中率: 被已看过滤掉的候选 / ANN 召回的候选
+//! - 降级率: 触发热门降级填充的请求 / 推荐请求
+//! - 召回为空、HNSW 返回不足 k 条的次数 (索引损坏或物品数不足的信号)
+//! - 分数为 NaN 被丢弃的候选数 (零范数向量等坏数据的信号)
+//! - ANN 索引不可用、召回降级为暴力检索的请求数
+
+use crate::service::{StageCounts, SEARCH_RECALL_K};
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// 全局流水线指标 (/recommend 与 /search 处理器写入)
+pub static PIPELINE: PipelineMetrics = PipelineMetrics::new();
+
+/// 流水线计数器
+#[derive(Debug, Default)]
+pub struct PipelineMetrics {
+    recommend_requests: AtomicU64,
+    recommend_candidates: AtomicU64,
+    bloom_filtered: AtomicU64,
+    fallback_invocations: AtomicU64,
+    fallback_items: AtomicU64,
+    recommend_empty_recall: AtomicU64,
+    recommend_short_recall: AtomicU64,
+    search_requests: AtomicU64,
+    search_empty_recall: AtomicU64,
+    search_short_recall: AtomicU64,
+    nan_dropped: AtomicU64,
+    ann_degraded: AtomicU64,
+}
+
+/// 指标快照
+#[derive(Debug, Clone, Serialize)]
+pub struct PipelineSnapshot {
+    pub recommend_requests: u64,
+    pub recommend_candidates: u64,
+    pub bloom_filtered: u64,
+    /// bloom_filtered / recommend_candidates
+    pub bloom_hit_ratio: f64,
+    pub fallback_invocations: u64,
+    pub fallback_items: u64,
+    /// fallback_invocations / recommend_requests
+    pub fallback_rate: f64,
+    pub recommend_empty_recall: u64,
+    /// HNSW 返回少于召回深度 (`recall_k`) 条的次数
+    pub recommend_short_recall: u64,
+    pub search_requests: u64,
+    pub search_empty_recall: u64,
+    /// HNSW 返回少于 SEARCH_RECALL_K 条的次数
+    pub search_short_recall: u64,
+    /// 分数为 NaN 被丢弃的候选数 (/recommend 与 /search 合计)
+    pub nan_dropped: u64,
+    /// 召回降级为暴力检索的请求数 (/recommend 与 /search 合计)
+    pub ann_degraded: u64,
+}
+
+fn ratio(num: u64, denom: u64) -> f64 {
+    if denom == 0 { 0.0 } else { num as f64 / denom as f64 }
+}
+
+impl PipelineMetrics {
+    pub const fn new() -> Self {
+        Self {
+            recommend_requests: AtomicU64::new(0),
+            recommend_candidates: AtomicU64::new(0),
+            bloom_filtered: AtomicU64::new(0),
+            fallback_invocations: AtomicU64::new(0),
+            fallback_items: AtomicU64::new(0),
+            recommend_empty_recall: AtomicU64::new(0),
+            recommend_short_recall: AtomicU64::new(0),
+            search_requests: AtomicU64::new(0),
+            search_empty_recall: AtomicU64::new(0),
+            search_short_recall: AtomicU64::new(0),
+            nan_dropped: AtomicU64::new(0),
+            ann_degraded: AtomicU64::new(0),
+        }
+    }
+
+    /// 记录一次 /recommend
+    pub fn record_recommend(&self, counts: &StageCounts, filtered_count: usize) {
+        self.recommend_requests.fetch_add(1, Ordering::Relaxed);
+        self.recommend_candidates.fetch_add(counts.ann as u64, Ordering::Relaxed);
+        self.bloom_filtered.fetch_add(filtered_count as u64, Ordering::Relaxed);
+        if let Some(filled) = counts.fallback {
+            self.fallback_invocations.fetch_add(1, Ordering::Relaxed);
+            self.fallback_items.fetch_add(filled as u64, Ordering::Relaxed);
+        }
+        if counts.ann == 0 {
+            self.recommend_empty_recall.fetch_add(1, Ordering::Relaxed);
+        }
+        if counts.ann < counts.recall_k {
+            self.recommend_short_recall.fetch_add(1, Ordering::Relaxed);
+        }
+        self.nan_dropped.fetch_add(counts.nan_dropped as u64, Ordering::Relaxed);
+        self.ann_degraded.fetch_add(u64::from(counts.degraded), Ordering::Relaxed);
+    }
+
+    /// 记录一次 /search
+    pub fn record_search(&self, counts: &StageCounts) {
+        self.search_requests.fetch_add(1, Ordering::Relaxed);
+        if counts.ann == 0 {
+            self.search_empty_recall.fetch_add(1, Ordering::Relaxed);
+        }
+        if counts.ann < SEARCH_RECALL_K {
+            self.search_short_recall.fetch_add(1, Ordering::Relaxed);
+        }
+        self.nan_dropped.fetch_add(counts.nan_dropped as u64, Ordering::Relaxed);
+        self.ann_degraded.fetch_add(u64::from(counts.degraded), Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> PipelineSnapshot {
+        let load = |c: &AtomicU64| c.load(Ordering::Relaxed);
+        let recommend_requests = load(&self.recommend_requests);
+        let recommend_candidates = load(&self.recommend_candidates);
+        let bloom_filtered = load(&self.bloom_filtered);
+        let fallback_invocations = load(&self.fallback_invocations);
+        PipelineSnapshot {
+            recommend_requests,
+            recommend_candidates,
+            bloom_filtered,
+            bloom_hit_ratio: ratio(bloom_filtered, recommend_candidates),
+            fallback_invocations,
+            fallback_items: load(&self.fallback_items),
+            fallback_rate: ratio(fallback_invocations, recommend_requests),
+            recommend_empty_recall: load(&self.recommend_empty_recall),
+            recommend_short_recall: load(&self.recommend_short_recall),
+            search_requests: load(&self.search_requests),
+            search_empty_recall: load(&self.search_empty_recall),
+            search_short_recall: load(&self.search_short_recall),
+            nan_dropped: load(&self.nan_dropped),
+            ann_degraded: load(&self.ann_degraded),
+        }
+    }
+}
+
+// ============================================================================
+// 单元测试
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recommend_ratios() {
+        let metrics = PipelineMetrics::new();
+        metrics.record_recommend(&StageCounts { ann: 100, recall_k: 100, ..StageCounts::default() }, 25);
+        metrics.record_recommend(&StageCounts { ann: 0, recall_k: 100, fallback: Some(5), nan_dropped: 2, degraded: true, ..StageCounts::default() }, 0);
+
+        let snap = metrics.snapshot();
+        assert_eq!(snap.recommend_requests, 2);
+        assert!((snap.bloom_hit_ratio - 0.25).abs() < 1e-9);
+        assert_eq!((snap.fallback_invocations, snap.fallback_items), (1, 5));
+        assert!((snap.fallback_rate - 0.5).abs() < 1e-9);
+        assert_eq!((snap.recommend_empty_recall, snap.recommend_short_recall), (1, 1));
+        assert_eq!(snap.nan_dropped, 2);
+        assert_eq!(snap.ann_degraded, 1);
+    }
+
+    #[test]
+    fn test_empty_snapshot_has_zero_ratios() {
+        let snap = PipelineMetrics::new().snapshot();
+        assert_eq!(snap.bloom_hit_ratio, 0.0);
+        assert_eq!(snap.fallback_rate, 0.0);
+    }
+}
@@ -1,7 +1,11 @@
 //! 数据模型定义
 
-use rand::Rng;
+use crate::hybrid::FusionStrategy;
+use crate::i18n;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 pub const DIM: usize = 384;
 
@@ -12,8 +16,11 @@ pub struct User {
     pub embedding: Vec<f32>,
 }
 
+/// 语言 -> 标题 (语言标签经 `i18n::normalize` 规范化)
+pub type Titles = BTreeMap<String, String>;
+
 /// 用于从 JSON 加载的临时结构（不含 embedding 和 popularity）
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct ItemJson {
     pub id: u64,
     #[serde(rename = "title")]
@@ -21,6 +28,17 @@ pub struct ItemJson {
     pub category: String,
     pub image_url: String,
     pub price: f32,
+    /// 其他语言的标题，例如 `{"zh": "罗技 MX Master 3S 鼠标"}`
+    #[serde(default)]
+    pub titles: Titles,
+}
+
+impl ItemJson {
+    /// `lang` 语言的标题，没有该语言 (或 `lang` 为 None) 时返回 `title`
+    pub fn title(&self, lang: Option<&str>) -> &str {
+        lang.and_then(|lang| self.titles.iter().find(|(tag, _)| i18n::normalize(tag) == lang))
+            .map_or(&self.name, |(_, title)| title)
+    }
 }
 
 /// 完整的 Item 结构（用于存储和运行时）
@@ -33,10 +51,17 @@ pub struct Item {
     pub price: f32,
     pub embedding: Vec<f32>,
     pub popularity: f32,
+    /// 其他语言的标题；单独存放在数据库的 item_titles Tree 中，物品记录的编码格式不变
+    #[serde(skip)]
+    pub titles: Titles,
 }
 
 impl Item {
     pub fn from_json(json: ItemJson, embedding: Vec<f32>, popularity: f32) -> Self {
+        let titles = json.titles.into_iter()
+            .map(|(tag, title)| (i18n::normalize(&tag), title.trim().to_string()))
+            .filter(|(tag, title)| !tag.is_empty() && !title.is_empty())
+            .collect();
         Self {
             id: json.id,
             name: json.name,
@@ -45,9 +70,15 @@ impl Item {
             price: json.price,
             embedding,
             popularity,
+            titles,
         }
     }
 
+    /// `lang` (已规范化) 语言的标题，没有该语言的译名时返回 `name`
+    pub fn title(&self, lang: &str) -> &str {
+        self.titles.get(lang).map_or(&self.name, String::as_str)
+    }
+
     #[cfg(test)]
     pub fn new(id: u64, name: impl Into<String>, embedding: Vec<f32>) -> Self {
         Self {
@@ -58,10 +89,76 @@ impl Item {
             price: 0.0,
             embedding,
             popularity: 0.5,
+            titles: Titles::new(),
         }
     }
 }
 
+/// 用户交互类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EventKind {
+    View,
+    Click,
+}
+
+/// 交互日志中的一条记录 (离线评估 / 协同过滤的原始数据)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InteractionEvent {
+    pub uid: u64,
+    pub item_id: u64,
+    pub kind: EventKind,
+    /// Unix 毫秒时间戳
+    pub ts_ms: u64,
+}
+
+impl InteractionEvent {
+    pub fn new(uid: u64, item_id: u64, kind: EventKind) -> Self {
+        Self { uid, item_id, kind, ts_ms: now_ms() }
+    }
+}
+
+/// 请求日志中记录的请求参数 (足以重放该请求)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum LoggedRequest {
+    Recommend { uid: u64 },
+    Search { q: String, fusion: FusionStrategy },
+}
+
+/// A/B 实验分桶结果
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExperimentTag {
+    pub experiment_id: String,
+    pub variant: String,
+}
+
+/// 一次已返回的请求及其结果 (请求分析日志)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RequestRecord {
+    /// Unix 毫秒时间戳
+    pub ts_ms: u64,
+    pub request: LoggedRequest,
+    /// 按返回顺序排列的物品 ID
+    pub item_ids: Vec<u64>,
+    /// 与 item_ids 一一对应的最终得分 (/recommend 为 final_score，/search 为融合分)
+    pub scores: Vec<f32>,
+    /// 该请求命中的实验变体
+    pub experiment: Option<ExperimentTag>,
+    /// 服务端处理耗时 (毫秒)
+    pub latency_ms: f64,
+}
+
+/// 当前 Unix 毫秒时间戳
+pub fn now_ms() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// 目录中的全部类别
+pub const CATEGORIES: [&str; 4] = ["Electronics", "Books", "Home", "Clothing"];
+
 /// 类别锚点向量
 pub fn category_base_vector(category: &str) -> Vec<f32> {
     let mut vec = vec![0.0f32; DIM];
@@ -78,8 +175,12 @@ pub fn category_base_vector(category: &str) -> Vec<f32> {
     vec
 }
 
-pub fn generate_category_embedding(category: &str) -> Vec<f32> {
-    let mut rng = rand::thread_rng();
+/// 演示数据的随机数生成器: 指定种子时结果可复现，否则使用系统熵
+pub fn demo_rng(seed: Option<u64>) -> StdRng {
+    seed.map_or_else(StdRng::from_entropy, StdRng::seed_from_u64)
+}
+
+pub fn generate_category_embedding(category: &str, rng: &mut impl Rng) -> Vec<f32> {
     let base = category_base_vector(category);
     let vec: Vec<f32> = base.iter()
         .map(|&v| v + rng.gen::<f32>() * 0.2 - 0.1)
@@ -88,8 +189,7 @@ pub fn generate_category_embedding(category: &str) -> Vec<f32> {
     vec.into_iter().map(|x| x / norm).collect()
 }
 
-pub fn generate_user_embedding(categories: &[&str]) -> Vec<f32> {
-    let mut rng = rand::thread_rng();
+pub fn generate_user_embedding(categories: &[&str], rng: &mut impl Rng) -> Vec<f32> {
     let mut combined = vec![0.0f32; DIM];
     for cat in categories {
         let base = category_base_vector(cat);
@@ -105,9 +205,27 @@ pub fn generate_user_embedding(categories: &[&str]) -> Vec<f32> {
 }
 
 /// 生成完全随机的向量 (用于噪声用户)
-pub fn generate_random_embedding() -> Vec<f32> {
-    let mut rng = rand::thread_rng();
+pub fn generate_random_embedding(rng: &mut impl Rng) -> Vec<f32> {
     let vec: Vec<f32> = (0..DIM).map(|_| rng.gen::<f32>() * 2.0 - 1.0).collect();
     let norm: f32 = vec.iter().map(|x| x * x).sum::<f32>().sqrt();
     vec.into_iter().map(|x| x / norm).collect()
 }
+
+// ============================================================================
+// 单元测试
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seeded_demo_data_is_reproducible() {
+        let generate = |seed| {
+            let mut rng = demo_rng(Some(seed));
+            (generate_category_embedding("Books", &mut rng), generate_random_embedding(&mut rng), rng.gen::<f32>())
+        };
+        assert_eq!(generate(7), generate(7));
+        assert_ne!(generate(7), generate(8));
+    }
+}
@@ -0,0 +1,209 @@
+//! NumPy `.npy` 读写 - 与外部工具交换向量 (`np.save("vectors.npy", X.astype("float32"))`)
+//!
+//! 读取只支持 C 顺序、小端的数组: 向量矩阵为 `<f4` / `<f8`，ID 为 `<i8` / `<u8` / `<i4` / `<u4`。
+//! 写出的矩阵为 `<f4`，ID 为 `<u8`。
+
+use anyhow::{anyhow, bail, Context, Result};
+use std::io::Write;
+use std::path::Path;
+
+const MAGIC: &[u8] = b"\x93NUMPY";
+
+/// 解析后的 `.npy` 头
+#[derive(Debug, PartialEq)]
+struct Header {
+    descr: String,
+    shape: Vec<usize>,
+}
+
+/// 读取二维浮点矩阵，每行一个向量
+pub fn read_matrix(path: &Path) -> Result<Vec<Vec<f32>>> {
+    let bytes = std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let (header, data) = parse(&bytes).with_context(|| format!("Invalid .npy file {}", path.display()))?;
+    let &[rows, cols] = header.shape.as_slice() else {
+        bail!("{} has shape {:?}, expected (items, dim)", path.display(), header.shape);
+    };
+    let values: Vec<f32> = match header.descr.as_str() {
+        "<f4" => elements::<4>(data, rows * cols)?.map(f32::from_le_bytes).collect(),
+        "<f8" => elements::<8>(data, rows * cols)?.map(|b| f64::from_le_bytes(b) as f32).collect(),
+        other => bail!("{} has dtype {}, expected float32 or float64", path.display(), other),
+    };
+    Ok(values.chunks(cols.max(1)).map(<[f32]>::to_vec).collect())
+}
+
+/// 读取一维整数 ID 数组
+pub fn read_ids(path: &Path) -> Result<Vec<u64>> {
+    let bytes = std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let (header, data) = parse(&bytes).with_context(|| format!("Invalid .npy file {}", path.display()))?;
+    let &[len] = header.shape.as_slice() else {
+        bail!("{} has shape {:?}, expected (items,)", path.display(), header.shape);
+    };
+    let signed = |id: i64| u64::try_from(id).map_err(|_| anyhow!("Negative id {} in {}", id, path.display()));
+    match header.descr.as_str() {
+        "<u8" => Ok(elements::<8>(data, len)?.map(u64::from_le_bytes).collect()),
+        "<i8" => elements::<8>(data, len)?.map(|b| signed(i64::from_le_bytes(b))).collect(),
+        "<u4" => Ok(elements::<4>(data, len)?.map(|b| u32::from_le_bytes(b) as u64).collect()),
+        "<i4" => elements::<4>(data, len)?.map(|b| signed(i32::from_le_bytes(b) as i64)).collect(),
+        other => bail!("{} has dtype {}, expected an integer type", path.display(), other),
+    }
+}
+
+/// 写出二维 float32 矩阵 (每个向量都须是 `dim` 维)
+pub fn write_matrix<'a>(out: impl Write, rows: impl ExactSizeIterator<Item = &'a [f32]>, dim: usize) -> Result<()> {
+    let mut out = start(out, "<f4", &format!("({}, {})", rows.len(), dim))?;
+    for row in rows {
+        if row.len() != dim {
+            bail!("Vector has {} dimensions, expected {}", row.len(), dim);
+        }
+        for value in row {
+            out.write_all(&value.to_le_bytes())?;
+        }
+    }
+    out.flush()?;
+    Ok(())
+}
+
+/// 写出一维 uint64 ID 数组
+pub fn write_ids(out: impl Write, ids: &[u64]) -> Result<()> {
+    let mut out = start(out, "<u8", &format!("({},)", ids.len()))?;
+    for id in ids {
+        out.write_all(&id.to_le_bytes())?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+/// 写出 v1.0 头部 (补空格使数据区按 64 字节对齐，与 numpy 一致)
+fn start<W: Write>(mut out: W, descr: &str, shape: &str) -> Result<W> {
+    let mut header = format!("{{'descr': '{}', 'fortran_order': False, 'shape': {}, }}", descr, shape);
+    while !(MAGIC.len() + 4 + header.len() + 1).is_multiple_of(64) {
+        header.push(' ');
+    }
+    header.push('\n');
+    out.write_all(MAGIC)?;
+    out.write_all(&[1, 0])?;
+    out.write_all(&(header.len() as u16).to_le_bytes())?;
+    out.write_all(header.as_bytes())?;
+    Ok(out)
+}
+
+/// 按 `N` 字节切分数据区，长度不足时报错
+fn elements<const N: usize>(data: &[u8], count: usize) -> Result<impl Iterator<Item = [u8; N]> + '_> {
+    if data.len() < count * N {
+        bail!("data is truncated: expected {} bytes, found {}", count * N, data.len());
+    }
+    Ok(data[..count * N].chunks_exact(N).map(|b| b.try_into().expect("chunk has N bytes")))
+}
+
+/// 拆分头部与数据区
+fn parse(bytes: &[u8]) -> Result<(Header, &[u8])> {
+    if !bytes.starts_with(MAGIC) || bytes.len() < 10 {
+        bail!("missing NUMPY magic");
+    }
+    let (header_len, start) = match bytes[6] {
+        1 => (u16::from_le_bytes([bytes[8], bytes[9]]) as usize, 10),
+        2 | 3 if bytes.len() >= 12 => (u32::from_le_bytes([bytes[8], bytes[9], bytes[10], bytes[11]]) as usize, 12),
+        v => bail!("unsupported .npy version {}", v),
+    };
+    let end = start + header_len;
+    let text = bytes.get(start..end).context("header is truncated")?;
+    let header = parse_header(std::str::from_utf8(text).context("header is not UTF-8")?)?;
+    Ok((header, &bytes[end..]))
+}
+
+/// 头部是 Python 字典字面量，例如 `{'descr': '<f4', 'fortran_order': False, 'shape': (3, 384), }`
+fn parse_header(text: &str) -> Result<Header> {
+    let value = |key: &str| -> Result<&str> {
+        let at = text.find(&format!("'{}'", key)).ok_or_else(|| anyhow!("header has no '{}'", key))?;
+        let rest = &text[at + key.len() + 2..];
+        Ok(rest.trim_start().strip_prefix(':').ok_or_else(|| anyhow!("malformed '{}'", key))?.trim_start())
+    };
+
+    let descr = value("descr")?
+        .strip_prefix('\'')
+        .and_then(|s| s.split('\'').next())
+        .ok_or_else(|| anyhow!("malformed 'descr'"))?;
+    // `|u1` 之类的单字节类型没有字节序
+    let descr = descr.replacen('=', "<", 1).replacen('|', "<", 1);
+
+    if value("fortran_order")?.starts_with("True") {
+        bail!("Fortran-ordered arrays are not supported (save with np.ascontiguousarray)");
+    }
+
+    let shape = value("shape")?
+        .strip_prefix('(')
+        .and_then(|s| s.split(')').next())
+        .ok_or_else(|| anyhow!("malformed 'shape'"))?;
+    let shape = shape.split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|s| s.parse::<usize>().map_err(|_| anyhow!("malformed 'shape' dimension {:?}", s)))
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(Header { descr, shape })
+}
+
+// ============================================================================
+// 单元测试
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn npy(descr: &str, shape: &str, data: &[u8]) -> Vec<u8> {
+        let mut bytes = start(Vec::new(), descr, shape).unwrap();
+        bytes.extend_from_slice(data);
+        bytes
+    }
+
+    fn write_temp(name: &str, bytes: &[u8]) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("mini-recsys-{}-{}.npy", name, std::process::id()));
+        std::fs::write(&path, bytes).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_parse_header() {
+        let header = parse_header("{'descr': '<f4', 'fortran_order': False, 'shape': (3, 384), }").unwrap();
+        assert_eq!(header, Header { descr: "<f4".into(), shape: vec![3, 384] });
+        assert_eq!(parse_header("{'descr': '<i8', 'fortran_order': False, 'shape': (5,), }").unwrap().shape, vec![5]);
+        assert!(parse_header("{'descr': '<f4', 'fortran_order': True, 'shape': (3, 4), }").is_err());
+    }
+
+    #[test]
+    fn test_read_matrix_and_ids() {
+        let data: Vec<u8> = [1.0f32, 2.0, 3.0, 4.0, 5.0, 6.0].iter().flat_map(|v| v.to_le_bytes()).collect();
+        let path = write_temp("matrix", &npy("<f4", "(2, 3)", &data));
+        assert_eq!(read_matrix(&path).unwrap(), vec![vec![1.0, 2.0, 3.0], vec![4.0, 5.0, 6.0]]);
+        assert!(read_ids(&path).is_err());
+        std::fs::remove_file(&path).ok();
+
+        let data: Vec<u8> = [7i64, 42].iter().flat_map(|v| v.to_le_bytes()).collect();
+        let path = write_temp("ids", &npy("<i8", "(2,)", &data));
+        assert_eq!(read_ids(&path).unwrap(), vec![7, 42]);
+        std::fs::remove_file(&path).ok();
+
+        let path = write_temp("truncated", &npy("<f4", "(2, 3)", &[0; 8]));
+        assert!(read_matrix(&path).is_err());
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn test_write_round_trip() {
+        let rows: [&[f32]; 2] = [&[0.5, 1.0], &[-1.0, 2.0]];
+        let mut bytes = Vec::new();
+        write_matrix(&mut bytes, rows.into_iter(), 2).unwrap();
+        assert_eq!((bytes.len() - 2 * 2 * 4) % 64, 0);
+        let path = write_temp("written", &bytes);
+        assert_eq!(read_matrix(&path).unwrap(), vec![vec![0.5, 1.0], vec![-1.0, 2.0]]);
+
+        let mut bytes = Vec::new();
+        write_ids(&mut bytes, &[3, u64::MAX]).unwrap();
+        std::fs::write(&path, &bytes).unwrap();
+        assert_eq!(read_ids(&path).unwrap(), vec![3, u64::MAX]);
+        std::fs::remove_file(&path).ok();
+
+        assert!(write_matrix(Vec::new(), rows.into_iter(), 3).is_err());
+    }
+}
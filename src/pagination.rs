@@ -0,0 +1,184 @@
+//! 推荐分页快照 - 翻页时不重新召回
+//!
+//! 每次请求都重新召回时，两页之间的已看过滤、热门度或索引变化会让结果前后错位，
+//! 客户端看到重复或漏掉的物品。带 `page_size` 的第一页把完整的排序结果存为短期快照，
+//! 响应中的 `next_page_token` 记录快照 ID 与偏移量，之后的页都从同一份快照中切片。
+//!
+//! 快照只保存在本进程内存中，过期 (`pagination.ttl_secs`) 或被容量上限淘汰后令牌失效，
+//! 客户端需要从第一页重新开始。多副本部署时翻页请求需要落在同一副本上。
+
+use crate::model::ExperimentTag;
+use crate::service::ScoredItem;
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// 一次推荐的完整排序结果
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    /// 快照只对生成它的用户有效
+    pub uid: u64,
+    pub ranked: Vec<ScoredItem>,
+    pub filtered_count: usize,
+    pub experiment: Option<ExperimentTag>,
+    pub reranked: bool,
+    pub degraded: bool,
+}
+
+impl Snapshot {
+    /// 从 `offset` 开始取 `size` 个结果，并返回下一页的偏移量 (已到末尾时为 None)
+    pub fn page(&self, offset: usize, size: usize) -> (&[ScoredItem], Option<usize>) {
+        let start = offset.min(self.ranked.len());
+        let end = start.saturating_add(size).min(self.ranked.len());
+        let next = (end < self.ranked.len()).then_some(end);
+        (&self.ranked[start..end], next)
+    }
+}
+
+/// 翻页令牌: `<快照 ID (16 位十六进制)>.<偏移量>`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PageToken {
+    pub snapshot: u64,
+    pub offset: usize,
+}
+
+impl fmt::Display for PageToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:016x}.{}", self.snapshot, self.offset)
+    }
+}
+
+impl FromStr for PageToken {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Malformed page_token {:?}", s);
+        let (snapshot, offset) = s.split_once('.').ok_or_else(invalid)?;
+        Ok(Self {
+            snapshot: u64::from_str_radix(snapshot, 16).map_err(|_| invalid())?,
+            offset: offset.parse().map_err(|_| invalid())?,
+        })
+    }
+}
+
+struct Entry {
+    snapshot: Arc<Snapshot>,
+    expires: Instant,
+}
+
+/// 带过期时间与容量上限的快照表
+pub struct PageSnapshots {
+    ttl: Duration,
+    capacity: usize,
+    entries: Mutex<HashMap<u64, Entry>>,
+}
+
+impl PageSnapshots {
+    /// 快照保留 `ttl`，最多同时保留 `capacity` 份 (至少 1 份)
+    pub fn new(ttl: Duration, capacity: usize) -> Self {
+        Self { ttl, capacity: capacity.max(1), entries: Mutex::new(HashMap::new()) }
+    }
+
+    /// 保存快照并返回其 ID；超出容量时淘汰最早过期的快照
+    pub fn insert(&self, snapshot: Snapshot) -> u64 {
+        let now = Instant::now();
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.retain(|_, entry| entry.expires > now);
+        while entries.len() >= self.capacity {
+            let Some(oldest) = entries.iter().min_by_key(|(_, entry)| entry.expires).map(|(&id, _)| id) else { break };
+            entries.remove(&oldest);
+        }
+
+        let mut id = rand::random::<u64>();
+        while entries.contains_key(&id) {
+            id = rand::random();
+        }
+        entries.insert(id, Entry { snapshot: Arc::new(snapshot), expires: now + self.ttl });
+        id
+    }
+
+    /// 取出未过期的快照
+    pub fn get(&self, id: u64) -> Option<Arc<Snapshot>> {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        match entries.get(&id) {
+            Some(entry) if entry.expires > Instant::now() => Some(Arc::clone(&entry.snapshot)),
+            Some(_) => {
+                entries.remove(&id);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// 当前保留的快照数 (含尚未清理的过期快照)
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+// ============================================================================
+// 单元测试
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(uid: u64, n: u64) -> Snapshot {
+        let ranked = (1..=n)
+            .map(|id| ScoredItem { item_id: id, sim_score: 0.0, popularity: 0.0, final_score: 1.0 / id as f32 })
+            .collect();
+        Snapshot { uid, ranked, filtered_count: 0, experiment: None, reranked: false, degraded: false }
+    }
+
+    fn ids(items: &[ScoredItem]) -> Vec<u64> {
+        items.iter().map(|item| item.item_id).collect()
+    }
+
+    #[test]
+    fn test_pages_cover_snapshot_without_gaps() {
+        let snap = snapshot(1, 5);
+        let (first, next) = snap.page(0, 2);
+        assert_eq!((ids(first), next), (vec![1, 2], Some(2)));
+        let (second, next) = snap.page(2, 2);
+        assert_eq!((ids(second), next), (vec![3, 4], Some(4)));
+        let (last, next) = snap.page(4, 2);
+        assert_eq!((ids(last), next), (vec![5], None));
+        // 偏移量越界时返回空页
+        assert_eq!(snap.page(9, 2).0.len(), 0);
+    }
+
+    #[test]
+    fn test_page_token_round_trip() {
+        let token = PageToken { snapshot: 0xdead_beef, offset: 20 };
+        assert_eq!(token.to_string(), "00000000deadbeef.20");
+        assert_eq!(token.to_string().parse::<PageToken>(), Ok(token));
+        for bad in ["", "deadbeef", "xyz.1", "deadbeef.-1", "deadbeef.1.2"] {
+            assert!(bad.parse::<PageToken>().is_err(), "{:?}", bad);
+        }
+    }
+
+    #[test]
+    fn test_snapshots_expire_and_evict() {
+        let store = PageSnapshots::new(Duration::from_secs(60), 2);
+        let a = store.insert(snapshot(1, 3));
+        let b = store.insert(snapshot(2, 3));
+        assert_eq!(store.get(a).unwrap().uid, 1);
+        // 超出容量时淘汰最早的快照
+        let c = store.insert(snapshot(3, 3));
+        assert!(store.get(a).is_none());
+        assert_eq!((store.get(b).unwrap().uid, store.get(c).unwrap().uid), (2, 3));
+        assert_eq!(store.len(), 2);
+
+        let expired = PageSnapshots::new(Duration::ZERO, 2);
+        let id = expired.insert(snapshot(1, 3));
+        assert!(expired.get(id).is_none());
+        assert!(expired.is_empty());
+    }
+}
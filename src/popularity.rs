@@ -0,0 +1,119 @@
+//! 热门度 - 按曝光与点击计算物品 CTR，并用 Wilson 区间下界平滑
+//!
+//! 曝光是请求分析日志中已返回的 /recommend 结果 (保留 `request_log.retention_hours`)，
+//! 点击是归因窗口内的 Click 事件 (与实验报表的口径相同，见 `experiment.rs`)。
+//! 曝光很少的物品 CTR 波动大，取 Wilson 下界会把它们压向 0，避免 1 次曝光 1 次点击就排到最前。
+//! 下界再除以全目录的最大值缩放到 0..=1，与排序权重调参时的取值范围一致。
+//!
+//! 由 `popularity_refresh` 后台任务 (或 `popularity` 命令) 重算并写入数据库；
+//! 没有曝光的物品 (包括新写入的物品) 热门度为 0。
+
+use crate::experiment::ClickIndex;
+use crate::model::{InteractionEvent, LoggedRequest, RequestRecord};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// 单个物品的曝光、点击与热门度 (持久化在数据库中)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct ItemPopularity {
+    pub impressions: u64,
+    /// 归因窗口内被点击的曝光数 (不超过 impressions)
+    pub clicks: u64,
+    /// 排序使用的热门度: Wilson 下界 / 全目录最大值 (0..=1)
+    pub score: f32,
+}
+
+/// CTR 的 Wilson 区间下界；`z` 为置信水平对应的分位数 (1.96 ≈ 95%)，没有曝光时为 0
+pub fn wilson_lower_bound(clicks: u64, impressions: u64, z: f64) -> f64 {
+    if impressions == 0 {
+        return 0.0;
+    }
+    let n = impressions as f64;
+    let p = (clicks as f64 / n).min(1.0);
+    let z2 = z * z;
+    let centre = p + z2 / (2.0 * n);
+    let margin = z * ((p * (1.0 - p) + z2 / (4.0 * n)) / n).sqrt();
+    ((centre - margin) / (1.0 + z2 / n)).max(0.0)
+}
+
+/// 统计每个被曝光过的物品的曝光与点击，并计算热门度
+pub fn compute(requests: &[RequestRecord], events: &[InteractionEvent], z: f64) -> HashMap<u64, ItemPopularity> {
+    let clicks = ClickIndex::new(events);
+    let mut stats: HashMap<u64, ItemPopularity> = HashMap::new();
+    for record in requests {
+        let LoggedRequest::Recommend { uid } = record.request else { continue };
+        for &item_id in &record.item_ids {
+            let entry = stats.entry(item_id).or_default();
+            entry.impressions += 1;
+            if clicks.clicked(uid, item_id, record.ts_ms) {
+                entry.clicks += 1;
+            }
+        }
+    }
+
+    let bounds: HashMap<u64, f64> = stats.iter()
+        .map(|(&id, s)| (id, wilson_lower_bound(s.clicks, s.impressions, z)))
+        .collect();
+    let max = bounds.values().copied().fold(0.0, f64::max);
+    if max > 0.0 {
+        for (id, entry) in stats.iter_mut() {
+            entry.score = (bounds[id] / max) as f32;
+        }
+    }
+    stats
+}
+
+// ============================================================================
+// 单元测试
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::EventKind;
+
+    fn request(uid: u64, ts_ms: u64, item_ids: Vec<u64>) -> RequestRecord {
+        RequestRecord {
+            ts_ms,
+            request: LoggedRequest::Recommend { uid },
+            scores: vec![0.0; item_ids.len()],
+            item_ids,
+            experiment: None,
+            latency_ms: 0.0,
+        }
+    }
+
+    fn click(uid: u64, item_id: u64, ts_ms: u64) -> InteractionEvent {
+        InteractionEvent { uid, item_id, kind: EventKind::Click, ts_ms }
+    }
+
+    #[test]
+    fn test_wilson_lower_bound() {
+        assert_eq!(wilson_lower_bound(0, 0, 1.96), 0.0);
+        assert_eq!(wilson_lower_bound(0, 100, 1.96), 0.0);
+        // 同样 100% 的 CTR，曝光越多下界越高
+        let few = wilson_lower_bound(1, 1, 1.96);
+        let many = wilson_lower_bound(100, 100, 1.96);
+        assert!(few < 0.25 && many > 0.95, "few = {}, many = {}", few, many);
+        // 已知值: 50 / 100 在 95% 置信度下约为 0.404
+        assert!((wilson_lower_bound(50, 100, 1.96) - 0.4038).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_compute_counts_attributed_clicks() {
+        let requests: Vec<RequestRecord> = (0..20)
+            .map(|i| request(i % 4, 1_000 * i, vec![1, 2, 3]))
+            .collect();
+        // 物品 1 每次都被点击，物品 2 只在 1/4 的曝光后被点击；曝光之前的点击不计
+        let mut events: Vec<InteractionEvent> = (0..20).map(|i| click(i % 4, 1, 1_000 * i + 10)).collect();
+        events.extend((0..20).step_by(4).map(|i| click(0, 2, 1_000 * i + 10)));
+        events.push(click(9, 3, 0));
+
+        let stats = compute(&requests, &events, 1.96);
+        assert_eq!(stats[&1], ItemPopularity { impressions: 20, clicks: 20, score: 1.0 });
+        assert_eq!((stats[&2].impressions, stats[&2].clicks), (20, 5));
+        assert!(stats[&2].score > 0.0 && stats[&2].score < 1.0);
+        assert_eq!(stats[&3].score, 0.0);
+        assert!(!stats.contains_key(&4));
+    }
+}
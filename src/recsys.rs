@@ -0,0 +1,946 @@
+//! RecSys 门面 - 供其他 Rust 服务直接嵌入推荐系统
+//!
+//! 负责存储、ANN 索引、语义编码与全文索引的初始化，并对外提供推荐 / 搜索 / 写入三个入口。
+//! HTTP 服务 (`main.rs`) 只是这一层之上的薄封装。
+//!
+//! 注意: HNSW 索引是进程级全局单例 (见 `ffi.rs`)，一个进程内只应打开一个 `RecSys`。
+//!
+//! ```no_run
+//! use mini_recsys::config::Config;
+//! use mini_recsys::recsys::{RecSys, RecommendOptions};
+//!
+//! let recsys = RecSys::open(Config::load()?)?;
+//! if let Some(rec) = recsys.recommend(1, RecommendOptions::default())? {
+//!     for item in &rec.output.ranked {
+//!         println!("{} {:.3}", item.item_id, item.final_score);
+//!     }
+//! }
+//! # anyhow::Ok(())
+//! ```
+
+use crate::ann::{self, HnswIndex, IndexGeneration};
+use crate::catalog::Catalog;
+use crate::config::Config;
+use crate::embedding::EmbeddingModel;
+use crate::eval::{self, EvalOptions, StrategyReport};
+use crate::experiment;
+use crate::export::{self, TrainingExample};
+use crate::ffi::{add_item_to_hnsw, get_hnsw_count, hnsw_search, init_hnsw_index, load_hnsw_index, save_hnsw_index, set_hnsw_ef, HnswConfig};
+use crate::hybrid::FusionStrategy;
+use crate::impressions::{self, RecentImpressions};
+use crate::lookalike::{self, UserHistories, UserIndex};
+use crate::popularity::{self, ItemPopularity};
+use crate::model::{
+    demo_rng, generate_category_embedding, generate_random_embedding, generate_user_embedding, EventKind, ExperimentTag,
+    InteractionEvent, Item, ItemJson, RequestRecord, User, DIM,
+};
+use crate::service::{self, RankOutput, RankWeights, RecallPolicy, SearchOutput, MAX_RECOMMENDATIONS};
+use crate::storage::Storage;
+use crate::text_search::TextSearch;
+use crate::warmup::{Phase, Warmup};
+use crate::events::{self, EventSink};
+use crate::webhooks::{WebhookEvent, Webhooks};
+use anyhow::{anyhow, bail, Context, Result};
+use fastbloom_rs::Membership;
+use rand::rngs::StdRng;
+use rand::Rng;
+use rayon::prelude::*;
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex, MutexGuard, RwLock};
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+
+/// 推荐参数
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RecommendOptions {
+    /// 指定排序权重；为 None 时按 A/B 实验分桶，未命中实验时使用配置中的默认权重
+    pub weights: Option<RankWeights>,
+    /// 最多返回的结果数；为 None 时返回 `MAX_RECOMMENDATIONS` 个
+    pub limit: Option<usize>,
+    /// 覆盖 `recall.k` (超出 `recall.max_k` 时取上限)
+    pub recall_k: Option<usize>,
+    /// 覆盖 `recall.min_results`
+    pub min_results: Option<usize>,
+}
+
+/// 推荐结果
+pub struct Recommendation {
+    pub output: RankOutput,
+    /// 命中的实验变体 (显式指定权重时为 None)
+    pub experiment: Option<ExperimentTag>,
+}
+
+/// `import_embeddings` 的结果
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EmbeddingImport {
+    pub updated: usize,
+    /// 目录中不存在、被跳过的 ID 数
+    pub unknown: usize,
+}
+
+/// `refresh_popularity` 的结果
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PopularityRefresh {
+    /// 更新的物品数 (目录中的全部物品)
+    pub items: usize,
+    /// 有曝光记录的物品数
+    pub impressed: usize,
+    pub impressions: u64,
+    pub clicks: u64,
+}
+
+/// 搜索参数
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SearchOptions {
+    pub fusion: FusionStrategy,
+}
+
+/// 推荐系统实例
+pub struct RecSys {
+    /// 当前生效的配置；热加载时整体替换
+    config: RwLock<Arc<Config>>,
+    storage: Arc<Storage>,
+    /// 用户与物品目录；写入时整体替换 (见 `catalog.rs`)
+    catalog: RwLock<Arc<Catalog>>,
+    /// 串行化目录写入与索引重建
+    writes: Mutex<()>,
+    embedding_model: Option<Arc<EmbeddingModel>>,
+    text_search: Arc<TextSearch>,
+    /// 配置了 `[[webhooks]]` 时推送物品与反馈事件
+    webhooks: Option<Webhooks>,
+    /// 交互与曝光记录的落地方式 (`[events] sinks`)
+    sinks: Vec<Box<dyn EventSink>>,
+    /// 生成演示用户与类别向量的随机源 (由 `demo.seed` 决定是否可复现)
+    rng: Mutex<StdRng>,
+    /// HNSW 索引的重建代数；加载失败或正在重建时召回降级为暴力检索
+    ann_generation: IndexGeneration,
+    /// 最近返回给各用户但未被点击的物品 (见 `impressions.rs`)
+    impressions: RecentImpressions,
+    /// 目录用户最近交互过的物品，供相似用户召回 (见 `lookalike.rs`)
+    histories: UserHistories,
+}
+
+impl RecSys {
+    /// 按配置打开数据库与索引
+    ///
+    /// 数据库为空时从 `paths.products` 导入物品并写入演示用户；
+    /// HNSW 索引文件缺失或与数据库不一致时从数据库重建。
+    pub fn open(config: Config) -> Result<Self> {
+        Self::open_with_progress(config, &Warmup::new())
+    }
+
+    /// 同 `open`，并把各阶段进度、用户列表与兜底推荐写入 `warmup`
+    ///
+    /// 不会把 `warmup` 标记为完成: 调用方在实例可供请求使用后自行调用 `Warmup::finish`。
+    pub fn open_with_progress(config: Config, warmup: &Warmup) -> Result<Self> {
+        // 1. 初始化 ONNX 模型 (失败时降级为类别向量，搜索不可用)
+        let sessions = config.embedding.session_count();
+        let embedding_model = match EmbeddingModel::from_files_with_sessions(&config.paths.model, &config.paths.tokenizer, sessions) {
+            Ok(model) => {
+                let model = model.with_cache(config.memory.embedding_cache);
+                info!(dimension = model.dimension(), sessions, cache = config.memory.embedding_cache, "🧠 Embedding model loaded");
+                if model.dimension() != config.embedding.dim {
+                    bail!("embedding.dim = {} but the model outputs {} dimensions", config.embedding.dim, model.dimension());
+                }
+                Some(Arc::new(model))
+            }
+            Err(e) => {
+                warn!(error = %e, "⚠️  Failed to load embedding model, search will be unavailable");
+                None
+            }
+        };
+        if embedding_model.is_none() && config.embedding.dim != DIM {
+            bail!("embedding.dim = {} but category fallback vectors have {} dimensions", config.embedding.dim, DIM);
+        }
+
+        let webhooks = match config.webhooks.is_empty() {
+            true => None,
+            false => {
+                info!(count = config.webhooks.len(), "🪝 Webhooks enabled");
+                Some(Webhooks::new(config.webhooks.clone())?)
+            }
+        };
+
+        config.paths.create_dirs()?;
+        let storage = Arc::new(Storage::open(&config.paths.db, config.bloom)?);
+        info!(path = %config.paths.db, "💾 Sled database opened");
+        let sinks = events::open_sinks(&config.events, &storage)?;
+
+        let text_search = Arc::new(TextSearch::new(&config.paths.text_index)?);
+        info!(path = %config.paths.text_index, "🔍 Text search index initialized");
+
+        // 2. 加载用户与物品 (用户先于耗时的物品编码发布，预热期间即可选择用户)
+        let mut rng = demo_rng(config.demo.seed);
+        if let Some(seed) = config.demo.seed {
+            info!(seed, "🎲 Using fixed seed for demo data");
+        }
+        let users = if storage.users_count() == 0 {
+            let users = demo_users(&mut rng);
+            for user in &users { storage.save_user(user)?; }
+            info!(count = users.len(), "💾 Saved users to database");
+            users
+        } else {
+            storage.get_all_users()?
+        };
+        warmup.set_users(&users);
+
+        let max_items = config.memory.max_items;
+        let items = if storage.items_count() == 0 {
+            info!(path = %config.paths.products, "📂 Database empty, loading products");
+            let items = read_catalog_json(&config.paths.products)
+                .context("The database is empty and needs a catalog to seed it (set paths.products or --products)")?;
+            let items = embed_items(items, embedding_model.as_deref(), config.i18n.encode_lang().as_deref(), &mut rng, warmup)?;
+            for item in &items { storage.save_item(item)?; }
+            info!(count = items.len(), "💾 Saved items to database");
+
+            info!("🔍 Building text search index");
+            for item in &items {
+                text_search.index_item(item)?;
+            }
+            text_search.commit()?;
+            info!("✅ Text index built");
+
+            items
+        } else {
+            info!("📂 Loading items from database");
+            let items: Vec<Item> = storage.iter_items()
+                .filter_map(|r| r.ok())
+                .collect();
+            info!(count = items.len(), "📦 Loaded items from database");
+            items
+        };
+        warmup.set_fallback(&items);
+        let catalog = Catalog::new(users, items, max_items);
+        if catalog.spilled() > 0 {
+            info!(resident = max_items, spilled = catalog.spilled(), "🪶 Item vectors beyond memory.max_items are read from the database");
+        }
+        info!(users = catalog.users().len(), items = catalog.items().len(), "📊 Catalog loaded");
+
+        // 相似用户召回关闭时不保留历史
+        let history_len = if config.lookalike.neighbors > 0 { config.lookalike.history_len } else { 0 };
+        let histories = UserHistories::new(history_len);
+        if history_len > 0 {
+            let events: Vec<InteractionEvent> = storage.iter_events()
+                .filter_map(|r| r.ok())
+                .filter(|event| catalog.user(event.uid).is_some())
+                .collect();
+            histories.load(&events);
+            info!(users = histories.users(), events = events.len(), "👥 User histories loaded");
+        }
+
+        let recsys = Self {
+            impressions: RecentImpressions::new(config.impressions.ttl(), config.impressions.max_users),
+            histories,
+            config: RwLock::new(Arc::new(config)),
+            storage,
+            catalog: RwLock::new(Arc::new(catalog)),
+            writes: Mutex::new(()),
+            embedding_model,
+            text_search,
+            webhooks,
+            sinks,
+            rng: Mutex::new(rng),
+            ann_generation: IndexGeneration::new(),
+        };
+
+        // 3. 加载 / 重建 HNSW 索引 (失败时仍可服务，召回降级为暴力检索)
+        if let Err(e) = recsys.hydrate_hnsw(warmup) {
+            warn!(error = %format!("{:#}", e), "⚠️  HNSW index unavailable, recall will fall back to brute force");
+            recsys.ann_generation.invalidate();
+        }
+        Ok(recsys)
+    }
+
+    // ========== 访问器 ==========
+
+    /// 当前生效配置的快照
+    pub fn config(&self) -> Arc<Config> {
+        Arc::clone(&self.config.read().unwrap_or_else(|e| e.into_inner()))
+    }
+
+    pub fn storage(&self) -> &Arc<Storage> {
+        &self.storage
+    }
+
+    /// 当前目录的快照，不受之后的写入影响
+    ///
+    /// 超出 `memory.max_items` 的物品 `embedding` 为空 (向量只在数据库与 HNSW 索引中)。
+    pub fn catalog(&self) -> Arc<Catalog> {
+        Arc::clone(&self.catalog.read().unwrap_or_else(|e| e.into_inner()))
+    }
+
+    pub fn embedding_model(&self) -> Option<&EmbeddingModel> {
+        self.embedding_model.as_deref()
+    }
+
+    pub fn text_search(&self) -> &TextSearch {
+        &self.text_search
+    }
+
+    /// 召回使用的 HNSW 索引 (加载失败时视为空索引)
+    pub fn hnsw_index(&self) -> HnswIndex<'_> {
+        HnswIndex::new(&self.ann_generation)
+    }
+
+    // ========== 推荐 / 搜索 / 写入 ==========
+
+    /// 为用户生成推荐，用户不存在时返回 None
+    pub fn recommend(&self, uid: u64, opts: RecommendOptions) -> Result<Option<Recommendation>> {
+        let catalog = self.catalog();
+        let Some(user) = catalog.user(uid) else { return Ok(None) };
+
+        let (experiment, weights) = match opts.weights {
+            Some(weights) => (None, weights),
+            // A/B 实验分桶决定排序权重
+            None => match experiment::assign(user.id) {
+                Some((tag, weights)) => (Some(tag), weights),
+                None => (None, self.config().ranking),
+            },
+        };
+
+        let limit = opts.limit.unwrap_or(MAX_RECOMMENDATIONS);
+        let policy = self.recall_policy(&opts);
+        let lookalike = self.lookalike_candidates(&catalog, user);
+        let penalty = self.config().impressions.penalty;
+        let recent = if penalty > 0.0 { self.impressions.recent(uid) } else { HashSet::new() };
+        if recent.is_empty() {
+            let output = service::recommend(&self.storage, &self.hnsw_index(), user, catalog.items(), catalog.item_map(), weights, limit, policy, lookalike)?;
+            return Ok(Some(Recommendation { output, experiment }));
+        }
+
+        // 近期曝光降权: 对完整的召回结果重新排序后再截断，排在后面的候选才能补上来
+        let mut output = service::recommend(&self.storage, &self.hnsw_index(), user, catalog.items(), catalog.item_map(), weights, limit.max(policy.k), policy, lookalike)?;
+        output.counts.penalized = impressions::penalize(&mut output.ranked, &recent, penalty);
+        output.ranked.truncate(limit);
+        output.counts.returned = output.ranked.len();
+        Ok(Some(Recommendation { output, experiment }))
+    }
+
+    /// 与用户最相似的 `k` 个其他用户及相似度，用户不存在时返回 None
+    pub fn similar_users(&self, uid: u64, k: usize) -> Option<Vec<(User, f32)>> {
+        let catalog = self.catalog();
+        let user = catalog.user(uid)?;
+        let neighbors = UserIndex::new(catalog.users()).neighbors(user, k);
+        Some(neighbors.into_iter()
+            .filter_map(|(id, similarity)| Some((catalog.user(id)?.clone(), similarity)))
+            .collect())
+    }
+
+    /// 混合搜索，模型未加载时返回 None
+    pub fn search(&self, query: &str, opts: SearchOptions) -> Result<Option<SearchOutput>> {
+        let Some(model) = self.embedding_model() else { return Ok(None) };
+        service::hybrid_search(model, &self.text_search, &self.hnsw_index(), self.catalog().items(), query, opts.fusion).map(Some)
+    }
+
+    /// 把文本编码为 L2 归一化的向量 (与物品、查询向量同一空间)，模型未加载时返回 None
+    ///
+    /// 单条文本走编码缓存，多条按启动编码的批大小分批推理。
+    pub fn embed(&self, texts: &[String]) -> Result<Option<Vec<Vec<f32>>>> {
+        let Some(model) = self.embedding_model() else { return Ok(None) };
+        if let [text] = texts {
+            return model.encode(text).map(|vector| Some(vec![vector]));
+        }
+        let mut vectors = Vec::with_capacity(texts.len());
+        for chunk in texts.chunks(ENCODE_BATCH) {
+            let chunk: Vec<&str> = chunk.iter().map(String::as_str).collect();
+            vectors.extend(model.encode_batch(&chunk)?);
+        }
+        Ok(Some(vectors))
+    }
+
+    /// 把物品写入用户的已看过滤器，之后不再推荐给该用户
+    pub fn mark_seen(&self, uid: u64, item_ids: &[u64]) -> Result<()> {
+        let mut filter = self.storage.get_user_filter(uid).context("Failed to get filter")?;
+        for item_id in item_ids {
+            filter.add(&item_id.to_le_bytes());
+        }
+        self.storage.save_user_filter(uid, &filter).context("Failed to save filter")
+    }
+
+    /// 记录一条交互: 浏览同时写入已看过滤器，所有交互都写入交互日志
+    pub fn record_event(&self, event: &InteractionEvent) -> Result<()> {
+        if event.kind == EventKind::View {
+            self.mark_seen(event.uid, &[event.item_id])?;
+        }
+        self.log_interaction(event)
+    }
+
+    /// 把一条交互写入每个 `events.sinks`
+    pub fn log_interaction(&self, event: &InteractionEvent) -> Result<()> {
+        if event.kind == EventKind::Click {
+            self.impressions.clicked(event.uid, event.item_id);
+        }
+        if self.catalog().user(event.uid).is_some() {
+            self.histories.record(event.uid, event.item_id);
+        }
+        for sink in &self.sinks {
+            sink.record_interaction(event).with_context(|| format!("Failed to log event to {}", sink.name()))?;
+        }
+        Ok(())
+    }
+
+    /// 记录返回给用户的推荐结果，之后的推荐会压低其中未被点击的物品 (`impressions.penalty` 为 0 时不记录)
+    pub fn record_impressions(&self, uid: u64, item_ids: &[u64]) {
+        if self.config().impressions.penalty > 0.0 {
+            self.impressions.record(uid, item_ids);
+        }
+    }
+
+    /// 该用户的下一次推荐会因近期曝光而轮换 (此时不能直接返回缓存的结果)
+    pub fn rotates(&self, uid: u64) -> bool {
+        self.config().impressions.penalty > 0.0 && !self.impressions.recent(uid).is_empty()
+    }
+
+    /// 删除曝光已全部过期的用户，返回删除的数量 (`impressions_prune` 任务)
+    pub fn prune_impressions(&self) -> usize {
+        self.impressions.prune()
+    }
+
+    /// 跟踪中的近期曝光用户数
+    pub fn impression_users(&self) -> usize {
+        self.impressions.users()
+    }
+
+    /// 把一次已返回的请求写入每个 `events.sinks`
+    pub fn log_impression(&self, record: &RequestRecord) -> Result<()> {
+        for sink in &self.sinks {
+            sink.record_impression(record).with_context(|| format!("Failed to log request to {}", sink.name()))?;
+        }
+        Ok(())
+    }
+
+    /// 等待各落地方式缓冲中的记录写完 (退出前调用)，每个最多等待 `timeout`
+    pub fn flush_events(&self, timeout: Duration) {
+        for sink in &self.sinks {
+            let unwritten = sink.flush(timeout);
+            if unwritten > 0 {
+                warn!(sink = sink.name(), unwritten, "⚠️  Exiting with unwritten event records");
+            }
+        }
+    }
+
+    /// 推送一个 Webhook 事件 (未配置 `[[webhooks]]` 时忽略)
+    pub fn notify(&self, event: &WebhookEvent) {
+        if let Some(webhooks) = &self.webhooks {
+            webhooks.notify(event);
+        }
+    }
+
+    /// 等待排队中的 Webhook 投递完成 (退出前调用)，最多等待 `timeout`
+    pub fn flush_webhooks(&self, timeout: Duration) {
+        let Some(webhooks) = &self.webhooks else { return };
+        let undelivered = webhooks.flush(timeout);
+        if undelivered > 0 {
+            warn!(undelivered, "⚠️  Exiting with undelivered webhooks");
+        }
+    }
+
+    /// 写入 (或覆盖) 单个物品并立即加入 HNSW 与全文索引，服务中也可调用
+    ///
+    /// 覆盖已有 ID 时，全文索引中的旧文档会保留到下一次 `reindex`。
+    pub fn ingest(&self, json: ItemJson) -> Result<()> {
+        let _writes = self.lock_writes();
+        let encode_lang = self.config().i18n.encode_lang();
+        let encoded = self.embedding_model.as_deref().and_then(|m| m.encode(json.title(encode_lang.as_deref())).ok());
+        let mut item = embed_item(json, encoded, &mut *self.rng());
+        item.popularity = self.stored_popularity(item.id)?;
+        self.storage.save_item(&item)?;
+        add_item_to_hnsw(item.id, &item.embedding).map_err(|e| anyhow!(e))?;
+        self.text_search.index_item(&item)?;
+        self.text_search.commit()?;
+        self.upsert(vec![item]);
+        Ok(())
+    }
+
+    /// 写入 (或覆盖) 用户，服务中也可调用；之后的请求即可为其推荐
+    pub fn upsert_user(&self, user: User) -> Result<()> {
+        let _writes = self.lock_writes();
+        self.storage.save_user(&user)?;
+        self.update_catalog(|catalog| catalog.upsert_user(user));
+        Ok(())
+    }
+
+    /// 从 JSON 文件批量导入物品 (格式同 assets/products.json)，随后重建全部索引
+    pub fn import(&self, path: &str) -> Result<usize> {
+        let items = read_catalog_json(path)?;
+        self.import_items(items)
+    }
+
+    /// 批量写入 (或覆盖) 物品，随后重建全部索引
+    ///
+    /// 服务中调用时，重建期间的请求召回降级为暴力检索 (见 `ann.rs`)。
+    pub fn import_items(&self, items: Vec<ItemJson>) -> Result<usize> {
+        let _writes = self.lock_writes();
+        let encode_lang = self.config().i18n.encode_lang();
+        let mut items = embed_items(items, self.embedding_model.as_deref(), encode_lang.as_deref(), &mut *self.rng(), &Warmup::new())?;
+        let count = items.len();
+        for item in &mut items {
+            item.popularity = self.stored_popularity(item.id)?;
+            self.storage.save_item(item)?;
+        }
+        self.upsert(items);
+        info!(count, "📥 Imported items");
+        self.rebuild_indexes()?;
+        self.storage.flush()?;
+        Ok(count)
+    }
+
+    /// 用外部计算的向量替换已有物品的向量 (不经过 ONNX 编码)，随后重建全部索引
+    ///
+    /// 向量先做 L2 归一化 (排序按点积计算相似度)。目录中不存在的 ID 会被跳过；
+    /// 任一向量维度与 `embedding.dim` 不符、含 NaN / 无穷值或范数为 0 时不写入任何数据。
+    pub fn import_embeddings(&self, vectors: Vec<(u64, Vec<f32>)>) -> Result<EmbeddingImport> {
+        let _writes = self.lock_writes();
+        let dim = self.config().embedding.dim;
+        if let Some((id, vector)) = vectors.iter().find(|(_, v)| v.len() != dim) {
+            bail!("Vector for item {} has {} dimensions, expected embedding.dim = {}", id, vector.len(), dim);
+        }
+        let vectors = vectors.into_iter()
+            .map(|(id, vector)| Ok((id, normalize_imported(id, vector)?)))
+            .collect::<Result<Vec<_>>>()?;
+
+        let catalog = self.catalog();
+        let mut summary = EmbeddingImport::default();
+        let mut updated = Vec::new();
+        for (id, embedding) in vectors {
+            let Some(existing) = catalog.item(id) else {
+                summary.unknown += 1;
+                continue;
+            };
+            let item = Item { embedding, ..existing.clone() };
+            self.storage.save_item(&item)?;
+            updated.push(item);
+        }
+        summary.updated = updated.len();
+        self.upsert(updated);
+        if summary.unknown > 0 {
+            warn!(unknown = summary.unknown, "⚠️  Skipped vectors for items that are not in the catalog");
+        }
+        info!(updated = summary.updated, "📥 Imported embeddings");
+        self.rebuild_indexes()?;
+        self.storage.flush()?;
+        Ok(summary)
+    }
+
+    /// 丢弃现有索引，从内存中的物品列表重建 HNSW (并保存到磁盘) 与全文索引
+    pub fn reindex(&self) -> Result<()> {
+        let _writes = self.lock_writes();
+        self.rebuild_indexes()
+    }
+
+    /// `reindex` 的实现 (调用方持有写入锁)
+    fn rebuild_indexes(&self) -> Result<()> {
+        let catalog = self.catalog();
+        info!(count = catalog.items().len(), "🔄 Rebuilding HNSW index");
+        let failed = ann::rebuild(&self.ann_generation, || -> Result<usize> {
+            init_hnsw_index(&self.hnsw_config(catalog.items().len())).map_err(|e| anyhow!(e))?;
+            Ok(catalog.items().iter().filter(|item| self.add_to_hnsw(item).is_err()).count())
+        })?;
+        if failed > 0 {
+            warn!(failed, "⚠️  Some items could not be added to the HNSW index");
+        }
+        let config = self.config();
+        let index_path = &config.paths.index;
+        save_hnsw_index(index_path).map_err(|e| anyhow!(e))?;
+        info!(count = get_hnsw_count(), path = %index_path, "💾 HNSW index saved");
+
+        info!("🔍 Rebuilding text search index");
+        self.text_search.clear()?;
+        for item in catalog.items() {
+            self.text_search.index_item(item)?;
+        }
+        self.text_search.commit()?;
+        info!("✅ Reindex finished");
+        Ok(())
+    }
+
+    /// 按请求日志中的 /recommend 曝光与交互日志中的点击重算全部物品的热门度 (见 `popularity.rs`)，
+    /// 写入数据库并替换内存目录中的值；没有曝光的物品热门度为 0
+    pub fn refresh_popularity(&self) -> Result<PopularityRefresh> {
+        let requests: Vec<RequestRecord> = self.storage.iter_requests_since(0).collect::<Result<_>>()?;
+        let events: Vec<InteractionEvent> = self.storage.iter_events().collect::<Result<_>>()?;
+        let stats = popularity::compute(&requests, &events, self.config().popularity.z);
+
+        let _writes = self.lock_writes();
+        let entries: Vec<(u64, ItemPopularity)> = self.catalog().items().iter()
+            .map(|item| (item.id, stats.get(&item.id).copied().unwrap_or_default()))
+            .collect();
+        self.storage.save_popularity(&entries)?;
+        self.update_catalog(|catalog| {
+            for (id, popularity) in &entries {
+                catalog.set_popularity(*id, popularity.score);
+            }
+        });
+
+        let impressed: Vec<&ItemPopularity> = entries.iter().map(|(_, p)| p).filter(|p| p.impressions > 0).collect();
+        Ok(PopularityRefresh {
+            items: entries.len(),
+            impressed: impressed.len(),
+            impressions: impressed.iter().map(|p| p.impressions).sum(),
+            clicks: impressed.iter().map(|p| p.clicks).sum(),
+        })
+    }
+
+    /// 按时间切分交互日志，离线评估各召回策略
+    pub fn evaluate(&self, opts: EvalOptions) -> Result<Vec<StrategyReport>> {
+        let catalog = self.catalog();
+        eval::evaluate(&self.storage, catalog.users(), catalog.items(), catalog.item_map(), hnsw_search, opts)
+    }
+
+    /// 基于当前请求日志与交互日志生成排序训练样本
+    pub fn training_examples(&self) -> Result<Vec<TrainingExample>> {
+        let requests: Vec<RequestRecord> = self.storage.iter_requests_since(0).collect::<Result<_>>()?;
+        let events: Vec<InteractionEvent> = self.storage.iter_events().collect::<Result<_>>()?;
+        let catalog = self.catalog();
+        let items = self.items_with_embeddings(&catalog)?;
+        Ok(export::training_examples(&requests, &events, catalog.users(), &items, catalog.item_map()))
+    }
+
+    /// `catalog` 中带完整向量的物品列表 (没有溢出时直接借用，溢出的向量从数据库读取)
+    pub fn items_with_embeddings<'a>(&self, catalog: &'a Catalog) -> Result<Cow<'a, [Item]>> {
+        if catalog.items().iter().all(|item| !item.embedding.is_empty()) {
+            return Ok(Cow::Borrowed(catalog.items()));
+        }
+        catalog.items().iter()
+            .map(|item| Ok(Item { embedding: self.embedding_of(item)?.into_owned(), ..item.clone() }))
+            .collect::<Result<Vec<_>>>()
+            .map(Cow::Owned)
+    }
+
+    /// 保存 HNSW 索引并刷新数据库 (退出前调用)
+    pub fn persist(&self) -> Result<()> {
+        let config = self.config();
+        let index_path = &config.paths.index;
+        save_hnsw_index(index_path).map_err(|e| anyhow!(e))?;
+        info!(path = %index_path, "💾 HNSW index saved");
+        self.storage.flush()?;
+        info!("💾 Sled database flushed");
+        Ok(())
+    }
+
+    /// 热加载配置，不重建索引、不中断服务
+    ///
+    /// 只应用可在运行时生效的配置项 (`ranking`、`recall`、`hnsw.ef_search`、`server.cors_origin`)，
+    /// 其余配置段的变更保留旧值并打印警告，需重启生效。返回实际变更的配置项。
+    pub fn reload(&self, new: Config) -> Result<Vec<&'static str>> {
+        new.validate()?;
+        let mut guard = self.config.write().unwrap_or_else(|e| e.into_inner());
+        let mut next = Config::clone(&guard);
+        let mut changed = Vec::new();
+
+        if next.ranking != new.ranking {
+            next.ranking = new.ranking;
+            changed.push("ranking");
+        }
+        if next.recall != new.recall {
+            next.recall = new.recall;
+            changed.push("recall");
+        }
+        if next.search != new.search {
+            next.search = new.search;
+            changed.push("search");
+        }
+        if next.hnsw.ef_search != new.hnsw.ef_search {
+            next.hnsw.ef_search = new.hnsw.ef_search;
+            set_hnsw_ef(next.hnsw.ef_search);
+            changed.push("hnsw.ef_search");
+        }
+        if next.server.cors_origin != new.server.cors_origin {
+            next.server.cors_origin = new.server.cors_origin.clone();
+            changed.push("server.cors_origin");
+        }
+
+        let pending = changed_sections(&next, &new)?;
+        if !pending.is_empty() {
+            warn!(sections = ?pending, "⚠️  Config changes that require a restart were ignored");
+        }
+
+        *guard = Arc::new(next);
+        Ok(changed)
+    }
+
+    // ========== 内部 ==========
+
+    /// 目录写入锁 (写入之间串行；读取不受影响)
+    fn lock_writes(&self) -> MutexGuard<'_, ()> {
+        self.writes.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// `recall` 配置加上请求中的覆盖值
+    fn recall_policy(&self, opts: &RecommendOptions) -> RecallPolicy {
+        let policy = self.config().recall;
+        RecallPolicy {
+            k: opts.recall_k.map_or(policy.k, |k| k.clamp(1, policy.max_k)),
+            min_results: opts.min_results.unwrap_or(policy.min_results),
+            ..policy
+        }
+    }
+
+    /// 最相似的 `lookalike.neighbors` 个用户交互过的物品 (关闭时为空)
+    fn lookalike_candidates(&self, catalog: &Catalog, user: &User) -> Vec<(u64, f32)> {
+        let config = self.config();
+        if config.lookalike.neighbors == 0 {
+            return Vec::new();
+        }
+        let neighbors = UserIndex::new(catalog.users()).neighbors(user, config.lookalike.neighbors);
+        lookalike::recall(&neighbors, &self.histories, config.lookalike.weight)
+    }
+
+    /// 上一次计算的热门度 (新物品为 0)，覆盖写入物品时保留
+    fn stored_popularity(&self, id: u64) -> Result<f32> {
+        Ok(self.storage.get_popularity(id)?.map_or(0.0, |p| p.score))
+    }
+
+    fn rng(&self) -> MutexGuard<'_, StdRng> {
+        self.rng.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// 在写锁内修改目录；仍有请求持有旧快照时先复制一份，已发出的快照保持不变
+    fn update_catalog<R>(&self, update: impl FnOnce(&mut Catalog) -> R) -> R {
+        let mut guard = self.catalog.write().unwrap_or_else(|e| e.into_inner());
+        update(Arc::make_mut(&mut guard))
+    }
+
+    /// 写入内存中的物品列表 (调用方已写入数据库) 并推送 `item.created` / `item.updated`
+    fn upsert(&self, items: Vec<Item>) {
+        let events: Vec<WebhookEvent> = self.update_catalog(|catalog| {
+            items.into_iter()
+                .map(|item| {
+                    let event = WebhookEvent::item_upserted(&item, catalog.item(item.id).is_some());
+                    catalog.upsert_item(item);
+                    event
+                })
+                .collect()
+        });
+        for event in &events {
+            self.notify(event);
+        }
+    }
+
+    /// 物品向量: 常驻内存时直接借用，已溢出时从数据库读取
+    fn embedding_of<'a>(&self, item: &'a Item) -> Result<Cow<'a, [f32]>> {
+        if !item.embedding.is_empty() {
+            return Ok(Cow::Borrowed(&item.embedding));
+        }
+        let stored = self.storage.get_item(item.id)?
+            .ok_or_else(|| anyhow!("Item {} is missing from the database", item.id))?;
+        Ok(Cow::Owned(stored.embedding))
+    }
+
+    fn add_to_hnsw(&self, item: &Item) -> Result<()> {
+        let embedding = self.embedding_of(item)?;
+        add_item_to_hnsw(item.id, &embedding).map_err(|e| anyhow!(e))
+    }
+
+    /// `items` 个物品的索引参数 (另留 `hnsw.headroom` 个空位给运行时写入)
+    fn hnsw_config(&self, items: usize) -> HnswConfig {
+        let config = self.config();
+        HnswConfig {
+            dim: config.embedding.dim,
+            max_elements: items + config.hnsw.headroom,
+            m: config.hnsw.m,
+            ef_construction: config.hnsw.ef_construction,
+            ef_search: config.hnsw.ef_search,
+        }
+    }
+
+    fn hydrate_hnsw(&self, warmup: &Warmup) -> Result<()> {
+        let config = self.config();
+        let index_path = &config.paths.index;
+        info!(path = %index_path, "🔧 Loading HNSW index");
+        let catalog = self.catalog();
+        let db_count = catalog.items().len();
+        let loaded = load_hnsw_index(index_path, &self.hnsw_config(db_count))
+            .map_err(|e| anyhow!(e))?;
+
+        let index_count = get_hnsw_count();
+
+        if loaded && index_count == db_count {
+            info!(count = index_count, "✅ HNSW index loaded (consistent with DB)");
+            return Ok(());
+        }
+
+        if !loaded {
+            info!("📝 Index file not found, created new empty index");
+        } else {
+            warn!(index_count, db_count, "⚠️  Index count != DB count, rebuilding");
+        }
+
+        info!("🔄 Hydrating index from database");
+        warmup.start_phase(Phase::Indexing, db_count);
+        let success = catalog.items().iter()
+            .inspect(|_| warmup.advance())
+            .filter(|item| self.add_to_hnsw(item).is_ok())
+            .count();
+        info!(count = success, "✅ HNSW index rebuilt");
+
+        Ok(())
+    }
+}
+
+/// 两份配置中取值不同的顶层配置段
+fn changed_sections(a: &Config, b: &Config) -> Result<Vec<String>> {
+    let (serde_json::Value::Object(a), serde_json::Value::Object(b)) = (serde_json::to_value(a)?, serde_json::to_value(b)?) else {
+        bail!("config is not an object");
+    };
+    Ok(a.into_iter().filter(|(key, value)| b.get(key) != Some(value)).map(|(key, _)| key).collect())
+}
+
+/// 校验并 L2 归一化一条导入的向量；范数在 f64 中计算，避免很大的分量溢出
+fn normalize_imported(id: u64, vector: Vec<f32>) -> Result<Vec<f32>> {
+    if let Some(idx) = vector.iter().position(|v| !v.is_finite()) {
+        bail!("Vector for item {} has a non-finite value at index {}", id, idx);
+    }
+    let norm = vector.iter().map(|&v| f64::from(v).powi(2)).sum::<f64>().sqrt();
+    if norm == 0.0 {
+        bail!("Vector for item {} has zero norm and cannot be normalized", id);
+    }
+    Ok(vector.into_iter().map(|v| (f64::from(v) / norm) as f32).collect())
+}
+
+// ============================================================================
+// 数据初始化
+// ============================================================================
+
+/// 演示用户
+fn demo_users(rng: &mut impl Rng) -> Vec<User> {
+    vec![
+        // 明确单一兴趣的用户
+        User { id: 1, name: "程序员小明 (Electronics + Books)".into(), embedding: generate_user_embedding(&["Electronics", "Books"], rng) },
+        User { id: 2, name: "居家达人小红 (Home)".into(), embedding: generate_user_embedding(&["Home"], rng) },
+        User { id: 3, name: "时尚达人小美 (Clothing)".into(), embedding: generate_user_embedding(&["Clothing"], rng) },
+
+        // 双兴趣用户
+        User { id: 4, name: "极客玩家 (Electronics)".into(), embedding: generate_user_embedding(&["Electronics"], rng) },
+        User { id: 5, name: "书虫 (Books)".into(), embedding: generate_user_embedding(&["Books"], rng) },
+        User { id: 6, name: "生活家 (Home + Clothing)".into(), embedding: generate_user_embedding(&["Home", "Clothing"], rng) },
+
+        // 混合兴趣用户
+        User { id: 7, name: "全能选手 (All Categories)".into(), embedding: generate_user_embedding(&["Electronics", "Books", "Home", "Clothing"], rng) },
+        User { id: 8, name: "科技宅 (Electronics + Home)".into(), embedding: generate_user_embedding(&["Electronics", "Home"], rng) },
+
+        // 噪声用户 - 使用随机embedding
+        User { id: 9, name: "新用户A (Random)".into(), embedding: generate_random_embedding(rng) },
+        User { id: 10, name: "新用户B (Random)".into(), embedding: generate_random_embedding(rng) },
+    ]
+}
+
+/// 启动编码时每次推理的批大小
+const ENCODE_BATCH: usize = 32;
+
+/// 使用已编码的标题向量 (编码失败或无模型时降级为类别向量)；热门度为 0，由 `refresh_popularity` 按点击率计算
+fn embed_item(json: ItemJson, encoded: Option<Vec<f32>>, rng: &mut impl Rng) -> Item {
+    let embedding = encoded.unwrap_or_else(|| generate_category_embedding(&json.category, rng));
+    Item::from_json(json, embedding, 0.0)
+}
+
+/// 并行编码物品标题: 按批切分，在与 Session 数相同大小的线程池中推理，结果保持输入顺序
+///
+/// 编码 `encode_lang` 语言的标题 (没有该语言时用 `title`)。
+/// 整批推理失败时逐条重试，单条仍失败的返回 None (由调用方降级为类别向量)。
+fn encode_titles(model: &EmbeddingModel, items: &[ItemJson], encode_lang: Option<&str>, warmup: &Warmup) -> Result<Vec<Option<Vec<f32>>>> {
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(model.sessions())
+        .thread_name(|i| format!("encode-{}", i))
+        .build()?;
+
+    let batches: Vec<Vec<Option<Vec<f32>>>> = pool.install(|| {
+        items.par_chunks(ENCODE_BATCH)
+            .map(|chunk| {
+                let names: Vec<&str> = chunk.iter().map(|json| json.title(encode_lang)).collect();
+                let encoded = match model.encode_batch(&names) {
+                    Ok(vectors) => vectors.into_iter().map(Some).collect(),
+                    Err(e) => {
+                        warn!(error = %e, batch = chunk.len(), "⚠️  Batch encoding failed, retrying one by one");
+                        names.iter().map(|name| model.encode(name).ok()).collect()
+                    }
+                };
+                warmup.advance_by(chunk.len());
+                debug!(encoded = warmup.status().done, total = items.len(), "Encoding progress");
+                encoded
+            })
+            .collect()
+    });
+    Ok(batches.into_iter().flatten().collect())
+}
+
+/// 读取 JSON 物品目录 (格式同 assets/products.json)
+fn read_catalog_json(path: &str) -> Result<Vec<ItemJson>> {
+    let json_str = std::fs::read_to_string(path).with_context(|| format!("Failed to read catalog {}", path))?;
+    serde_json::from_str(&json_str).with_context(|| format!("Failed to parse catalog {}", path))
+}
+
+/// 编码标题并生成物品，进度写入 `warmup`
+fn embed_items(
+    items_json: Vec<ItemJson>,
+    model: Option<&EmbeddingModel>,
+    encode_lang: Option<&str>,
+    rng: &mut impl Rng,
+    warmup: &Warmup,
+) -> Result<Vec<Item>> {
+    let total = items_json.len();
+
+    // 编码完成前先按目录顺序提供兜底推荐 (此时还没有热门度)
+    let preview: Vec<Item> = items_json.iter().take(MAX_RECOMMENDATIONS)
+        .map(|json| Item::from_json(json.clone(), Vec::new(), 0.0))
+        .collect();
+    warmup.set_fallback(&preview);
+    warmup.start_phase(Phase::Encoding, total);
+
+    // 编码可并行；降级向量的随机数之后再按目录顺序串行生成
+    let encoded = match model {
+        Some(model) => {
+            info!(total, sessions = model.sessions(), "🧠 Encoding items with ONNX model");
+            let started = Instant::now();
+            let encoded = encode_titles(model, &items_json, encode_lang, warmup)?;
+            let failed = encoded.iter().filter(|e| e.is_none()).count();
+            info!(total, failed, elapsed_ms = service::elapsed_ms(started), "✅ All items encoded with semantic vectors");
+            encoded
+        }
+        None => {
+            warn!("⚠️  No embedding model, using category-based vectors");
+            warmup.advance_by(total);
+            vec![None; total]
+        }
+    };
+
+    Ok(items_json.into_iter()
+        .zip(encoded)
+        .map(|(json, encoded)| embed_item(json, encoded, rng))
+        .collect())
+}
+
+// ============================================================================
+// 单元测试
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_imported() {
+        let vector = normalize_imported(1, vec![3.0, 0.0, 4.0]).unwrap();
+        assert_eq!(vector, vec![0.6, 0.0, 0.8]);
+
+        let err = normalize_imported(2, vec![1.0, f32::NAN, 0.0]).unwrap_err().to_string();
+        assert!(err.contains("item 2") && err.contains("non-finite"), "{}", err);
+        assert!(normalize_imported(3, vec![f32::INFINITY, 0.0]).is_err());
+        let err = normalize_imported(4, vec![0.0; 3]).unwrap_err().to_string();
+        assert!(err.contains("item 4") && err.contains("zero norm"), "{}", err);
+    }
+
+    #[test]
+    fn test_changed_sections() {
+        let a = Config::default();
+        let mut b = Config::default();
+        assert!(changed_sections(&a, &b).unwrap().is_empty());
+
+        b.paths.db = "elsewhere".into();
+        b.hnsw.m = 32;
+        let mut sections = changed_sections(&a, &b).unwrap();
+        sections.sort();
+        assert_eq!(sections, vec!["hnsw", "paths"]);
+    }
+}
@@ -0,0 +1,188 @@
+//! S3 兼容对象存储 (需要 `s3` feature) - 副本启动时从桶中拉取数据库快照与 HNSW 索引
+//!
+//! 桶中的布局 (`<prefix>` 为 `remote.prefix`):
+//!
+//! - `<prefix>snapshot.bin`: 数据库快照 (见 `Storage::write_snapshot`)
+//! - `<prefix>index.bin`: HNSW 索引文件
+//!
+//! 本地数据库为空时才会拉取，已有数据的副本不受影响。两个文件分别上传，可能来自不同时刻；
+//! 索引与数据库不一致时启动过程会从数据库重建索引 (与本地文件不一致时相同)。
+//!
+//! 未启用 feature 时 `RemoteStore` 无法构造。
+
+#[cfg(feature = "s3")]
+pub use s3::RemoteStore;
+
+#[cfg(feature = "s3")]
+mod s3 {
+    use crate::config::{PathsConfig, RemoteConfig};
+    use crate::storage::Storage;
+    use anyhow::{Context, Result};
+    use futures::StreamExt;
+    use object_store::aws::{AmazonS3, AmazonS3Builder};
+    use object_store::buffered::BufWriter;
+    use object_store::path::Path as ObjectPath;
+    use object_store::ObjectStore;
+    use std::path::{Path, PathBuf};
+    use std::sync::Arc;
+    use tokio::io::AsyncWriteExt;
+    use tracing::info;
+
+    const SNAPSHOT_OBJECT: &str = "snapshot.bin";
+    const INDEX_OBJECT: &str = "index.bin";
+
+    pub struct RemoteStore {
+        store: Arc<AmazonS3>,
+        prefix: String,
+    }
+
+    impl RemoteStore {
+        pub fn new(config: &RemoteConfig) -> Result<Self> {
+            // 未配置的凭证回退到 AWS_ACCESS_KEY_ID / AWS_SECRET_ACCESS_KEY 等环境变量
+            let mut builder = AmazonS3Builder::from_env()
+                .with_bucket_name(&config.bucket)
+                .with_region(&config.region)
+                .with_allow_http(config.allow_http);
+            if let Some(endpoint) = &config.endpoint {
+                builder = builder.with_endpoint(endpoint);
+            }
+            if let Some(key) = &config.access_key_id {
+                builder = builder.with_access_key_id(key);
+            }
+            if let Some(secret) = &config.secret_access_key {
+                builder = builder.with_secret_access_key(secret);
+            }
+            let store = builder.build().with_context(|| format!("Invalid remote config for bucket {}", config.bucket))?;
+            Ok(Self { store: Arc::new(store), prefix: config.prefix.clone() })
+        }
+
+        fn object(&self, name: &str) -> ObjectPath {
+            ObjectPath::from(format!("{}{}", self.prefix, name))
+        }
+
+        /// 本地数据库为空时从桶中恢复数据库与索引；返回是否恢复了数据
+        pub async fn hydrate(&self, paths: &PathsConfig) -> Result<bool> {
+            if !is_empty_dir(Path::new(&paths.db))? {
+                info!(path = %paths.db, "💾 Local database present, skipping remote hydration");
+                return Ok(false);
+            }
+
+            let snapshot_file = sibling(&paths.db, "snapshot.download");
+            if !self.download(SNAPSHOT_OBJECT, &snapshot_file).await? {
+                info!(object = %self.object(SNAPSHOT_OBJECT), "☁️  No remote snapshot, starting from the catalog");
+                return Ok(false);
+            }
+            let db_path = paths.db.clone();
+            let restore_file = snapshot_file.clone();
+            let restored = tokio::task::spawn_blocking(move || {
+                let input = std::fs::File::open(&restore_file)?;
+                Storage::restore_snapshot(&db_path, input)
+            }).await?;
+            let _ = std::fs::remove_file(&snapshot_file);
+            let entries = match restored {
+                Ok(entries) => entries,
+                Err(e) => {
+                    // 不留下恢复了一半的数据库，下次启动重新拉取
+                    let _ = std::fs::remove_dir_all(&paths.db);
+                    return Err(e.context("Failed to restore remote snapshot"));
+                }
+            };
+            info!(entries, path = %paths.db, "☁️  Database restored from remote snapshot");
+
+            if self.download(INDEX_OBJECT, Path::new(&paths.index)).await? {
+                info!(path = %paths.index, "☁️  HNSW index downloaded");
+            }
+            Ok(true)
+        }
+
+        /// 写出数据库快照并与 HNSW 索引文件一起上传 (调用方先保存索引)
+        pub async fn upload(&self, storage: Arc<Storage>, paths: &PathsConfig) -> Result<()> {
+            let snapshot_file = sibling(&paths.db, "snapshot.upload");
+            let write_file = snapshot_file.clone();
+            let entries = tokio::task::spawn_blocking(move || {
+                storage.write_snapshot(std::fs::File::create(&write_file)?)
+            }).await??;
+
+            let result = async {
+                self.upload_file(&snapshot_file, SNAPSHOT_OBJECT).await?;
+                self.upload_file(Path::new(&paths.index), INDEX_OBJECT).await
+            }.await;
+            let _ = std::fs::remove_file(&snapshot_file);
+            result?;
+            info!(entries, bucket_prefix = %self.prefix, "☁️  Snapshot and index uploaded");
+            Ok(())
+        }
+
+        /// 下载对象到本地文件 (先写临时文件再改名)；对象不存在时返回 false
+        async fn download(&self, name: &str, to: &Path) -> Result<bool> {
+            let object = self.object(name);
+            let result = match self.store.get(&object).await {
+                Ok(result) => result,
+                Err(object_store::Error::NotFound { .. }) => return Ok(false),
+                Err(e) => return Err(e).with_context(|| format!("Failed to download {}", object)),
+            };
+
+            let partial = to.with_extension("partial");
+            let mut file = tokio::fs::File::create(&partial).await
+                .with_context(|| format!("Failed to create {}", partial.display()))?;
+            let mut stream = result.into_stream();
+            while let Some(chunk) = stream.next().await {
+                let chunk = chunk.with_context(|| format!("Failed to download {}", object))?;
+                file.write_all(&chunk).await?;
+            }
+            file.flush().await?;
+            tokio::fs::rename(&partial, to).await?;
+            Ok(true)
+        }
+
+        /// 分段上传本地文件
+        async fn upload_file(&self, from: &Path, name: &str) -> Result<()> {
+            let object = self.object(name);
+            let mut file = tokio::fs::File::open(from).await
+                .with_context(|| format!("Failed to open {}", from.display()))?;
+            let mut writer = BufWriter::new(Arc::clone(&self.store) as Arc<dyn ObjectStore>, object.clone());
+            tokio::io::copy(&mut file, &mut writer).await
+                .with_context(|| format!("Failed to upload {}", object))?;
+            writer.shutdown().await.with_context(|| format!("Failed to upload {}", object))?;
+            Ok(())
+        }
+    }
+
+    /// 目录不存在或为空
+    fn is_empty_dir(path: &Path) -> Result<bool> {
+        match std::fs::read_dir(path) {
+            Ok(mut entries) => Ok(entries.next().is_none()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(true),
+            Err(e) => Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        }
+    }
+
+    /// 与数据库目录同级的临时文件，例如 `data/db.snapshot.upload`
+    fn sibling(db: &str, suffix: &str) -> PathBuf {
+        PathBuf::from(format!("{}.{}", db.trim_end_matches('/'), suffix))
+    }
+}
+
+// ============================================================================
+// 未启用 s3 feature 时的占位实现
+// ============================================================================
+
+#[cfg(not(feature = "s3"))]
+pub struct RemoteStore {
+    never: std::convert::Infallible,
+}
+
+#[cfg(not(feature = "s3"))]
+impl RemoteStore {
+    pub fn new(_config: &crate::config::RemoteConfig) -> anyhow::Result<Self> {
+        anyhow::bail!("mini-recsys was built without the `s3` feature")
+    }
+
+    pub async fn hydrate(&self, _paths: &crate::config::PathsConfig) -> anyhow::Result<bool> {
+        match self.never {}
+    }
+
+    pub async fn upload(&self, _storage: std::sync::Arc<crate::storage::Storage>, _paths: &crate::config::PathsConfig) -> anyhow::Result<()> {
+        match self.never {}
+    }
+}
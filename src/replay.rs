@@ -0,0 +1,174 @@
+//! 请求重放 - 用当前构建 (及可选的参数覆盖) 重新执行请求日志中的 /recommend 与 /search，
+//! 并与当时返回的结果做对比
+//!
+//! 注意: /recommend 的重放使用用户*当前*的 Bloom Filter，已被标记为看过的物品不会再出现，
+//! 因此即使配置不变，重放结果也可能与历史记录不同。
+
+use crate::ann::HnswIndex;
+use crate::embedding::EmbeddingModel;
+use crate::experiment;
+use crate::hybrid::FusionStrategy;
+use crate::model::{Item, LoggedRequest, RequestRecord, User};
+use crate::service::{self, RankWeights, RecallPolicy, MAX_RECOMMENDATIONS};
+use crate::storage::Storage;
+use crate::text_search::TextSearch;
+use anyhow::Result;
+use std::collections::{HashMap, HashSet};
+
+/// 重放参数
+#[derive(Debug, Clone, Copy)]
+pub struct ReplayOptions {
+    /// 只重放该时间戳 (Unix 毫秒) 之后的请求
+    pub since_ms: u64,
+    /// 最多重放的请求数
+    pub limit: Option<usize>,
+    /// 覆盖 /search 记录中的融合策略
+    pub fusion: Option<FusionStrategy>,
+    /// 覆盖 /recommend 的排序权重；未指定时沿用记录中实验变体的权重
+    pub weights: Option<RankWeights>,
+    /// 打印变化最大的前 N 条请求
+    pub show: usize,
+    /// /recommend 的召回深度与结果数下限 (通常取当前配置)
+    pub recall: RecallPolicy,
+}
+
+impl Default for ReplayOptions {
+    fn default() -> Self {
+        Self {
+            since_ms: 0,
+            limit: None,
+            fusion: None,
+            weights: None,
+            show: 10,
+            recall: RecallPolicy::default(),
+        }
+    }
+}
+
+/// 单条请求的重放对比
+#[derive(Debug)]
+pub struct ReplayDiff {
+    pub record: RequestRecord,
+    pub replayed: Vec<u64>,
+    /// |历史 ∩ 重放| / max(|历史|, |重放|)
+    pub overlap: f64,
+}
+
+/// 重放统计
+#[derive(Debug, Default)]
+pub struct ReplaySummary {
+    pub total: usize,
+    /// 结果列表完全相同 (含顺序)
+    pub identical: usize,
+    /// 用户不存在或模型未加载而无法重放
+    pub skipped: usize,
+    pub mean_overlap: f64,
+}
+
+/// 两个结果列表的重合度
+pub fn overlap(before: &[u64], after: &[u64]) -> f64 {
+    let denom = before.len().max(after.len());
+    if denom == 0 {
+        return 1.0;
+    }
+    let before: HashSet<u64> = before.iter().copied().collect();
+    let common = after.iter().filter(|id| before.contains(id)).count();
+    common as f64 / denom as f64
+}
+
+/// 重放单条请求，无法重放时返回 None
+#[allow(clippy::too_many_arguments)]
+fn replay_one(
+    record: &RequestRecord,
+    storage: &Storage,
+    users: &[User],
+    items: &[Item],
+    item_map: &HashMap<u64, usize>,
+    model: Option<&EmbeddingModel>,
+    text_search: &TextSearch,
+    opts: &ReplayOptions,
+) -> Result<Option<Vec<u64>>> {
+    match &record.request {
+        LoggedRequest::Recommend { uid } => {
+            let Some(user) = users.iter().find(|u| u.id == *uid) else { return Ok(None) };
+            let weights = opts.weights
+                .or_else(|| record.experiment.as_ref().and_then(experiment::weights_for))
+                .unwrap_or_default();
+            let output = service::recommend(storage, &HnswIndex::default(), user, items, item_map, weights, MAX_RECOMMENDATIONS, opts.recall, Vec::new())?;
+            Ok(Some(output.ranked.iter().map(|r| r.item_id).collect()))
+        }
+        LoggedRequest::Search { q, fusion } => {
+            let Some(model) = model else { return Ok(None) };
+            let strategy = opts.fusion.unwrap_or(*fusion);
+            let output = service::hybrid_search(model, text_search, &HnswIndex::default(), items, q, strategy)?;
+            Ok(Some(output.results.iter().map(|r| r.id).collect()))
+        }
+    }
+}
+
+/// 重放请求日志并打印差异
+pub fn run(
+    storage: &Storage,
+    users: &[User],
+    items: &[Item],
+    item_map: &HashMap<u64, usize>,
+    model: Option<&EmbeddingModel>,
+    text_search: &TextSearch,
+    opts: ReplayOptions,
+) -> Result<ReplaySummary> {
+    let mut summary = ReplaySummary::default();
+    let mut diffs: Vec<ReplayDiff> = Vec::new();
+
+    let records = storage.iter_requests_since(opts.since_ms)
+        .take(opts.limit.unwrap_or(usize::MAX));
+    for record in records {
+        let record = record?;
+        let Some(replayed) = replay_one(&record, storage, users, items, item_map, model, text_search, &opts)? else {
+            summary.skipped += 1;
+            continue;
+        };
+
+        summary.total += 1;
+        if replayed == record.item_ids {
+            summary.identical += 1;
+        }
+        let overlap = overlap(&record.item_ids, &replayed);
+        summary.mean_overlap += overlap;
+        diffs.push(ReplayDiff { record, replayed, overlap });
+    }
+
+    if summary.total > 0 {
+        summary.mean_overlap /= summary.total as f64;
+    }
+
+    // 打印变化最大的请求
+    diffs.sort_by(|a, b| a.overlap.total_cmp(&b.overlap));
+    for diff in diffs.iter().filter(|d| d.replayed != d.record.item_ids).take(opts.show) {
+        let before: HashSet<u64> = diff.record.item_ids.iter().copied().collect();
+        let after: HashSet<u64> = diff.replayed.iter().copied().collect();
+        let removed: Vec<u64> = diff.record.item_ids.iter().filter(|id| !after.contains(id)).copied().collect();
+        let added: Vec<u64> = diff.replayed.iter().filter(|id| !before.contains(id)).copied().collect();
+        println!("[{}] {:?}  overlap={:.2}", diff.record.ts_ms, diff.record.request, diff.overlap);
+        println!("    - {:?}", removed);
+        println!("    + {:?}", added);
+    }
+
+    Ok(summary)
+}
+
+// ============================================================================
+// 单元测试
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overlap() {
+        assert_eq!(overlap(&[], &[]), 1.0);
+        assert_eq!(overlap(&[1, 2, 3], &[3, 2, 1]), 1.0);
+        assert_eq!(overlap(&[1, 2, 3, 4], &[1, 2]), 0.5);
+        assert_eq!(overlap(&[1], &[2]), 0.0);
+    }
+}
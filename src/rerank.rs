@@ -0,0 +1,238 @@
+//! 外部重排 - 把排序后的前 K 个候选连同用户 / 查询上下文交给外部模型服务 (LLM、托管的 cross-encoder 等)
+//!
+//! 外部服务返回候选 ID 的新顺序，只调整前 K 个的先后，K 之后的结果保持本地排序。
+//! 超时、出错或返回无法使用的结果时沿用本地排序，请求不会因此失败。
+//!
+//! HTTP 实现 (`HttpReranker`，需要 `rerank` feature) 的协议:
+//!
+//! ```text
+//! POST <rerank.url>
+//! {"context": {"type": "user", "uid": 1, "name": "Alice"} | {"type": "query", "q": "..."},
+//!  "candidates": [{"id": 42, "name": "Desk Lamp", "category": "Home", "score": 0.83}, ...]}
+//!
+//! 200 {"ids": [17, 42, ...]}
+//! ```
+//!
+//! 返回中未知的 ID 被忽略，遗漏的候选按原顺序排在返回的 ID 之后。
+
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::future::Future;
+use std::time::Duration;
+use tracing::warn;
+
+/// 发给外部服务的请求上下文
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum RerankContext {
+    /// /recommend
+    User { uid: u64, name: String },
+    /// /search
+    Query { q: String },
+}
+
+/// 一个待重排的候选
+#[derive(Debug, Clone, Serialize)]
+pub struct RerankCandidate {
+    pub id: u64,
+    pub name: String,
+    pub category: String,
+    /// 本地排序分 (/recommend 为 final_score，/search 为融合分)
+    pub score: f32,
+}
+
+/// 外部重排服务
+pub trait Reranker: Send + Sync {
+    /// 返回候选 ID 的新顺序 (可以只包含部分候选)
+    fn rerank(&self, context: &RerankContext, candidates: &[RerankCandidate]) -> impl Future<Output = Result<Vec<u64>>> + Send;
+}
+
+/// 对 `items` 的前 `top_k` 个调用重排，超过 `timeout` 或出错时原样返回；第二个返回值表示是否应用了重排
+pub async fn rerank_top_k<R, T>(
+    reranker: &R,
+    top_k: usize,
+    timeout: Duration,
+    context: &RerankContext,
+    mut items: Vec<T>,
+    describe: impl Fn(&T) -> RerankCandidate,
+) -> (Vec<T>, bool)
+where
+    R: Reranker,
+{
+    let head_len = top_k.min(items.len());
+    if head_len < 2 {
+        return (items, false);
+    }
+    let candidates: Vec<RerankCandidate> = items[..head_len].iter().map(&describe).collect();
+
+    let order = match tokio::time::timeout(timeout, reranker.rerank(context, &candidates)).await {
+        Ok(Ok(order)) => order,
+        Ok(Err(e)) => {
+            warn!(error = %format!("{:#}", e), "⚠️  Reranker failed, keeping local ranking");
+            return (items, false);
+        }
+        Err(_) => {
+            warn!(timeout_ms = timeout.as_millis() as u64, "⚠️  Reranker timed out, keeping local ranking");
+            return (items, false);
+        }
+    };
+
+    let tail = items.split_off(head_len);
+    let ids: Vec<u64> = candidates.iter().map(|c| c.id).collect();
+    let mut reordered = reorder(items, &ids, &order);
+    reordered.extend(tail);
+    (reordered, true)
+}
+
+/// 按 `order` 重排 `items` (`ids[i]` 为 `items[i]` 的 ID)；未知或重复的 ID 忽略，遗漏的按原顺序补在最后
+fn reorder<T>(items: Vec<T>, ids: &[u64], order: &[u64]) -> Vec<T> {
+    let position: HashMap<u64, usize> = ids.iter().enumerate().map(|(i, &id)| (id, i)).collect();
+    let mut slots: Vec<Option<T>> = items.into_iter().map(Some).collect();
+    let mut reordered = Vec::with_capacity(slots.len());
+    for id in order {
+        if let Some(item) = position.get(id).and_then(|&i| slots[i].take()) {
+            reordered.push(item);
+        }
+    }
+    reordered.extend(slots.into_iter().flatten());
+    reordered
+}
+
+#[cfg(feature = "rerank")]
+pub use http::HttpReranker;
+
+#[cfg(feature = "rerank")]
+mod http {
+    use super::{RerankCandidate, RerankContext, Reranker};
+    use crate::config::RerankConfig;
+    use anyhow::{bail, Context, Result};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize)]
+    struct RerankRequest<'a> {
+        context: &'a RerankContext,
+        candidates: &'a [RerankCandidate],
+    }
+
+    #[derive(Deserialize)]
+    struct RerankResponse {
+        ids: Vec<u64>,
+    }
+
+    /// 通过 HTTP 调用外部重排服务 (协议见模块文档)
+    pub struct HttpReranker {
+        client: reqwest::Client,
+        url: String,
+        bearer_token: Option<String>,
+    }
+
+    impl HttpReranker {
+        pub fn new(config: &RerankConfig) -> Result<Self> {
+            // 整体超时由 `rerank_top_k` 控制，这里只限制建立连接
+            let client = reqwest::Client::builder()
+                .connect_timeout(config.timeout())
+                .build()
+                .context("Failed to create reranker HTTP client")?;
+            Ok(Self { client, url: config.url.clone(), bearer_token: config.bearer_token.clone() })
+        }
+    }
+
+    impl Reranker for HttpReranker {
+        async fn rerank(&self, context: &RerankContext, candidates: &[RerankCandidate]) -> Result<Vec<u64>> {
+            let body = serde_json::to_vec(&RerankRequest { context, candidates })?;
+            let mut request = self.client.post(&self.url)
+                .header("Content-Type", "application/json")
+                .body(body);
+            if let Some(token) = &self.bearer_token {
+                request = request.bearer_auth(token);
+            }
+            let response = request.send().await.with_context(|| format!("Failed to call reranker at {}", self.url))?;
+            let status = response.status();
+            if !status.is_success() {
+                bail!("Reranker returned HTTP {}", status);
+            }
+            let bytes = response.bytes().await.context("Failed to read reranker response")?;
+            let parsed: RerankResponse = serde_json::from_slice(&bytes).context("Reranker response has no ids array")?;
+            Ok(parsed.ids)
+        }
+    }
+}
+
+// ============================================================================
+// 未启用 rerank feature 时的占位实现
+// ============================================================================
+
+#[cfg(not(feature = "rerank"))]
+pub struct HttpReranker {
+    never: std::convert::Infallible,
+}
+
+#[cfg(not(feature = "rerank"))]
+impl HttpReranker {
+    pub fn new(_config: &crate::config::RerankConfig) -> Result<Self> {
+        anyhow::bail!("mini-recsys was built without the `rerank` feature")
+    }
+}
+
+#[cfg(not(feature = "rerank"))]
+impl Reranker for HttpReranker {
+    async fn rerank(&self, _context: &RerankContext, _candidates: &[RerankCandidate]) -> Result<Vec<u64>> {
+        match self.never {}
+    }
+}
+
+// ============================================================================
+// 单元测试
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 把候选倒序返回；`delay` 模拟慢服务
+    struct Reverse {
+        delay: Duration,
+        fail: bool,
+    }
+
+    impl Reranker for Reverse {
+        async fn rerank(&self, _context: &RerankContext, candidates: &[RerankCandidate]) -> Result<Vec<u64>> {
+            tokio::time::sleep(self.delay).await;
+            if self.fail {
+                anyhow::bail!("model overloaded");
+            }
+            Ok(candidates.iter().rev().map(|c| c.id).collect())
+        }
+    }
+
+    fn describe(id: &u64) -> RerankCandidate {
+        RerankCandidate { id: *id, name: String::new(), category: String::new(), score: 0.0 }
+    }
+
+    #[test]
+    fn test_reorder() {
+        let ids = [1, 2, 3, 4];
+        // 未知 ID (9) 与重复 ID 被忽略，遗漏的 2、4 按原顺序补在最后
+        assert_eq!(reorder(ids.to_vec(), &ids, &[3, 9, 1, 3]), vec![3, 1, 2, 4]);
+        assert_eq!(reorder(ids.to_vec(), &ids, &[]), vec![1, 2, 3, 4]);
+    }
+
+    #[tokio::test]
+    async fn test_rerank_top_k_and_fallback() {
+        let context = RerankContext::Query { q: "lamp".into() };
+        let timeout = Duration::from_millis(50);
+        let items = vec![1u64, 2, 3, 4, 5];
+
+        let fast = Reverse { delay: Duration::ZERO, fail: false };
+        let (reranked, applied) = rerank_top_k(&fast, 3, timeout, &context, items.clone(), describe).await;
+        assert!(applied);
+        assert_eq!(reranked, vec![3, 2, 1, 4, 5]);
+
+        let slow = Reverse { delay: Duration::from_secs(5), fail: false };
+        assert_eq!(rerank_top_k(&slow, 3, timeout, &context, items.clone(), describe).await, (items.clone(), false));
+
+        let failing = Reverse { delay: Duration::ZERO, fail: true };
+        assert_eq!(rerank_top_k(&failing, 3, timeout, &context, items.clone(), describe).await, (items, false));
+    }
+}
@@ -0,0 +1,80 @@
+//! 排序分数 - 全序的 f32，排序时不会因 NaN panic
+//!
+//! NaN 分数 (例如零范数向量的相似度) 没有意义，构造 `Score` 时即被拒绝；
+//! `sort_desc` 在排序前移除这些候选并返回数量，由调用方计入 `StageCounts::nan_dropped`。
+
+use std::cmp::{Ordering, Reverse};
+
+/// 非 NaN 的分数，按 `f32::total_cmp` 全序比较 (-0.0 < 0.0，±∞ 为两端)
+#[derive(Debug, Clone, Copy)]
+pub struct Score(f32);
+
+impl Score {
+    /// NaN 时返回 None
+    pub fn new(value: f32) -> Option<Self> {
+        (!value.is_nan()).then_some(Self(value))
+    }
+
+    pub fn get(self) -> f32 {
+        self.0
+    }
+}
+
+impl PartialEq for Score {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Score {}
+
+impl PartialOrd for Score {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Score {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+/// 移除分数为 NaN 的元素，其余按分数从高到低稳定排序；返回移除的数量
+pub fn sort_desc<T>(items: &mut Vec<T>, score: impl Fn(&T) -> f32) -> usize {
+    let before = items.len();
+    items.retain(|item| Score::new(score(item)).is_some());
+    items.sort_by_key(|item| Reverse(Score(score(item))));
+    before - items.len()
+}
+
+// ============================================================================
+// 单元测试
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_total_order() {
+        assert!(Score::new(f32::NAN).is_none());
+        let mut scores: Vec<Score> = [0.5, f32::INFINITY, -0.0, 0.0, f32::NEG_INFINITY]
+            .into_iter()
+            .filter_map(Score::new)
+            .collect();
+        scores.sort();
+        let values: Vec<f32> = scores.iter().map(|s| s.get()).collect();
+        assert_eq!(values, vec![f32::NEG_INFINITY, -0.0, 0.0, 0.5, f32::INFINITY]);
+        assert!(values[1].is_sign_negative());
+    }
+
+    #[test]
+    fn test_sort_desc_drops_nan() {
+        let mut items = vec![(1, 0.2), (2, f32::NAN), (3, 0.9), (4, 0.2), (5, f32::NAN)];
+        assert_eq!(sort_desc(&mut items, |&(_, s)| s), 2);
+        let ids: Vec<u64> = items.iter().map(|&(id, _)| id).collect();
+        // 同分保持原顺序
+        assert_eq!(ids, vec![3, 1, 4]);
+    }
+}
@@ -0,0 +1,391 @@
+//! 业务逻辑 - 推荐 (Recall -> Filter -> Rank) 与混合搜索流程
+//!
+//! HTTP 层、离线评估与请求重放共用这一套流程，保证离线结果反映线上行为。
+
+use crate::ann::{self, AnnIndex, BruteForce};
+use crate::embedding::EmbeddingModel;
+use crate::hybrid::{self, FusionStrategy, RankedSource, SearchResult, SourceLabel};
+use crate::lookalike;
+use crate::model::{Item, User};
+use crate::score;
+use crate::storage::Storage;
+use crate::text_search::TextSearch;
+use anyhow::{Context, Result};
+use fastbloom_rs::Membership;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Instant;
+use tracing::debug_span;
+
+/// 召回阶段的默认候选数量 (`recall.k`)
+pub const RECALL_K: usize = 100;
+/// 结果不足时触发热门降级填充的默认阈值 (`recall.min_results`)
+pub const MIN_RECOMMENDATIONS: usize = 5;
+/// 召回深度的默认上限 (`recall.max_k`)
+pub const MAX_RECALL_K: usize = 1000;
+/// 单次返回的最大推荐数
+pub const MAX_RECOMMENDATIONS: usize = 10;
+/// /search 每一路召回的候选数量
+pub const SEARCH_RECALL_K: usize = 50;
+/// /search 返回的最大结果数
+pub const MAX_SEARCH_RESULTS: usize = 20;
+
+/// 排序权重: final_score = sim_score * sim + popularity * popularity
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RankWeights {
+    pub sim: f32,
+    pub popularity: f32,
+}
+
+impl Default for RankWeights {
+    fn default() -> Self {
+        Self { sim: 0.7, popularity: 0.3 }
+    }
+}
+
+/// /recommend 的召回深度与结果数下限 (`[recall]` 配置，可按请求覆盖)
+///
+/// 召回后的过滤 (已看过滤器、已不在目录中的物品) 剩下的候选不足 `limit` 个时，
+/// 按过滤掉的比例扩大召回深度重新召回，直到 `max_k`。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RecallPolicy {
+    /// 召回的候选数量
+    pub k: usize,
+    /// 自动扩大召回深度 (以及按请求覆盖 k) 的上限；等于 k 时不扩大
+    pub max_k: usize,
+    /// 结果少于该值时从热门物品中补充
+    pub min_results: usize,
+}
+
+impl Default for RecallPolicy {
+    fn default() -> Self {
+        Self { k: RECALL_K, max_k: MAX_RECALL_K, min_results: MIN_RECOMMENDATIONS }
+    }
+}
+
+impl RecallPolicy {
+    /// 召回 `k` 个、过滤后剩 `surviving` 个，不足 `limit` 时下一轮的召回深度 (按存活比例估算，至少翻倍)；
+    /// 已到 `max_k` 时返回 None
+    pub fn next_k(&self, k: usize, surviving: usize, limit: usize) -> Option<usize> {
+        if surviving >= limit || k >= self.max_k {
+            return None;
+        }
+        let estimated = k.saturating_mul(limit).div_ceil(surviving.max(1));
+        Some(estimated.max(k.saturating_mul(2)).min(self.max_k))
+    }
+}
+
+/// 排序后的单个物品
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScoredItem {
+    pub item_id: u64,
+    pub sim_score: f32,
+    pub popularity: f32,
+    pub final_score: f32,
+}
+
+/// 各阶段耗时 (毫秒)，未经过的阶段为 0
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct StageTimings {
+    /// 等待阻塞线程池空闲的时间 (HTTP 处理器把流水线放到 spawn_blocking 中执行)
+    pub queue_ms: f64,
+    pub encode_ms: f64,
+    pub ann_ms: f64,
+    pub filter_ms: f64,
+    pub rank_ms: f64,
+    /// 外部重排 (配置了 `[rerank]` 时)
+    pub rerank_ms: f64,
+    pub total_ms: f64,
+}
+
+/// 各阶段的候选数量
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct StageCounts {
+    /// ANN 召回数量
+    pub ann: usize,
+    /// 最终的召回深度 (仅 /recommend，过滤掉的候选过多时会自动扩大)
+    #[serde(skip_serializing_if = "is_zero")]
+    pub recall_k: usize,
+    /// 只由相似用户召回的数量 (仅 /recommend，未启用或没有相似用户的历史时为 None)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lookalike: Option<usize>,
+    /// 关键词召回数量 (仅 /search)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keyword: Option<usize>,
+    /// 过滤已看后的数量 (仅 /recommend)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub after_filter: Option<usize>,
+    /// 融合后的数量 (仅 /search)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fused: Option<usize>,
+    /// 触发热门降级时补充的数量 (仅 /recommend，未触发时为 None)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fallback: Option<usize>,
+    /// 分数为 NaN 而被丢弃的候选数
+    #[serde(skip_serializing_if = "is_zero")]
+    pub nan_dropped: usize,
+    /// ANN 索引不可用，召回降级为暴力检索
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub degraded: bool,
+    /// 因近期曝光被压低分数的候选数 (仅 /recommend)
+    #[serde(skip_serializing_if = "is_zero")]
+    pub penalized: usize,
+    pub returned: usize,
+}
+
+fn is_zero(n: &usize) -> bool {
+    *n == 0
+}
+
+/// 毫秒 (保留小数)
+pub fn elapsed_ms(start: Instant) -> f64 {
+    start.elapsed().as_secs_f64() * 1000.0
+}
+
+/// 排序输出
+pub struct RankOutput {
+    pub ranked: Vec<ScoredItem>,
+    /// 被 `is_seen` 过滤掉的候选数量
+    pub filtered_count: usize,
+    pub timings: StageTimings,
+    pub counts: StageCounts,
+}
+
+/// 搜索输出
+pub struct SearchOutput {
+    pub results: Vec<SearchResult>,
+    pub timings: StageTimings,
+    pub counts: StageCounts,
+}
+
+/// 对召回候选执行 过滤 -> 打分 -> 排序 -> 降级填充 -> 截断
+///
+/// # Arguments
+/// * `candidates` - 召回结果 (item_id, similarity)
+/// * `is_seen` - 返回 true 的物品会被过滤 (例如 Bloom Filter 命中)
+/// * `min_results` - 结果少于该值时从热门物品中补充
+/// * `limit` - 最终返回数量上限
+pub fn rank_candidates(
+    candidates: Vec<(u64, f32)>,
+    items: &[Item],
+    item_map: &HashMap<u64, usize>,
+    is_seen: impl Fn(u64) -> bool,
+    weights: RankWeights,
+    min_results: usize,
+    limit: usize,
+) -> RankOutput {
+    let mut timings = StageTimings::default();
+    let mut counts = StageCounts { ann: candidates.len(), ..StageCounts::default() };
+
+    // Step 1: 过滤已看过的商品
+    let stage = Instant::now();
+    let mut filtered_count = 0;
+    let unseen: Vec<(u64, f32)> = {
+        let _span = debug_span!("bloom_filter", candidates = candidates.len()).entered();
+        candidates.into_iter()
+            .filter(|&(item_id, _)| {
+                let seen = is_seen(item_id);
+                if seen {
+                    filtered_count += 1;
+                }
+                !seen
+            })
+            .collect()
+    };
+
+    timings.filter_ms = elapsed_ms(stage);
+    counts.after_filter = Some(unseen.len());
+
+    // Step 2: 打分并排序
+    let stage = Instant::now();
+    let mut ranked: Vec<ScoredItem> = {
+        let _span = debug_span!("rank", candidates = unseen.len()).entered();
+        let mut ranked: Vec<ScoredItem> = unseen.into_iter()
+            .filter_map(|(item_id, sim_score)| {
+                let idx = *item_map.get(&item_id)?;
+                let item = &items[idx];
+                Some(ScoredItem {
+                    item_id,
+                    sim_score,
+                    popularity: item.popularity,
+                    final_score: sim_score * weights.sim + item.popularity * weights.popularity,
+                })
+            })
+            .collect();
+        counts.nan_dropped += score::sort_desc(&mut ranked, |r| r.final_score);
+        ranked
+    };
+
+    // Step 3: 降级填充 (Fallback)
+    if ranked.len() < min_results {
+        let before_fallback = ranked.len();
+        let _span = debug_span!("fallback", missing = min_results - ranked.len()).entered();
+        // 从热门商品中补充
+        let mut popular_items: Vec<_> = items.iter()
+            .filter(|item| !is_seen(item.id))
+            .collect();
+        counts.nan_dropped += score::sort_desc(&mut popular_items, |item| item.popularity);
+
+        for item in popular_items.into_iter().take(min_results - ranked.len()) {
+            if !ranked.iter().any(|r| r.item_id == item.id) {
+                ranked.push(ScoredItem {
+                    item_id: item.id,
+                    sim_score: 0.0,
+                    popularity: item.popularity,
+                    final_score: item.popularity * weights.popularity,
+                });
+            }
+        }
+        counts.fallback = Some(ranked.len() - before_fallback);
+    }
+
+    ranked.truncate(limit);
+    timings.rank_ms = elapsed_ms(stage);
+    counts.returned = ranked.len();
+
+    RankOutput { ranked, filtered_count, timings, counts }
+}
+
+/// 完整推荐流程: HNSW 召回 (+ 相似用户召回) -> Bloom Filter 过滤 -> 排序 -> 降级填充
+///
+/// 最多返回 `limit` 个结果 (通常为 `MAX_RECOMMENDATIONS`，翻页时更多)。
+/// 召回 `policy.k` 个候选 (limit 更大时召回 limit 个)，过滤后不足 `limit` 个时自动扩大召回深度 (见 `RecallPolicy`)。
+/// `index` 为空或搜索出错时对 `items` 暴力召回 (见 `ann.rs`)；
+/// `lookalike` 为相似用户召回的候选 (见 `lookalike.rs`)，与 ANN 召回合并后一起排序
+#[allow(clippy::too_many_arguments)]
+pub fn recommend(
+    storage: &Storage,
+    index: &impl AnnIndex,
+    user: &User,
+    items: &[Item],
+    item_map: &HashMap<u64, usize>,
+    weights: RankWeights,
+    limit: usize,
+    policy: RecallPolicy,
+    lookalike: Vec<(u64, f32)>,
+) -> Result<RankOutput> {
+    let start = Instant::now();
+
+    // Step A: 获取用户的 Bloom Filter
+    let filter = debug_span!("load_filter", uid = user.id)
+        .in_scope(|| storage.get_user_filter(user.id))
+        .context("Failed to get filter")?;
+    let is_seen = |item_id: u64| filter.contains(&item_id.to_le_bytes());
+    let load_filter_ms = elapsed_ms(start);
+
+    // Step B: 召回 Top-K；过滤后不足 limit 个且索引中还有更多候选时扩大深度重新召回
+    // (与 rank_candidates 相同的过滤条件: 未看过且仍在目录中)
+    let survives = |item_id: u64| !is_seen(item_id) && item_map.contains_key(&item_id);
+    let stage = Instant::now();
+    let mut k = policy.k.max(limit);
+    let mut recall = loop {
+        let recall = debug_span!("recall", k)
+            .in_scope(|| ann::recall(index, &BruteForce::new(items), &user.embedding, k));
+        let surviving = recall.candidates.iter().filter(|&&(item_id, _)| survives(item_id)).count();
+        match policy.next_k(k, surviving, limit) {
+            Some(next) if recall.candidates.len() >= k => k = next,
+            _ => break recall,
+        }
+    };
+    let ann_count = recall.candidates.len();
+    let lookalike_count = (!lookalike.is_empty()).then(|| lookalike::merge(&mut recall.candidates, lookalike));
+    let ann_ms = elapsed_ms(stage);
+
+    // Step C: 过滤已看过的商品 + 排序 + 降级填充
+    let mut output = rank_candidates(
+        recall.candidates,
+        items,
+        item_map,
+        is_seen,
+        weights,
+        policy.min_results,
+        limit,
+    );
+    output.counts.ann = ann_count;
+    output.counts.recall_k = k;
+    output.counts.lookalike = lookalike_count;
+    output.counts.degraded = recall.degraded;
+    output.timings.ann_ms = ann_ms;
+    output.timings.filter_ms += load_filter_ms;
+    output.timings.total_ms = elapsed_ms(start);
+    Ok(output)
+}
+
+/// 混合搜索: 向量召回 + 关键词召回 -> 融合 -> 截断
+///
+/// 向量召回与 `recommend` 一样在 `index` 不可用时降级为对 `items` 的暴力检索
+pub fn hybrid_search(
+    model: &EmbeddingModel,
+    text_search: &TextSearch,
+    index: &impl AnnIndex,
+    items: &[Item],
+    query: &str,
+    strategy: FusionStrategy,
+) -> Result<SearchOutput> {
+    let start = Instant::now();
+    let mut timings = StageTimings::default();
+    let mut counts = StageCounts::default();
+
+    // 1. Semantic Search (Vector)
+    let query_vec = model.encode(query).context("Encoding failed")?;
+    timings.encode_ms = elapsed_ms(start);
+
+    let stage = Instant::now();
+    let recall = debug_span!("recall", k = SEARCH_RECALL_K)
+        .in_scope(|| ann::recall(index, &BruteForce::new(items), &query_vec, SEARCH_RECALL_K));
+    let mut vec_results = recall.candidates;
+    counts.degraded = recall.degraded;
+    timings.ann_ms = elapsed_ms(stage);
+    counts.ann = vec_results.len();
+
+    // 2. Keyword Search (Tantivy)
+    let stage = Instant::now();
+    let mut kw_results = debug_span!("keyword_search", k = SEARCH_RECALL_K)
+        .in_scope(|| text_search.search(query, SEARCH_RECALL_K))
+        .context("Text search failed")?;
+    counts.keyword = Some(kw_results.len());
+
+    // NaN 分数会污染 min-max / z-score 归一化，融合前先丢弃
+    counts.nan_dropped = score::sort_desc(&mut vec_results, |&(_, s)| s) + score::sort_desc(&mut kw_results, |&(_, s)| s);
+
+    // 3. Fusion (关键词检索与融合都计入 rank_ms)
+    let mut merged = hybrid::fuse(&[
+        RankedSource::new(SourceLabel::Vector, vec_results),
+        RankedSource::new(SourceLabel::Keyword, kw_results),
+    ], strategy);
+    counts.fused = Some(merged.len());
+    merged.truncate(MAX_SEARCH_RESULTS);
+    timings.rank_ms = elapsed_ms(stage);
+    counts.returned = merged.len();
+    timings.total_ms = elapsed_ms(start);
+
+    Ok(SearchOutput { results: merged, timings, counts })
+}
+
+// ============================================================================
+// 单元测试
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recall_policy_next_k() {
+        let policy = RecallPolicy::default();
+        // 过滤后仍够 limit 个: 不扩大
+        assert_eq!(policy.next_k(100, 10, 10), None);
+        // 存活 5% -> 估算 200，至少翻倍
+        assert_eq!(policy.next_k(100, 5, 10), Some(200));
+        // 存活 1% -> 估算 1000
+        assert_eq!(policy.next_k(100, 1, 10), Some(1000));
+        // 全部被过滤时直接扩到上限；已到上限时停止
+        assert_eq!(policy.next_k(100, 0, 50), Some(1000));
+        assert_eq!(policy.next_k(1000, 0, 10), None);
+
+        let fixed = RecallPolicy { max_k: 100, ..RecallPolicy::default() };
+        assert_eq!(fixed.next_k(100, 0, 10), None);
+    }
+}
@@ -0,0 +1,121 @@
+//! 用户行为模拟器 - 生成带类别偏好的合成用户，并让其与推荐流水线交互
+//!
+//! 每个会话: 请求推荐 -> 逐条曝光 (View) -> 按偏好概率点击 (Click) -> 标记已看。
+//! 产生的事件写入交互日志，可直接用于离线评估或压测。
+
+use crate::ffi::hnsw_search;
+use crate::model::{demo_rng, generate_user_embedding, EventKind, InteractionEvent, Item, User, CATEGORIES};
+use crate::service::{self, RankWeights, MAX_RECOMMENDATIONS, MIN_RECOMMENDATIONS, RECALL_K};
+use crate::storage::Storage;
+use anyhow::Result;
+use fastbloom_rs::Membership;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use std::collections::HashMap;
+use std::time::Instant;
+
+/// 模拟参数
+#[derive(Debug, Clone, Copy)]
+pub struct SimOptions {
+    /// 生成的合成用户数
+    pub users: usize,
+    /// 每个用户的会话数
+    pub sessions: usize,
+    /// 偏好类别物品的点击概率
+    pub click_prob_preferred: f32,
+    /// 其他类别物品的点击概率
+    pub click_prob_other: f32,
+    /// 随机种子，指定后偏好与点击序列可复现
+    pub seed: Option<u64>,
+}
+
+impl Default for SimOptions {
+    fn default() -> Self {
+        Self {
+            users: 50,
+            sessions: 5,
+            click_prob_preferred: 0.3,
+            click_prob_other: 0.02,
+            seed: None,
+        }
+    }
+}
+
+/// 模拟统计
+#[derive(Debug, Default)]
+pub struct SimSummary {
+    pub users: usize,
+    pub sessions: usize,
+    pub views: usize,
+    pub clicks: usize,
+    pub elapsed_ms: u128,
+}
+
+/// 创建合成用户并执行模拟会话
+///
+/// 合成用户的 id 从当前最大 uid + 1 开始分配，并持久化到 Sled，
+/// 下次启动服务时即可在 /users 中看到。
+pub fn run(
+    storage: &Storage,
+    existing_users: &[User],
+    items: &[Item],
+    item_map: &HashMap<u64, usize>,
+    opts: SimOptions,
+) -> Result<SimSummary> {
+    let mut rng = demo_rng(opts.seed);
+    let start = Instant::now();
+    let mut summary = SimSummary::default();
+
+    let first_uid = existing_users.iter().map(|u| u.id).max().unwrap_or(0) + 1;
+
+    for i in 0..opts.users {
+        // Step 1: 随机 1~2 个偏好类别
+        let n_prefs = rng.gen_range(1..=2);
+        let prefs: Vec<&str> = CATEGORIES.choose_multiple(&mut rng, n_prefs).copied().collect();
+        let user = User {
+            id: first_uid + i as u64,
+            name: format!("模拟用户 {} ({})", first_uid + i as u64, prefs.join(" + ")),
+            embedding: generate_user_embedding(&prefs, &mut rng),
+        };
+        storage.save_user(&user)?;
+        summary.users += 1;
+
+        // Step 2: 模拟会话
+        for _ in 0..opts.sessions {
+            let mut filter = storage.get_user_filter(user.id)?;
+            let output = service::rank_candidates(
+                hnsw_search(&user.embedding, RECALL_K),
+                items,
+                item_map,
+                |item_id| filter.contains(&item_id.to_le_bytes()),
+                RankWeights::default(),
+                MIN_RECOMMENDATIONS,
+                MAX_RECOMMENDATIONS,
+            );
+
+            for scored in &output.ranked {
+                let Some(&idx) = item_map.get(&scored.item_id) else { continue };
+                storage.append_event(&InteractionEvent::new(user.id, scored.item_id, EventKind::View))?;
+                summary.views += 1;
+
+                let click_prob = if prefs.contains(&items[idx].category.as_str()) {
+                    opts.click_prob_preferred
+                } else {
+                    opts.click_prob_other
+                };
+                if rng.gen::<f32>() < click_prob {
+                    storage.append_event(&InteractionEvent::new(user.id, scored.item_id, EventKind::Click))?;
+                    summary.clicks += 1;
+                }
+
+                // 与前端行为一致: 返回过的物品都标记为已看
+                filter.add(&scored.item_id.to_le_bytes());
+            }
+            storage.save_user_filter(user.id, &filter)?;
+            summary.sessions += 1;
+        }
+    }
+
+    summary.elapsed_ms = start.elapsed().as_millis();
+    Ok(summary)
+}
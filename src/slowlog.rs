@@ -0,0 +1,135 @@
+//! 慢查询日志 - 超过延迟阈值的请求完整写入独立的 JSONL 文件
+//!
+//! 与请求分析日志不同，这里保留排查所需的全部细节 (查询文本、uid、各阶段耗时与候选数量)，
+//! 并且只记录长尾请求，便于直接定位慢在 ONNX 编码、ANN 还是排序。
+
+use crate::hybrid::FusionStrategy;
+use crate::model::ExperimentTag;
+use crate::service::{StageCounts, StageTimings};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::{LineWriter, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use tracing::warn;
+
+/// 慢查询阈值 (毫秒)
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SlowQueryThresholds {
+    pub recommend_ms: f64,
+    pub search_ms: f64,
+}
+
+impl Default for SlowQueryThresholds {
+    fn default() -> Self {
+        Self { recommend_ms: 50.0, search_ms: 200.0 }
+    }
+}
+
+/// 慢查询的请求参数
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SlowRequest {
+    Recommend { uid: u64, experiment: Option<ExperimentTag> },
+    Search { q: String, fusion: FusionStrategy },
+}
+
+/// 一条慢查询记录
+#[derive(Debug, Clone, Serialize)]
+pub struct SlowQueryEntry {
+    pub ts_ms: u64,
+    pub request: SlowRequest,
+    pub threshold_ms: f64,
+    pub timing: StageTimings,
+    pub counts: StageCounts,
+}
+
+/// 慢查询日志
+pub struct SlowQueryLog {
+    thresholds: SlowQueryThresholds,
+    sink: Mutex<LineWriter<File>>,
+}
+
+impl SlowQueryLog {
+    /// 以追加模式打开日志文件
+    pub fn open(path: impl AsRef<Path>, thresholds: SlowQueryThresholds) -> Result<Self> {
+        let path = path.as_ref();
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Failed to open slow query log {}", path.display()))?;
+        Ok(Self { thresholds, sink: Mutex::new(LineWriter::new(file)) })
+    }
+
+    pub fn thresholds(&self) -> SlowQueryThresholds {
+        self.thresholds
+    }
+
+    /// 该请求对应的阈值
+    pub fn threshold_for(&self, request: &SlowRequest) -> f64 {
+        match request {
+            SlowRequest::Recommend { .. } => self.thresholds.recommend_ms,
+            SlowRequest::Search { .. } => self.thresholds.search_ms,
+        }
+    }
+
+    /// 若 total_ms 超过阈值则写入一条记录，返回是否写入；写入失败只打印警告
+    pub fn record(&self, request: SlowRequest, timing: &StageTimings, counts: &StageCounts) -> bool {
+        let threshold_ms = self.threshold_for(&request);
+        if timing.total_ms <= threshold_ms {
+            return false;
+        }
+
+        warn!(total_ms = timing.total_ms, threshold_ms, request = ?request, "🐢 Slow query");
+        let entry = SlowQueryEntry {
+            ts_ms: crate::model::now_ms(),
+            request,
+            threshold_ms,
+            timing: timing.clone(),
+            counts: counts.clone(),
+        };
+        let mut sink = self.sink.lock().unwrap_or_else(|e| e.into_inner());
+        let result = serde_json::to_writer(&mut *sink, &entry)
+            .map_err(anyhow::Error::from)
+            .and_then(|_| sink.write_all(b"\n").map_err(anyhow::Error::from));
+        if let Err(e) = result {
+            warn!(error = %e, "Failed to write slow query log");
+        }
+        true
+    }
+}
+
+// ============================================================================
+// 单元测试
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_requests_over_threshold_are_written() {
+        let path = std::env::temp_dir().join(format!("mini_recsys_slowlog_{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let log = SlowQueryLog::open(&path, SlowQueryThresholds { recommend_ms: 10.0, search_ms: 100.0 }).unwrap();
+
+        let timing = |total_ms| StageTimings { total_ms, ..StageTimings::default() };
+        let counts = StageCounts::default();
+        let search = || SlowRequest::Search { q: "phone".into(), fusion: FusionStrategy::Rrf };
+
+        assert!(!log.record(SlowRequest::Recommend { uid: 1, experiment: None }, &timing(5.0), &counts));
+        assert!(log.record(SlowRequest::Recommend { uid: 1, experiment: None }, &timing(20.0), &counts));
+        assert!(!log.record(search(), &timing(20.0), &counts));
+        assert!(log.record(search(), &timing(150.0), &counts));
+        drop(log);
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = content.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[1].contains("\"q\":\"phone\""));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,35 +1,89 @@
 //! 存储层 - Sled 嵌入式数据库封装
 
-use anyhow::{Context, Result};
-use fastbloom_rs::{BloomFilter, FilterBuilder, Membership};
+use anyhow::{anyhow, bail, Context, Result};
+use fastbloom_rs::{BloomFilter, FilterBuilder};
+use serde::{Deserialize, Serialize};
+use sled::transaction::{ConflictableTransactionError, Transactional};
 use sled::{Db, Tree};
-use crate::model::{User, Item};
+use crate::model::{now_ms, InteractionEvent, RequestRecord, Titles, User, Item};
+use crate::popularity::ItemPopularity;
+use std::io::{BufReader, BufWriter, Read, Write};
 
-/// Bloom Filter 参数
-const BLOOM_EXPECTED_ITEMS: u32 = 10000;
-const BLOOM_FPR: f64 = 0.01;
-/// 固定 hash 函数数量 (根据 expected_items 和 fpr 计算: k = -ln(fpr) / ln(2) ≈ 7)
-const BLOOM_HASHES: u32 = 7;
+/// Bloom Filter 参数 (只影响新建的过滤器；已保存的过滤器按原大小还原)
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BloomConfig {
+    pub expected_items: u64,
+    pub fpr: f64,
+    /// hash 函数数量 (根据 expected_items 和 fpr 计算: k = -ln(fpr) / ln(2) ≈ 7)
+    /// 注意: 修改后已保存的过滤器将无法正确还原
+    pub hashes: u32,
+    /// 过滤器超过该天数没有写入时由 `history_prune` 任务删除，用户重新可以看到其中的物品；0 表示永久保留
+    #[serde(default)]
+    pub ttl_days: u64,
+}
+
+impl Default for BloomConfig {
+    fn default() -> Self {
+        Self { expected_items: 10000, fpr: 0.01, hashes: 7, ttl_days: 0 }
+    }
+}
+
+/// 快照文件头 (格式变化时递增版本号)
+const SNAPSHOT_MAGIC: &[u8; 8] = b"MRSNAP1\n";
+
+/// 快照中的一条记录: 先写 Tree 名，随后是该 Tree 的全部键值对
+#[derive(Serialize, Deserialize)]
+enum SnapshotRecord {
+    Tree(Vec<u8>),
+    Entry(Vec<u8>, Vec<u8>),
+    End,
+}
 
 pub struct Storage {
-    _db: Db,
+    db: Db,
     users_tree: Tree,
     items_tree: Tree,
+    /// 物品 ID -> 其他语言的标题 (没有译名的物品不写入)
+    titles_tree: Tree,
+    /// 物品 ID -> 曝光、点击与热门度 (由 `save_popularity` 整体写入，覆盖物品记录中的 popularity)
+    popularity_tree: Tree,
     history_tree: Tree,
+    /// 用户 ID -> 已看过滤器最后写入的 Unix 毫秒时间戳 (BE)
+    history_updated_tree: Tree,
+    events_tree: Tree,
+    requests_tree: Tree,
+    bloom: BloomConfig,
 }
 
 impl Storage {
     pub fn new(path: &str) -> Result<Self> {
+        Self::open(path, BloomConfig::default())
+    }
+
+    pub fn open(path: &str, bloom: BloomConfig) -> Result<Self> {
         let db = sled::open(path).context("Failed to open sled database")?;
         let users_tree = db.open_tree("users").context("Failed to open users tree")?;
         let items_tree = db.open_tree("items").context("Failed to open items tree")?;
+        let titles_tree = db.open_tree("item_titles").context("Failed to open item titles tree")?;
+        let popularity_tree = db.open_tree("item_popularity").context("Failed to open item popularity tree")?;
         let history_tree = db.open_tree("history").context("Failed to open history tree")?;
+        let history_updated_tree = db.open_tree("history_updated").context("Failed to open history timestamps tree")?;
+        let events_tree = db.open_tree("events").context("Failed to open events tree")?;
+        // 旧版 "requests" Tree 的记录缺少 scores / latency_ms，无法再反序列化，故换用新 Tree
+        let requests_tree = db.open_tree("request_log").context("Failed to open request log tree")?;
         
         Ok(Self {
-            _db: db,
+            db,
             users_tree,
             items_tree,
+            titles_tree,
+            popularity_tree,
             history_tree,
+            history_updated_tree,
+            events_tree,
+            requests_tree,
+            bloom,
         })
     }
 
@@ -73,27 +127,61 @@ impl Storage {
         let key = Self::u64_to_key(item.id);
         let value = bincode::serialize(item).context("Failed to serialize item")?;
         self.items_tree.insert(key, value).context("Failed to insert item")?;
+        if item.titles.is_empty() {
+            self.titles_tree.remove(key).context("Failed to remove item titles")?;
+        } else {
+            let titles = bincode::serialize(&item.titles).context("Failed to serialize item titles")?;
+            self.titles_tree.insert(key, titles).context("Failed to insert item titles")?;
+        }
         Ok(())
     }
 
     pub fn get_item(&self, id: u64) -> Result<Option<Item>> {
         let key = Self::u64_to_key(id);
         match self.items_tree.get(key).context("Failed to get item")? {
-            Some(bytes) => {
-                let item = bincode::deserialize(&bytes).context("Failed to deserialize item")?;
-                Ok(Some(item))
-            }
+            Some(bytes) => self.decode_item(&key, &bytes).map(Some),
             None => Ok(None),
         }
     }
 
     pub fn iter_items(&self) -> impl Iterator<Item = Result<Item>> + '_ {
         self.items_tree.iter().map(|result| {
-            let (_, value) = result.context("Failed to iterate items")?;
-            bincode::deserialize(&value).context("Failed to deserialize item")
+            let (key, value) = result.context("Failed to iterate items")?;
+            self.decode_item(&key, &value)
         })
     }
 
+    /// 反序列化物品记录并补上其他语言的标题与最近一次计算的热门度
+    fn decode_item(&self, key: &[u8], bytes: &[u8]) -> Result<Item> {
+        let mut item: Item = bincode::deserialize(bytes).context("Failed to deserialize item")?;
+        if let Some(titles) = self.titles_tree.get(key).context("Failed to get item titles")? {
+            item.titles = bincode::deserialize::<Titles>(&titles).context("Failed to deserialize item titles")?;
+        }
+        if let Some(popularity) = self.get_popularity(item.id)? {
+            item.popularity = popularity.score;
+        }
+        Ok(item)
+    }
+
+    // ========== 热门度 ==========
+
+    /// 在一个批次中写入各物品的热门度
+    pub fn save_popularity(&self, entries: &[(u64, ItemPopularity)]) -> Result<()> {
+        let mut batch = sled::Batch::default();
+        for (id, popularity) in entries {
+            let value = bincode::serialize(popularity).context("Failed to serialize item popularity")?;
+            batch.insert(&Self::u64_to_key(*id), value);
+        }
+        self.popularity_tree.apply_batch(batch).context("Failed to save item popularity")
+    }
+
+    pub fn get_popularity(&self, id: u64) -> Result<Option<ItemPopularity>> {
+        match self.popularity_tree.get(Self::u64_to_key(id)).context("Failed to get item popularity")? {
+            Some(bytes) => Ok(Some(bincode::deserialize(&bytes).context("Failed to deserialize item popularity")?)),
+            None => Ok(None),
+        }
+    }
+
     pub fn items_count(&self) -> usize {
         self.items_tree.len()
     }
@@ -105,8 +193,8 @@ impl Storage {
     // ========== Bloom Filter (用户历史去重) ==========
 
     /// 创建一个新的空 Bloom Filter
-    fn new_bloom_filter() -> BloomFilter {
-        FilterBuilder::new(BLOOM_EXPECTED_ITEMS as u64, BLOOM_FPR).build_bloom_filter()
+    fn new_bloom_filter(&self) -> BloomFilter {
+        FilterBuilder::new(self.bloom.expected_items, self.bloom.fpr).build_bloom_filter()
     }
 
     /// 获取用户的 Bloom Filter（若不存在则返回新空过滤器）
@@ -115,9 +203,9 @@ impl Storage {
         match self.history_tree.get(key).context("Failed to get history")? {
             Some(bytes) => {
                 // 从字节数组还原 BloomFilter
-                Ok(BloomFilter::from_u8_array(&bytes, BLOOM_HASHES))
+                Ok(BloomFilter::from_u8_array(&bytes, self.bloom.hashes))
             }
-            None => Ok(Self::new_bloom_filter()),
+            None => Ok(self.new_bloom_filter()),
         }
     }
 
@@ -125,15 +213,279 @@ impl Storage {
     pub fn save_user_filter(&self, uid: u64, filter: &BloomFilter) -> Result<()> {
         let key = Self::u64_to_key(uid);
         let bytes = filter.get_u8_array();
-        self.history_tree.insert(key, bytes).context("Failed to save history")?;
+        let now = now_ms().to_be_bytes();
+        // 过滤器与写入时间在同一事务中更新，与 `prune_user_filters_before` 互斥
+        (&self.history_tree, &self.history_updated_tree)
+            .transaction(|(history, updated)| {
+                history.insert(&key, bytes)?;
+                updated.insert(&key, &now)?;
+                Ok::<_, ConflictableTransactionError<String>>(())
+            })
+            .map_err(|e| anyhow!("Failed to save history: {}", e))
+    }
+
+    /// 删除最后写入早于 `before_ms` 的已看过滤器，返回删除数
+    ///
+    /// 没有写入时间的过滤器 (升级前保存的) 记为本次运行的时间，一个 TTL 之后再过期。
+    /// 每个用户的检查与删除在一个事务中完成，期间写入的 `mark_seen` 不会被一起删掉。
+    pub fn prune_user_filters_before(&self, before_ms: u64) -> Result<usize> {
+        let now = now_ms().to_be_bytes();
+        let mut removed = 0;
+        for result in self.history_tree.iter() {
+            let (key, _) = result.context("Failed to iterate history")?;
+            let expired = (&self.history_tree, &self.history_updated_tree)
+                .transaction(|(history, updated)| {
+                    let updated_ms = match updated.get(&key)? {
+                        Some(bytes) => {
+                            let bytes: [u8; 8] = bytes.as_ref().try_into()
+                                .map_err(|_| ConflictableTransactionError::Abort("Invalid history timestamp".to_string()))?;
+                            u64::from_be_bytes(bytes)
+                        }
+                        None => {
+                            if history.get(&key)?.is_some() {
+                                updated.insert(&key, &now)?;
+                            }
+                            return Ok(false);
+                        }
+                    };
+                    if updated_ms >= before_ms {
+                        return Ok(false);
+                    }
+                    history.remove(&key)?;
+                    updated.remove(&key)?;
+                    Ok(true)
+                })
+                .map_err(|e| anyhow!("Failed to prune history: {}", e))?;
+            removed += expired as usize;
+        }
+        Ok(removed)
+    }
+
+    pub fn user_filters_count(&self) -> usize {
+        self.history_tree.len()
+    }
+
+    // ========== Interaction Log ==========
+
+    /// 日志类 Tree 的 Key = ts_ms (BE) + 自增序号 (BE)，保证按时间顺序迭代
+    fn log_key(&self, ts_ms: u64) -> Result<[u8; 16]> {
+        let seq = self.db.generate_id().context("Failed to generate log id")?;
+        let mut key = [0u8; 16];
+        key[..8].copy_from_slice(&ts_ms.to_be_bytes());
+        key[8..].copy_from_slice(&seq.to_be_bytes());
+        Ok(key)
+    }
+
+    /// 追加一条交互事件
+    pub fn append_event(&self, event: &InteractionEvent) -> Result<()> {
+        let key = self.log_key(event.ts_ms)?;
+        let value = bincode::serialize(event).context("Failed to serialize event")?;
+        self.events_tree.insert(key, value).context("Failed to insert event")?;
         Ok(())
     }
 
+    /// 按时间顺序遍历全部交互事件
+    pub fn iter_events(&self) -> impl Iterator<Item = Result<InteractionEvent>> + '_ {
+        self.events_tree.iter().map(|result| {
+            let (_, value) = result.context("Failed to iterate events")?;
+            bincode::deserialize(&value).context("Failed to deserialize event")
+        })
+    }
+
+    pub fn events_count(&self) -> usize {
+        self.events_tree.len()
+    }
+
+    // ========== Request Log ==========
+
+    /// 追加一条请求记录
+    pub fn append_request(&self, record: &RequestRecord) -> Result<()> {
+        let key = self.log_key(record.ts_ms)?;
+        let value = bincode::serialize(record).context("Failed to serialize request")?;
+        self.requests_tree.insert(key, value).context("Failed to insert request")?;
+        Ok(())
+    }
+
+    /// 遍历 ts_ms >= since_ms 的请求记录 (按时间顺序)
+    pub fn iter_requests_since(&self, since_ms: u64) -> impl Iterator<Item = Result<RequestRecord>> + '_ {
+        self.requests_tree.range(since_ms.to_be_bytes()..).map(|result| {
+            let (_, value) = result.context("Failed to iterate requests")?;
+            bincode::deserialize(&value).context("Failed to deserialize request")
+        })
+    }
+
+    pub fn requests_count(&self) -> usize {
+        self.requests_tree.len()
+    }
+
+    /// 删除 ts_ms < before_ms 的请求记录 (日志轮转)，返回删除条数
+    pub fn prune_requests_before(&self, before_ms: u64) -> Result<usize> {
+        let mut removed = 0;
+        for result in self.requests_tree.range(..before_ms.to_be_bytes()) {
+            let (key, _) = result.context("Failed to iterate requests")?;
+            self.requests_tree.remove(key).context("Failed to remove request")?;
+            removed += 1;
+        }
+        Ok(removed)
+    }
+
+    // ========== 快照 ==========
+
+    /// 把所有 Tree 按顺序写成快照 (流式写出，服务运行中也可调用)，返回键值对数量
+    pub fn write_snapshot(&self, out: impl Write) -> Result<usize> {
+        let mut out = BufWriter::new(out);
+        out.write_all(SNAPSHOT_MAGIC)?;
+        let mut entries = 0;
+        for name in self.db.tree_names() {
+            let tree = self.db.open_tree(&name).context("Failed to open tree")?;
+            bincode::serialize_into(&mut out, &SnapshotRecord::Tree(name.to_vec()))?;
+            for result in tree.iter() {
+                let (key, value) = result.context("Failed to iterate tree")?;
+                bincode::serialize_into(&mut out, &SnapshotRecord::Entry(key.to_vec(), value.to_vec()))?;
+                entries += 1;
+            }
+        }
+        bincode::serialize_into(&mut out, &SnapshotRecord::End)?;
+        out.flush()?;
+        Ok(entries)
+    }
+
+    /// 把快照恢复到 `path` 处的数据库 (须为空库)，返回键值对数量
+    pub fn restore_snapshot(path: &str, input: impl Read) -> Result<usize> {
+        let db = sled::open(path).context("Failed to open sled database")?;
+        for name in db.tree_names() {
+            if !db.open_tree(&name).context("Failed to open tree")?.is_empty() {
+                bail!("Refusing to restore a snapshot into the non-empty database at {}", path);
+            }
+        }
+
+        let mut input = BufReader::new(input);
+        let mut magic = [0u8; 8];
+        input.read_exact(&mut magic).context("Snapshot is empty")?;
+        if &magic != SNAPSHOT_MAGIC {
+            bail!("Not a mini-recsys snapshot");
+        }
+
+        let mut tree = None;
+        let mut entries = 0;
+        loop {
+            match bincode::deserialize_from(&mut input).context("Snapshot is truncated or corrupt")? {
+                SnapshotRecord::Tree(name) => tree = Some(db.open_tree(name).context("Failed to open tree")?),
+                SnapshotRecord::Entry(key, value) => {
+                    let tree: &Tree = tree.as_ref().context("Snapshot entry appears before any tree")?;
+                    tree.insert(key, value).context("Failed to restore entry")?;
+                    entries += 1;
+                }
+                SnapshotRecord::End => break,
+            }
+        }
+        db.flush().context("Failed to flush restored database")?;
+        Ok(entries)
+    }
+
     /// 强制刷新数据到磁盘
     pub fn flush(&self) -> Result<()> {
         self.users_tree.flush().context("Failed to flush users tree")?;
         self.items_tree.flush().context("Failed to flush items tree")?;
         self.history_tree.flush().context("Failed to flush history tree")?;
+        self.history_updated_tree.flush().context("Failed to flush history timestamps tree")?;
+        self.events_tree.flush().context("Failed to flush events tree")?;
+        self.requests_tree.flush().context("Failed to flush requests tree")?;
         Ok(())
     }
 }
+
+// ============================================================================
+// 单元测试
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_db(name: &str) -> String {
+        let path = std::env::temp_dir().join(format!("mini-recsys-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&path);
+        path.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let (source_path, target_path) = (temp_db("snapshot-src"), temp_db("snapshot-dst"));
+        let source = Storage::new(&source_path).unwrap();
+        source.save_item(&Item::new(7, "Desk Lamp", vec![0.5; 4])).unwrap();
+        source.append_event(&InteractionEvent::new(1, 7, crate::model::EventKind::Click)).unwrap();
+
+        let mut snapshot = Vec::new();
+        assert_eq!(source.write_snapshot(&mut snapshot).unwrap(), 2);
+        drop(source);
+
+        assert_eq!(Storage::restore_snapshot(&target_path, snapshot.as_slice()).unwrap(), 2);
+        let target = Storage::new(&target_path).unwrap();
+        assert_eq!(target.get_item(7).unwrap().unwrap().name, "Desk Lamp");
+        assert_eq!(target.events_count(), 1);
+        drop(target);
+
+        // 不覆盖已有数据
+        assert!(Storage::restore_snapshot(&source_path, snapshot.as_slice()).is_err());
+        let bad_path = temp_db("snapshot-bad");
+        assert!(Storage::restore_snapshot(&bad_path, &b"garbage!"[..]).is_err());
+
+        for path in [source_path, target_path, bad_path] {
+            let _ = std::fs::remove_dir_all(path);
+        }
+    }
+
+    #[test]
+    fn test_item_titles_round_trip() {
+        let path = temp_db("titles");
+        let storage = Storage::new(&path).unwrap();
+        let mut item = Item::new(7, "Desk Lamp", vec![0.5; 4]);
+        item.titles.insert("zh".into(), "台灯".into());
+        storage.save_item(&item).unwrap();
+        assert_eq!(storage.get_item(7).unwrap().unwrap().title("zh"), "台灯");
+
+        // 译名清空后不再保留旧记录
+        storage.save_item(&Item::new(7, "Desk Lamp", vec![0.5; 4])).unwrap();
+        let items: Vec<Item> = storage.iter_items().collect::<Result<_>>().unwrap();
+        assert!(items[0].titles.is_empty());
+
+        drop(storage);
+        let _ = std::fs::remove_dir_all(path);
+    }
+
+    #[test]
+    fn test_popularity_overrides_item_record() {
+        let path = temp_db("popularity");
+        let storage = Storage::new(&path).unwrap();
+        storage.save_item(&Item { popularity: 0.9, ..Item::new(7, "Desk Lamp", vec![0.5; 4]) }).unwrap();
+        assert_eq!(storage.get_popularity(7).unwrap(), None);
+        assert_eq!(storage.get_item(7).unwrap().unwrap().popularity, 0.9);
+
+        let stats = ItemPopularity { impressions: 40, clicks: 4, score: 0.25 };
+        storage.save_popularity(&[(7, stats)]).unwrap();
+        assert_eq!(storage.get_popularity(7).unwrap(), Some(stats));
+        assert_eq!(storage.get_item(7).unwrap().unwrap().popularity, 0.25);
+
+        drop(storage);
+        let _ = std::fs::remove_dir_all(path);
+    }
+
+    #[test]
+    fn test_prune_user_filters() {
+        let path = temp_db("history-prune");
+        let storage = Storage::new(&path).unwrap();
+        let filter = storage.new_bloom_filter();
+        storage.save_user_filter(1, &filter).unwrap();
+        assert_eq!(storage.prune_user_filters_before(0).unwrap(), 0);
+
+        // 升级前保存的过滤器没有写入时间: 第一次运行只补上时间，之后才会过期
+        storage.history_tree.insert(Storage::u64_to_key(2), filter.get_u8_array()).unwrap();
+        assert_eq!(storage.prune_user_filters_before(now_ms() + 1).unwrap(), 1);
+        assert_eq!(storage.prune_user_filters_before(u64::MAX).unwrap(), 1);
+        assert_eq!(storage.user_filters_count(), 0);
+
+        drop(storage);
+        let _ = std::fs::remove_dir_all(path);
+    }
+}
@@ -0,0 +1,207 @@
+//! OpenTelemetry OTLP 导出 (需要 `otel` feature) - 把 tracing span 与流水线指标发往 Jaeger / Tempo 等后端
+//!
+//! - 链路: 现有的 `tracing` span (`recommend`、`search` 及各阶段 span) 经 `tracing-opentelemetry` 导出；
+//!   HTTP 请求按 W3C `traceparent` 头接入调用方的链路
+//! - 指标: `PIPELINE` 计数器以 observable counter 形式定期上报，另记录每个路由的请求耗时直方图
+//!
+//! 导出走 OTLP/gRPC，地址取 `telemetry.otlp_endpoint`，未配置时取 `OTEL_EXPORTER_OTLP_ENDPOINT`。
+//! 两者都没有时不导出。未启用 feature 时 `Telemetry` 无法构造，环境变量被忽略。
+
+#[cfg(feature = "otel")]
+pub use otlp::{trace_request, Telemetry};
+
+#[cfg(feature = "otel")]
+mod otlp {
+    use crate::config::TelemetryConfig;
+    use crate::metrics::{PipelineSnapshot, PIPELINE};
+    use anyhow::{Context, Result};
+    use axum::extract::{MatchedPath, Request};
+    use axum::http::HeaderMap;
+    use axum::middleware::Next;
+    use axum::response::Response;
+    use opentelemetry::metrics::{Histogram, Meter, MeterProvider as _};
+    use opentelemetry::propagation::Extractor;
+    use opentelemetry::trace::TracerProvider as _;
+    use opentelemetry::{global, KeyValue};
+    use opentelemetry_otlp::{MetricExporter, SpanExporter, WithExportConfig};
+    use opentelemetry_sdk::metrics::{PeriodicReader, SdkMeterProvider};
+    use opentelemetry_sdk::propagation::TraceContextPropagator;
+    use opentelemetry_sdk::trace::TracerProvider;
+    use opentelemetry_sdk::{runtime, Resource};
+    use std::sync::OnceLock;
+    use std::time::{Duration, Instant};
+    use tracing::{warn, Instrument, Subscriber};
+    use tracing_opentelemetry::OpenTelemetrySpanExt;
+    use tracing_subscriber::registry::LookupSpan;
+    use tracing_subscriber::Layer;
+
+    /// 请求耗时直方图 (启动导出后才有)
+    static REQUEST_DURATION: OnceLock<Histogram<f64>> = OnceLock::new();
+
+    pub struct Telemetry {
+        tracer_provider: TracerProvider,
+        meter_provider: SdkMeterProvider,
+    }
+
+    impl Telemetry {
+        /// 配置了导出地址时启动导出 (需要在 tokio 运行时中调用)
+        pub fn start(config: &TelemetryConfig) -> Result<Option<Self>> {
+            let Some(endpoint) = config.endpoint() else { return Ok(None) };
+            let resource = Resource::new([KeyValue::new("service.name", config.service_name.clone())]);
+
+            let spans = SpanExporter::builder().with_tonic().with_endpoint(&endpoint).build()
+                .with_context(|| format!("Invalid OTLP endpoint {}", endpoint))?;
+            let tracer_provider = TracerProvider::builder()
+                .with_batch_exporter(spans, runtime::Tokio)
+                .with_resource(resource.clone())
+                .build();
+
+            let metrics = MetricExporter::builder().with_tonic().with_endpoint(&endpoint).build()
+                .with_context(|| format!("Invalid OTLP endpoint {}", endpoint))?;
+            let reader = PeriodicReader::builder(metrics, runtime::Tokio)
+                .with_interval(Duration::from_secs(config.metrics_interval_secs))
+                .build();
+            let meter_provider = SdkMeterProvider::builder().with_reader(reader).with_resource(resource).build();
+
+            let meter = meter_provider.meter("mini-recsys");
+            register_pipeline_metrics(&meter);
+            let _ = REQUEST_DURATION.set(
+                meter.f64_histogram("http.server.request.duration")
+                    .with_unit("s")
+                    .with_description("HTTP 请求耗时")
+                    .build(),
+            );
+
+            global::set_text_map_propagator(TraceContextPropagator::new());
+            global::set_tracer_provider(tracer_provider.clone());
+            global::set_meter_provider(meter_provider.clone());
+            Ok(Some(Self { tracer_provider, meter_provider }))
+        }
+
+        /// 把 tracing span 转成 OpenTelemetry span 的 layer
+        pub fn layer<S>(&self) -> impl Layer<S>
+        where
+            S: Subscriber + for<'span> LookupSpan<'span>,
+        {
+            tracing_opentelemetry::layer().with_tracer(self.tracer_provider.tracer("mini-recsys"))
+        }
+
+        /// 导出剩余的 span 与指标 (批处理器的关闭会阻塞，放在阻塞线程中进行)
+        pub async fn shutdown(self) {
+            let result = tokio::task::spawn_blocking(move || {
+                if let Err(e) = self.tracer_provider.shutdown() {
+                    warn!(error = %e, "⚠️  Failed to flush OTLP spans");
+                }
+                if let Err(e) = self.meter_provider.shutdown() {
+                    warn!(error = %e, "⚠️  Failed to flush OTLP metrics");
+                }
+            }).await;
+            if let Err(e) = result {
+                warn!(error = %e, "⚠️  OTLP shutdown panicked");
+            }
+        }
+    }
+
+    /// 从流水线快照中读取一个计数器
+    type CounterReader = fn(&PipelineSnapshot) -> u64;
+
+    /// `PIPELINE` 计数器 (同 /admin/metrics)，每个导出周期读取一次快照
+    fn register_pipeline_metrics(meter: &Meter) {
+        let counters: &[(&'static str, CounterReader)] = &[
+            ("recsys.recommend.requests", |s| s.recommend_requests),
+            ("recsys.recommend.candidates", |s| s.recommend_candidates),
+            ("recsys.recommend.bloom_filtered", |s| s.bloom_filtered),
+            ("recsys.recommend.fallback_invocations", |s| s.fallback_invocations),
+            ("recsys.recommend.fallback_items", |s| s.fallback_items),
+            ("recsys.recommend.empty_recall", |s| s.recommend_empty_recall),
+            ("recsys.recommend.short_recall", |s| s.recommend_short_recall),
+            ("recsys.search.requests", |s| s.search_requests),
+            ("recsys.search.empty_recall", |s| s.search_empty_recall),
+            ("recsys.search.short_recall", |s| s.search_short_recall),
+            ("recsys.nan_dropped", |s| s.nan_dropped),
+            ("recsys.ann_degraded", |s| s.ann_degraded),
+        ];
+        for &(name, read) in counters {
+            meter.u64_observable_counter(name)
+                .with_callback(move |observer| observer.observe(read(&PIPELINE.snapshot()), &[]))
+                .build();
+        }
+    }
+
+    /// 从请求头读取 W3C trace context
+    struct HeaderExtractor<'a>(&'a HeaderMap);
+
+    impl Extractor for HeaderExtractor<'_> {
+        fn get(&self, key: &str) -> Option<&str> {
+            self.0.get(key).and_then(|v| v.to_str().ok())
+        }
+
+        fn keys(&self) -> Vec<&str> {
+            self.0.keys().map(|k| k.as_str()).collect()
+        }
+    }
+
+    /// axum 中间件 (`route_layer`): 为每个请求建立 server span，父 span 取自调用方的 `traceparent`，
+    /// 并记录请求耗时
+    pub async fn trace_request(request: Request, next: Next) -> Response {
+        let route = request.extensions().get::<MatchedPath>()
+            .map(|path| path.as_str().to_string())
+            .unwrap_or_default();
+        let method = request.method().to_string();
+        let parent = global::get_text_map_propagator(|propagator| propagator.extract(&HeaderExtractor(request.headers())));
+
+        let span = tracing::info_span!(
+            "http.request",
+            otel.name = %format!("{} {}", method, route),
+            otel.kind = "server",
+            http.request.method = %method,
+            http.route = %route,
+            http.response.status_code = tracing::field::Empty,
+        );
+        span.set_parent(parent);
+
+        let started = Instant::now();
+        let response = next.run(request).instrument(span.clone()).await;
+        let status = response.status().as_u16();
+        span.record("http.response.status_code", status);
+
+        if let Some(histogram) = REQUEST_DURATION.get() {
+            histogram.record(started.elapsed().as_secs_f64(), &[
+                KeyValue::new("http.route", route),
+                KeyValue::new("http.request.method", method),
+                KeyValue::new("http.response.status_code", i64::from(status)),
+            ]);
+        }
+        response
+    }
+}
+
+// ============================================================================
+// 未启用 otel feature 时的占位实现
+// ============================================================================
+
+#[cfg(not(feature = "otel"))]
+pub struct Telemetry {
+    never: std::convert::Infallible,
+}
+
+#[cfg(not(feature = "otel"))]
+impl Telemetry {
+    /// `Config::validate` 已拒绝配置了 `telemetry.otlp_endpoint` 的情况；环境变量被忽略
+    pub fn start(_config: &crate::config::TelemetryConfig) -> anyhow::Result<Option<Self>> {
+        Ok(None)
+    }
+
+    pub fn layer(&self) -> tracing_subscriber::layer::Identity {
+        match self.never {}
+    }
+
+    pub async fn shutdown(self) {
+        match self.never {}
+    }
+}
+
+#[cfg(not(feature = "otel"))]
+pub async fn trace_request(request: axum::extract::Request, next: axum::middleware::Next) -> axum::response::Response {
+    next.run(request).await
+}
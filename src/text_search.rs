@@ -2,7 +2,7 @@ use tantivy::collector::TopDocs;
 use tantivy::query::QueryParser;
 use tantivy::schema::*;
 use tantivy::{doc, Index, IndexReader, IndexWriter, ReloadPolicy};
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, MutexGuard};
 use anyhow::Result;
 use crate::model::Item;
 
@@ -52,26 +52,46 @@ impl TextSearch {
         })
     }
 
+    /// 持锁的线程 panic 后锁会中毒；IndexWriter 自身的操作是原子的，继续使用而不是让全文索引永久不可写
+    fn writer(&self) -> MutexGuard<'_, IndexWriter> {
+        self.writer.lock().unwrap_or_else(|e| {
+            tracing::warn!("⚠️  Text index writer lock was poisoned by a panic, recovering");
+            e.into_inner()
+        })
+    }
+
     pub fn index_item(&self, item: &Item) -> Result<()> {
-        let mut writer = self.writer.lock().map_err(|_| anyhow::anyhow!("Poisoned lock"))?;
+        let writer = self.writer();
         
-        let doc = doc!(
-            self.fields.id => item.id as u64,
+        let mut doc = doc!(
+            self.fields.id => item.id,
             self.fields.title => item.name.clone(),
             self.fields.category => item.category.clone()
         );
+        // 各语言的标题写入同一字段，任一语言的关键词都能命中
+        for title in item.titles.values() {
+            doc.add_text(self.fields.title, title);
+        }
         
         writer.add_document(doc)?;
         Ok(())
     }
 
+    /// 删除全部文档 (重建索引前调用，需再 commit 才生效)
+    pub fn clear(&self) -> Result<()> {
+        let writer = self.writer();
+        writer.delete_all_documents()?;
+        Ok(())
+    }
+
     pub fn commit(&self) -> Result<()> {
-        let mut writer = self.writer.lock().map_err(|_| anyhow::anyhow!("Poisoned lock"))?;
+        let mut writer = self.writer();
         writer.commit()?;
         Ok(())
     }
 
-    pub fn search(&self, query_str: &str, limit: usize) -> Result<Vec<u32>> {
+    /// 返回 (id, BM25 score)，按相关度降序
+    pub fn search(&self, query_str: &str, limit: usize) -> Result<Vec<(u64, f32)>> {
         let searcher = self.reader.searcher();
         let query_parser = QueryParser::for_index(&self.index, vec![self.fields.title]);
         
@@ -79,11 +99,11 @@ impl TextSearch {
         let top_docs = searcher.search(&query, &TopDocs::with_limit(limit))?;
         
         let mut results = Vec::new();
-        for (_score, doc_address) in top_docs {
+        for (score, doc_address) in top_docs {
             let retrieved_doc: TantivyDocument = searcher.doc(doc_address)?;
             if let Some(id_val) = retrieved_doc.get_first(self.fields.id) {
                 if let Some(id) = id_val.as_u64() {
-                    results.push(id as u32);
+                    results.push((id, score));
                 }
             }
         }
@@ -0,0 +1,172 @@
+//! 启动预热进度 - 编码与建索引期间 HTTP 服务已可访问，通过 `GET /status` 查看进度
+//!
+//! `RecSys::open_with_progress` 在各阶段更新这里的计数，并尽早发布用户列表与兜底推荐
+//! (按热门度排序的物品；编码尚未完成时按目录文件顺序)，供预热期间的 `/users` 与 `/recommend` 使用。
+
+use crate::model::{Item, User};
+use crate::service::{self, MAX_RECOMMENDATIONS};
+use serde::Serialize;
+use std::sync::atomic::{AtomicU8, AtomicUsize, Ordering};
+use std::sync::RwLock;
+use std::time::Instant;
+
+/// 预热阶段
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Phase {
+    /// 加载模型、打开数据库
+    Loading,
+    /// 编码物品向量
+    Encoding,
+    /// 构建 HNSW 索引
+    Indexing,
+    Ready,
+    Failed,
+}
+
+impl Phase {
+    const ALL: [Phase; 5] = [Phase::Loading, Phase::Encoding, Phase::Indexing, Phase::Ready, Phase::Failed];
+}
+
+/// `GET /status` 响应
+#[derive(Debug, Clone, Serialize)]
+pub struct WarmupStatus {
+    pub phase: Phase,
+    /// 当前阶段已处理 / 总数 (Loading / Ready 阶段为 0)
+    pub done: usize,
+    pub total: usize,
+    pub elapsed_ms: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// 预热进度 (线程安全，预热线程写、请求处理线程读)
+pub struct Warmup {
+    started: Instant,
+    phase: AtomicU8,
+    done: AtomicUsize,
+    total: AtomicUsize,
+    error: RwLock<Option<String>>,
+    users: RwLock<Vec<User>>,
+    fallback: RwLock<Vec<Item>>,
+}
+
+impl Default for Warmup {
+    fn default() -> Self {
+        Self {
+            started: Instant::now(),
+            phase: AtomicU8::new(Phase::Loading as u8),
+            done: AtomicUsize::new(0),
+            total: AtomicUsize::new(0),
+            error: RwLock::new(None),
+            users: RwLock::new(Vec::new()),
+            fallback: RwLock::new(Vec::new()),
+        }
+    }
+}
+
+impl Warmup {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn phase(&self) -> Phase {
+        Phase::ALL[self.phase.load(Ordering::Acquire) as usize]
+    }
+
+    /// 进入新阶段并重置计数
+    pub fn start_phase(&self, phase: Phase, total: usize) {
+        self.done.store(0, Ordering::Relaxed);
+        self.total.store(total, Ordering::Relaxed);
+        self.phase.store(phase as u8, Ordering::Release);
+    }
+
+    pub fn advance(&self) {
+        self.advance_by(1);
+    }
+
+    pub fn advance_by(&self, n: usize) {
+        self.done.fetch_add(n, Ordering::Relaxed);
+    }
+
+    /// 标记预热完成 (由调用方在 RecSys 可供请求使用之后调用)
+    pub fn finish(&self) {
+        self.start_phase(Phase::Ready, 0);
+    }
+
+    pub fn fail(&self, error: &anyhow::Error) {
+        *self.error.write().unwrap_or_else(|e| e.into_inner()) = Some(format!("{:#}", error));
+        self.start_phase(Phase::Failed, 0);
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.phase() == Phase::Ready
+    }
+
+    pub fn status(&self) -> WarmupStatus {
+        WarmupStatus {
+            phase: self.phase(),
+            done: self.done.load(Ordering::Relaxed),
+            total: self.total.load(Ordering::Relaxed),
+            elapsed_ms: service::elapsed_ms(self.started),
+            error: self.error.read().unwrap_or_else(|e| e.into_inner()).clone(),
+        }
+    }
+
+    pub fn set_users(&self, users: &[User]) {
+        *self.users.write().unwrap_or_else(|e| e.into_inner()) = users.to_vec();
+    }
+
+    pub fn users(&self) -> Vec<User> {
+        self.users.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// 发布兜底推荐: 按热门度降序取前 MAX_RECOMMENDATIONS 个 (热门度相同时保持输入顺序)
+    pub fn set_fallback<'a>(&self, items: impl IntoIterator<Item = &'a Item>) {
+        let mut popular: Vec<&Item> = items.into_iter().collect();
+        popular.sort_by(|a, b| b.popularity.total_cmp(&a.popularity));
+        let fallback = popular.into_iter()
+            .take(MAX_RECOMMENDATIONS)
+            .map(|item| Item { embedding: Vec::new(), ..item.clone() })
+            .collect();
+        *self.fallback.write().unwrap_or_else(|e| e.into_inner()) = fallback;
+    }
+
+    /// 兜底推荐 (不含向量)
+    pub fn fallback(&self) -> Vec<Item> {
+        self.fallback.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+}
+
+// ============================================================================
+// 单元测试
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_phases_and_fallback() {
+        let warmup = Warmup::new();
+        assert_eq!(warmup.phase(), Phase::Loading);
+
+        warmup.start_phase(Phase::Encoding, 3);
+        warmup.advance();
+        let status = warmup.status();
+        assert_eq!((status.phase, status.done, status.total), (Phase::Encoding, 1, 3));
+
+        let items: Vec<Item> = (1..=15)
+            .map(|id| Item { popularity: id as f32 / 100.0, ..Item::new(id, format!("item {}", id), vec![1.0]) })
+            .collect();
+        warmup.set_fallback(&items);
+        let fallback = warmup.fallback();
+        assert_eq!(fallback.len(), MAX_RECOMMENDATIONS);
+        assert_eq!(fallback[0].id, 15);
+        assert!(fallback[0].embedding.is_empty());
+
+        warmup.fail(&anyhow::anyhow!("disk full"));
+        assert_eq!(warmup.status().error.as_deref(), Some("disk full"));
+        assert!(!warmup.is_ready());
+    }
+}
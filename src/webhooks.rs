@@ -0,0 +1,268 @@
+//! 出站 Webhook - 物品新增 / 更新 / 删除与用户反馈时向配置的 URL 推送 JSON
+//!
+//! 投递在独立线程中进行，不阻塞请求；失败 (网络错误或非 2xx) 按 1s、2s、4s... 退避重试。
+//! 配置了 `secret` 时附带签名头，接收方用同一个 secret 校验:
+//!
+//! - `X-Recsys-Event`: 事件名，例如 `item.created`
+//! - `X-Recsys-Timestamp`: 发送时的 Unix 毫秒时间戳
+//! - `X-Recsys-Signature`: `sha256=<hex>`，对 `<timestamp>.<body>` 做 HMAC-SHA256
+//!
+//! 需要 `webhooks` feature；未启用时 `Webhooks` 无法构造。
+
+use crate::model::{EventKind, Item};
+use serde::Serialize;
+
+/// Webhook 事件
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", content = "data")]
+pub enum WebhookEvent {
+    #[serde(rename = "item.created")]
+    ItemCreated(ItemPayload),
+    #[serde(rename = "item.updated")]
+    ItemUpdated(ItemPayload),
+    #[serde(rename = "item.deleted")]
+    ItemDeleted { id: u64 },
+    #[serde(rename = "feedback")]
+    Feedback { uid: u64, item_id: u64, kind: EventKind },
+}
+
+/// 事件名 (配置 `webhooks.events` 时使用)
+pub const EVENT_NAMES: [&str; 4] = ["item.created", "item.updated", "item.deleted", "feedback"];
+
+impl WebhookEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            WebhookEvent::ItemCreated(_) => EVENT_NAMES[0],
+            WebhookEvent::ItemUpdated(_) => EVENT_NAMES[1],
+            WebhookEvent::ItemDeleted { .. } => EVENT_NAMES[2],
+            WebhookEvent::Feedback { .. } => EVENT_NAMES[3],
+        }
+    }
+
+    /// 物品写入事件: `existed` 为写入前目录中是否已有该 ID
+    pub fn item_upserted(item: &Item, existed: bool) -> Self {
+        let payload = ItemPayload::from(item);
+        if existed { WebhookEvent::ItemUpdated(payload) } else { WebhookEvent::ItemCreated(payload) }
+    }
+}
+
+/// 物品元数据 (不含向量)
+#[derive(Debug, Clone, Serialize)]
+pub struct ItemPayload {
+    pub id: u64,
+    pub name: String,
+    pub category: String,
+    pub image_url: String,
+    pub price: f32,
+}
+
+impl From<&Item> for ItemPayload {
+    fn from(item: &Item) -> Self {
+        Self {
+            id: item.id,
+            name: item.name.clone(),
+            category: item.category.clone(),
+            image_url: item.image_url.clone(),
+            price: item.price,
+        }
+    }
+}
+
+/// 请求体: `{"event": "...", "ts_ms": ..., "data": {...}}`
+#[cfg(any(feature = "webhooks", test))]
+#[derive(Serialize)]
+struct Envelope<'a> {
+    #[serde(flatten)]
+    event: &'a WebhookEvent,
+    ts_ms: u64,
+}
+
+#[cfg(feature = "webhooks")]
+pub use delivery::Webhooks;
+
+#[cfg(feature = "webhooks")]
+mod delivery {
+    use super::{Envelope, WebhookEvent};
+    use crate::config::WebhookConfig;
+    use crate::model::now_ms;
+    use anyhow::{bail, Context, Result};
+    use hmac::{Hmac, Mac};
+    use sha2::Sha256;
+    use std::sync::mpsc::{self, Receiver, Sender};
+    use std::sync::{Arc, Condvar, Mutex};
+    use std::time::Duration;
+    use tracing::{debug, warn};
+
+    const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+    /// Webhook 分发器 (投递队列与后台线程)
+    pub struct Webhooks {
+        hooks: Arc<Vec<WebhookConfig>>,
+        queue: Sender<(usize, String, &'static str)>,
+        /// 尚未投递完成 (含重试中) 的数量
+        pending: Arc<(Mutex<usize>, Condvar)>,
+    }
+
+    impl Webhooks {
+        pub fn new(hooks: Vec<WebhookConfig>) -> Result<Self> {
+            let hooks = Arc::new(hooks);
+            let pending = Arc::new((Mutex::new(0), Condvar::new()));
+            let (queue, rx) = mpsc::channel();
+            let (worker_hooks, worker_pending) = (Arc::clone(&hooks), Arc::clone(&pending));
+            std::thread::Builder::new()
+                .name("webhooks".into())
+                .spawn(move || deliver_loop(&worker_hooks, rx, &worker_pending))
+                .context("Failed to start webhook thread")?;
+            Ok(Self { hooks, queue, pending })
+        }
+
+        /// 把事件加入投递队列 (订阅了该事件的每个 URL 各投递一次)
+        pub fn notify(&self, event: &WebhookEvent) {
+            let body = match serde_json::to_string(&Envelope { event, ts_ms: now_ms() }) {
+                Ok(body) => body,
+                Err(e) => return warn!(error = %e, "⚠️  Failed to serialize webhook payload"),
+            };
+            for (i, hook) in self.hooks.iter().enumerate() {
+                if !hook.events.is_empty() && !hook.events.iter().any(|e| e == event.name()) {
+                    continue;
+                }
+                *lock(&self.pending.0) += 1;
+                if self.queue.send((i, body.clone(), event.name())).is_err() {
+                    *lock(&self.pending.0) -= 1;
+                    warn!("⚠️  Webhook thread is gone, event dropped");
+                }
+            }
+        }
+
+        /// 等待队列中的投递完成 (退出前调用)，返回超时时仍未完成的数量
+        pub fn flush(&self, timeout: Duration) -> usize {
+            let (count, done) = &*self.pending;
+            let guard = lock(count);
+            let (guard, _) = done.wait_timeout_while(guard, timeout, |n| *n > 0)
+                .unwrap_or_else(|e| e.into_inner());
+            *guard
+        }
+    }
+
+    fn lock(count: &Mutex<usize>) -> std::sync::MutexGuard<'_, usize> {
+        count.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn deliver_loop(hooks: &[WebhookConfig], rx: Receiver<(usize, String, &'static str)>, pending: &(Mutex<usize>, Condvar)) {
+        let client = match reqwest::blocking::Client::builder().timeout(REQUEST_TIMEOUT).build() {
+            Ok(client) => client,
+            Err(e) => return warn!(error = %e, "⚠️  Failed to create webhook HTTP client"),
+        };
+        for (i, body, event) in rx {
+            let hook = &hooks[i];
+            let mut attempt = 0;
+            loop {
+                match send(&client, hook, &body, event) {
+                    Ok(()) => {
+                        debug!(url = %hook.url, event, "Webhook delivered");
+                        break;
+                    }
+                    Err(e) if attempt < hook.retries => {
+                        debug!(url = %hook.url, event, attempt, error = %format!("{:#}", e), "Webhook failed, retrying");
+                        std::thread::sleep(Duration::from_secs(1 << attempt.min(6)));
+                        attempt += 1;
+                    }
+                    Err(e) => {
+                        warn!(url = %hook.url, event, error = %format!("{:#}", e), "⚠️  Webhook delivery failed, giving up");
+                        break;
+                    }
+                }
+            }
+            let (count, done) = pending;
+            *lock(count) -= 1;
+            done.notify_all();
+        }
+    }
+
+    fn send(client: &reqwest::blocking::Client, hook: &WebhookConfig, body: &str, event: &str) -> Result<()> {
+        let ts = now_ms().to_string();
+        let mut request = client.post(&hook.url)
+            .header("Content-Type", "application/json")
+            .header("X-Recsys-Event", event)
+            .header("X-Recsys-Timestamp", &ts);
+        if let Some(secret) = &hook.secret {
+            request = request.header("X-Recsys-Signature", signature(secret, &ts, body));
+        }
+        let status = request.body(body.to_string()).send()?.status();
+        if !status.is_success() {
+            bail!("HTTP {}", status);
+        }
+        Ok(())
+    }
+
+    /// `sha256=<hex(HMAC-SHA256(secret, "<timestamp>.<body>"))>`
+    pub(super) fn signature(secret: &str, ts: &str, body: &str) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+        mac.update(ts.as_bytes());
+        mac.update(b".");
+        mac.update(body.as_bytes());
+        let hex: String = mac.finalize().into_bytes().iter().map(|b| format!("{:02x}", b)).collect();
+        format!("sha256={}", hex)
+    }
+}
+
+// ============================================================================
+// 未启用 webhooks feature 时的占位实现
+// ============================================================================
+
+#[cfg(not(feature = "webhooks"))]
+pub struct Webhooks {
+    never: std::convert::Infallible,
+}
+
+#[cfg(not(feature = "webhooks"))]
+impl Webhooks {
+    pub fn new(_hooks: Vec<crate::config::WebhookConfig>) -> anyhow::Result<Self> {
+        anyhow::bail!("mini-recsys was built without the `webhooks` feature")
+    }
+
+    pub fn notify(&self, _event: &WebhookEvent) {
+        match self.never {}
+    }
+
+    pub fn flush(&self, _timeout: std::time::Duration) -> usize {
+        match self.never {}
+    }
+}
+
+// ============================================================================
+// 单元测试
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_payload_shape() {
+        let item = Item::new(7, "Desk Lamp", vec![1.0]);
+        let event = WebhookEvent::item_upserted(&item, false);
+        assert_eq!(event.name(), "item.created");
+
+        let body = serde_json::to_value(Envelope { event: &event, ts_ms: 1 }).unwrap();
+        assert_eq!(body["event"], "item.created");
+        assert_eq!(body["ts_ms"], 1);
+        assert_eq!(body["data"]["id"], 7);
+        assert!(body["data"].get("embedding").is_none());
+
+        let feedback = WebhookEvent::Feedback { uid: 1, item_id: 7, kind: EventKind::Click };
+        let body = serde_json::to_value(Envelope { event: &feedback, ts_ms: 1 }).unwrap();
+        assert_eq!(body["data"], serde_json::json!({ "uid": 1, "item_id": 7, "kind": "click" }));
+    }
+
+    #[cfg(feature = "webhooks")]
+    #[test]
+    fn test_signature() {
+        // 与 `printf '1700000000000.{}' | openssl dgst -sha256 -hmac secret` 一致
+        assert_eq!(
+            delivery::signature("secret", "1700000000000", "{}"),
+            "sha256=8399216d111287e3bb28e25c0f4f31dffdf831c68c9ee2b96c2f67c9b81d341b",
+        );
+        assert_ne!(delivery::signature("other", "1700000000000", "{}"), delivery::signature("secret", "1700000000000", "{}"));
+    }
+}
@@ -0,0 +1,138 @@
+//! 排序流水线 Golden 测试
+//!
+//! 固定的小型物品库 + 手写向量 + 固定热门度，断言每个场景的推荐列表与期望完全一致。
+//! 召回使用 C++ 暴力搜索 (结果确定)，排序走与线上相同的 `service::rank_candidates`。
+//! 修改排序逻辑导致这里失败时，请确认新输出符合预期后再更新期望值。
+
+use mini_recsys::ffi::recommend_recall;
+use mini_recsys::hybrid::{fuse, FusionStrategy, RankedSource, SourceLabel};
+use mini_recsys::model::{Item, CATEGORIES};
+use mini_recsys::service::{rank_candidates, RankWeights, MAX_RECOMMENDATIONS, MIN_RECOMMENDATIONS};
+use std::collections::{HashMap, HashSet};
+
+/// (id, 类别下标, 向量 [Electronics, Books, Home, Clothing], 热门度)
+const CATALOG: [(u64, usize, [f32; 4], f32); 12] = [
+    (1, 0, [0.95, 0.10, 0.05, 0.00], 0.20),
+    (2, 0, [0.90, 0.00, 0.20, 0.10], 0.90),
+    (3, 0, [0.80, 0.30, 0.00, 0.05], 0.55),
+    (4, 1, [0.10, 0.92, 0.00, 0.05], 0.35),
+    (5, 1, [0.30, 0.85, 0.10, 0.00], 0.75),
+    (6, 1, [0.00, 0.80, 0.25, 0.15], 0.10),
+    (7, 2, [0.05, 0.00, 0.97, 0.10], 0.65),
+    (8, 2, [0.25, 0.10, 0.88, 0.00], 0.45),
+    (9, 2, [0.00, 0.20, 0.83, 0.30], 0.95),
+    (10, 3, [0.00, 0.05, 0.10, 0.96], 0.30),
+    (11, 3, [0.15, 0.00, 0.20, 0.89], 0.85),
+    (12, 3, [0.05, 0.25, 0.00, 0.82], 0.05),
+];
+
+const TECH_USER: [f32; 4] = [1.0, 0.0, 0.0, 0.0];
+const READER_USER: [f32; 4] = [0.6, 0.8, 0.0, 0.0];
+const HOME_FASHION_USER: [f32; 4] = [0.0, 0.0, 0.6, 0.8];
+
+struct Fixture {
+    items: Vec<Item>,
+    item_map: HashMap<u64, usize>,
+}
+
+impl Fixture {
+    fn new() -> Self {
+        let items: Vec<Item> = CATALOG.iter()
+            .map(|&(id, cat, embedding, popularity)| Item {
+                id,
+                name: format!("{} #{}", CATEGORIES[cat], id),
+                category: CATEGORIES[cat].to_string(),
+                image_url: String::new(),
+                price: 0.0,
+                embedding: embedding.to_vec(),
+                popularity,
+                titles: Default::default(),
+            })
+            .collect();
+        let item_map = items.iter().enumerate().map(|(i, item)| (item.id, i)).collect();
+        Self { items, item_map }
+    }
+
+    fn recommend(&self, user: &[f32], seen: &[u64], recall_k: usize, weights: RankWeights) -> (Vec<u64>, usize) {
+        let seen: HashSet<u64> = seen.iter().copied().collect();
+        let output = rank_candidates(
+            recommend_recall(user, &self.items, recall_k),
+            &self.items,
+            &self.item_map,
+            |item_id| seen.contains(&item_id),
+            weights,
+            MIN_RECOMMENDATIONS,
+            MAX_RECOMMENDATIONS,
+        );
+        (output.ranked.iter().map(|r| r.item_id).collect(), output.filtered_count)
+    }
+}
+
+#[test]
+fn golden_default_weights() {
+    let fx = Fixture::new();
+    let w = RankWeights::default();
+    assert_eq!(fx.recommend(&TECH_USER, &[], 6, w), (vec![2, 3, 1, 5, 11, 8], 0));
+    assert_eq!(fx.recommend(&READER_USER, &[], 6, w), (vec![5, 3, 4, 2, 1, 6], 0));
+    assert_eq!(fx.recommend(&HOME_FASHION_USER, &[], 6, w), (vec![11, 9, 10, 7, 8, 12], 0));
+}
+
+#[test]
+fn golden_full_recall_truncates_to_limit() {
+    let fx = Fixture::new();
+    assert_eq!(
+        fx.recommend(&HOME_FASHION_USER, &[], 100, RankWeights::default()),
+        (vec![11, 9, 10, 7, 8, 12, 2, 5, 6, 3], 0)
+    );
+}
+
+#[test]
+fn golden_similarity_only() {
+    let fx = Fixture::new();
+    let w = RankWeights { sim: 1.0, popularity: 0.0 };
+    assert_eq!(fx.recommend(&TECH_USER, &[], 6, w), (vec![1, 2, 3, 5, 8, 11], 0));
+}
+
+#[test]
+fn golden_seen_filter_and_fallback() {
+    let fx = Fixture::new();
+    let w = RankWeights::default();
+    // 过滤后只剩 3 个候选，补充 2 个热门物品: 9 补入，11 已在列表中被跳过
+    assert_eq!(fx.recommend(&TECH_USER, &[1, 2, 3], 6, w), (vec![5, 11, 8, 9], 3));
+    // 过滤后剩 2 个候选，热门前 3 为 9 / 2 / 11，其中 2 已在列表中
+    assert_eq!(fx.recommend(&READER_USER, &[5, 4, 1, 3], 6, w), (vec![2, 6, 9, 11], 4));
+}
+
+#[test]
+fn golden_fusion_orders() {
+    let sources = [
+        RankedSource::new(SourceLabel::Vector, vec![(2, 0.91), (1, 0.90), (3, 0.10)]),
+        RankedSource::new(SourceLabel::Keyword, vec![(1, 12.0), (3, 11.9), (4, 1.0)]),
+    ];
+    let order = |strategy| fuse(&sources, strategy).iter().map(|r| r.id).collect::<Vec<_>>();
+
+    // RRF 只看名次: 3 在两路都出现，排在只出现一次的 2 之前
+    assert_eq!(order(FusionStrategy::Rrf), vec![1, 3, 2, 4]);
+    // 归一化后 3 在向量路的分数接近 0，被 2 反超
+    assert_eq!(order(FusionStrategy::MinMax), vec![1, 2, 3, 4]);
+    assert_eq!(order(FusionStrategy::ZScore), vec![1, 2, 3, 4]);
+}
+
+#[test]
+fn golden_nan_scores_are_dropped() {
+    let fx = Fixture::new();
+    // 零范数向量的相似度为 NaN: 该候选被丢弃并计数，其余照常排序
+    let candidates = vec![(1, 0.95), (2, f32::NAN), (3, 0.70), (4, 0.10), (5, 0.30), (7, 0.05)];
+    let output = rank_candidates(
+        candidates,
+        &fx.items,
+        &fx.item_map,
+        |_| false,
+        RankWeights::default(),
+        MIN_RECOMMENDATIONS,
+        MAX_RECOMMENDATIONS,
+    );
+    let ids: Vec<u64> = output.ranked.iter().map(|r| r.item_id).collect();
+    assert_eq!(ids, vec![1, 3, 5, 7, 4]);
+    assert_eq!(output.counts.nan_dropped, 1);
+}